@@ -1,39 +1,83 @@
 extern crate Browser;
 use Browser::{
-    csselements, cssparser, dom, htmlparser, layouttree, screencommanddisplay, screenrender,
-    styletree,
+    cssanimations, csselements, cssfonts, cssuseragent, dom, htmlparser, layouttree,
+    resourceloader, screencommanddisplay, screenrender, styletree,
 };
 
+use std::collections::HashMap;
 use std::env;
 use std::fs::File;
 use std::io::{BufReader, Read};
 
 fn main() {
-    let html = get_html();
+    let location = document_location();
+    let html = get_html(&location);
 
     let ref root_node = html[0];
 
-    let css = get_css();
+    let author_css =
+        resourceloader::document_stylesheets(&resourceloader::FileLoader, &location, root_node);
+    let user_agent_css = cssuseragent::user_agent_stylesheet();
+    let user_css = get_user_css();
+    let dark_color_scheme = env::args().skip(1).any(|arg| arg == "--dark");
+    let mut stylesheets = vec![&user_agent_css];
+    stylesheets.extend(user_css.as_ref());
+    stylesheets.extend(author_css.iter());
 
-    let styletree_root = styletree::StyleNode::new(&root_node, &css);
+    //A font file is only read once however often the page is restyled
+    let fonts = cssfonts::FontCache::default();
+    let layout = |width: f32, height: f32, timeline: &mut cssanimations::Timeline| {
+        let mut viewport = layouttree::Dimensions::default();
+        viewport.coordinates.width = width;
+        viewport.coordinates.height = height;
 
-    let mut viewport = layouttree::Dimensions::default();
-    viewport.coordinates.width = 1024.0;
-    viewport.coordinates.height = 768.0;
+        let mut device = csselements::Device::new(width, height);
+        device.dark_color_scheme = dark_color_scheme;
+        let faces = stylesheets
+            .iter()
+            .flat_map(|&stylesheet| stylesheet.font_faces_for_device(&device))
+            .collect::<Vec<&csselements::FontFace>>();
+        let load = |face| fonts.load(&resourceloader::FileLoader, face);
+        let metrics = |style: &styletree::ComputedStyle| {
+            load(cssfonts::face_for_style(style, &faces)?)?.metrics
+        };
 
-    let layouttree = layouttree::get_layout_tree(&styletree_root, viewport);
+        let mut styletree_root =
+            styletree::StyleNode::with_fonts(&root_node, &stylesheets, &device, &metrics);
+        let mut keyframes = HashMap::new();
+        for stylesheet in &stylesheets {
+            keyframes.extend(stylesheet.keyframes_for_device(&device));
+        }
+        timeline.apply(&mut styletree_root, &keyframes);
 
-    let display_coommands = screencommanddisplay::get_display_command_list(&layouttree);
+        let font = cssfonts::document_face(root_node, &styletree_root, &faces)
+            .and_then(&load)
+            .map(|font| font.data);
+
+        let layouttree = layouttree::get_layout_tree(&styletree_root, viewport);
+        (
+            screencommanddisplay::get_display_command_list(&layouttree),
+            font,
+        )
+    };
+
+    let mut timeline = cssanimations::Timeline::default();
+    let (display_coommands, font) = layout(1024.0, 768.0, &mut timeline);
     println!("Display Commands:{:?}", display_coommands);
-    screenrender::render_loop(&display_coommands);
+
+    screenrender::render_loop(&display_coommands, font, &mut timeline, layout);
 }
 
-fn get_html() -> Vec<dom::Node> {
+fn document_location() -> String {
     let mut currentpath = env::current_dir().unwrap();
     currentpath.push("websrc/index.html");
-    let mut file_reader = match File::open(&currentpath) {
+    currentpath.to_string_lossy().into_owned()
+}
+
+fn get_html(location: &str) -> Vec<dom::Node> {
+    let mut file_reader = match File::open(location) {
         Ok(f) => BufReader::new(f),
-        Err(e) => panic!("file:{},error:{}", currentpath.display(), e),
+        Err(e) => panic!("file:{},error:{}", location, e),
     };
 
     let mut html_input = String::new();
@@ -43,16 +87,15 @@ fn get_html() -> Vec<dom::Node> {
 
     nodes
 }
-fn get_css() -> csselements::Stylesheet {
-    let mut currentpath = env::current_dir().unwrap();
-    currentpath.push("websrc/index.css");
-    let mut file_reader = match File::open(&currentpath) {
-        Ok(f) => BufReader::new(f),
-        Err(e) => panic!("file:{},error:{}", currentpath.display(), e),
-    };
-    let mut css_input = String::new();
-    file_reader.read_to_string(&mut css_input).unwrap();
-
-    let css_stylesheet = cssparser::cssparser::new(&css_input).parse_stylesheet();
-    css_stylesheet
+//The user stylesheet is optional,its path is the first command line argument that is not
+//a flag.--dark makes prefers-color-scheme: dark match.
+fn get_user_css() -> Option<csselements::Stylesheet> {
+    let location = env::args().skip(1).find(|arg| !arg.starts_with("--"))?;
+    match resourceloader::load_stylesheet(&resourceloader::FileLoader, &location) {
+        Some(mut stylesheet) => {
+            stylesheet.origin = csselements::Origin::User;
+            Some(stylesheet)
+        }
+        None => panic!("file:{},error:could not be loaded", location),
+    }
 }
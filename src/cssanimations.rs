@@ -0,0 +1,1086 @@
+//@keyframes animations and transitions.The timeline keeps the running animations of every element
+//and overrides the animated values in the style tree each time it is applied.
+use crate::csselements::{
+    Animation, AnimationDirection, Color, FillMode, KeyframesRule, ListSeparator, MathExpression,
+    StepPosition, TimingFunction, Transition, Unit, Value,
+};
+use crate::cssparser::parse_property_value;
+use crate::cssproperties::{self, Grammar, CSS_WIDE_KEYWORDS};
+use crate::cssvalues::{each_item, is_ident_in};
+use crate::styletree::StyleNode;
+
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+use std::time::Instant;
+
+const EASE: TimingFunction = TimingFunction::CubicBezier(0.25, 0.1, 0.25, 1.0);
+
+//Source of the current time in seconds
+pub trait Clock {
+    fn now(&self) -> f64;
+}
+
+//Seconds since the clock was created
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> SystemClock {
+        SystemClock {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> SystemClock {
+        SystemClock::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> f64 {
+        self.start.elapsed().as_secs_f64()
+    }
+}
+
+//A clock that only moves when told to,clones share the same time
+#[derive(Clone, Default)]
+pub struct ManualClock {
+    time: Rc<Cell<f64>>,
+}
+
+impl ManualClock {
+    pub fn new() -> ManualClock {
+        ManualClock::default()
+    }
+
+    pub fn set(&self, seconds: f64) {
+        self.time.set(seconds);
+    }
+
+    pub fn advance(&self, seconds: f64) {
+        self.time.set(self.time.get() + seconds);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> f64 {
+        self.time.get()
+    }
+}
+
+struct RunningAnimation {
+    animation: Animation,
+    start: f64,
+    //Time at which the animation was paused,the time spent paused is added to start on resume
+    paused_at: Option<f64>,
+}
+
+struct RunningTransition {
+    property: &'static str,
+    from: Value,
+    to: Value,
+    start: f64,
+    transition: Transition,
+}
+
+#[derive(Default)]
+struct ElementAnimations {
+    animations: Vec<RunningAnimation>,
+    transitions: Vec<RunningTransition>,
+    //Values of the animatable properties the last time the element was styled,before animations
+    previous: HashMap<&'static str, Value>,
+}
+
+//Elements are identified by the indices of the style nodes on the way down from the root
+type ElementPath = Vec<usize>;
+
+pub struct Timeline {
+    clock: Box<dyn Clock>,
+    now: f64,
+    elements: HashMap<ElementPath, ElementAnimations>,
+}
+
+impl Timeline {
+    pub fn new(clock: Box<dyn Clock>) -> Timeline {
+        let now = clock.now();
+        Timeline {
+            clock,
+            now,
+            elements: HashMap::new(),
+        }
+    }
+
+    //Reads the clock,the values applied afterwards are the ones for the new time
+    pub fn tick(&mut self) {
+        self.now = self.clock.now();
+    }
+
+    pub fn now(&self) -> f64 {
+        self.now
+    }
+
+    //Whether any animation or transition still changes over time,so frames have to be restyled
+    pub fn is_running(&self) -> bool {
+        let now = self.now;
+        self.elements.values().any(|element| {
+            element.transitions.iter().any(|t| {
+                now < t.start + f64::from(t.transition.delay) + f64::from(t.transition.duration)
+            }) || element.animations.iter().any(|a| {
+                let active = f64::from(a.animation.duration) * f64::from(a.animation.iterations);
+                a.paused_at.is_none() && now < a.start + f64::from(a.animation.delay) + active
+            })
+        })
+    }
+
+    //Starts,updates and stops the animations and transitions of every element of root,then
+    //replaces the animated values with their values at the current time
+    pub fn apply(&mut self, root: &mut StyleNode, keyframes: &HashMap<&str, &KeyframesRule>) {
+        let mut seen = HashSet::new();
+        self.apply_node(root, &mut Vec::new(), keyframes, &mut seen);
+        self.elements.retain(|path, _| seen.contains(path));
+    }
+
+    fn apply_node(
+        &mut self,
+        node: &mut StyleNode,
+        path: &mut ElementPath,
+        keyframes: &HashMap<&str, &KeyframesRule>,
+        seen: &mut HashSet<ElementPath>,
+    ) {
+        let now = self.now;
+        let element = self.elements.entry(path.clone()).or_default();
+        seen.insert(path.clone());
+
+        update_transitions(element, node, now);
+        update_animations(element, node, now);
+
+        for running in &element.animations {
+            let progress = match animation_progress(running, now) {
+                Some(p) => p,
+                None => continue,
+            };
+            let rule = match keyframes.get(running.animation.name.as_str()) {
+                Some(r) => *r,
+                None => continue,
+            };
+            for property in animated_properties(rule) {
+                let base = base_value(node, property);
+                if let Some(value) =
+                    keyframe_value(rule, property, base, running.animation.timing, progress)
+                {
+                    node.set_value(property, value);
+                }
+            }
+        }
+        //Transitions come after animations in the cascade
+        element.transitions.retain(|t| {
+            now < t.start + f64::from(t.transition.delay) + f64::from(t.transition.duration)
+        });
+        for running in &element.transitions {
+            node.set_value(running.property, transition_value(running, now));
+        }
+
+        for (i, child) in node.children.iter_mut().enumerate() {
+            path.push(i);
+            self.apply_node(child, path, keyframes, seen);
+            path.pop();
+        }
+    }
+}
+
+impl Default for Timeline {
+    fn default() -> Timeline {
+        Timeline::new(Box::new(SystemClock::new()))
+    }
+}
+
+//Starts a transition for every animatable property whose value changed since the last styling
+fn update_transitions(element: &mut ElementAnimations, node: &StyleNode, now: f64) {
+    let transitions = transitions(node);
+
+    for definition in cssproperties::PROPERTIES
+        .iter()
+        .filter(|p| is_animatable(p))
+    {
+        let name = definition.name;
+        let current = match base_value(node, name) {
+            Some(v) => v,
+            None => continue,
+        };
+        let previous = match element.previous.insert(name, current.clone()) {
+            Some(p) => p,
+            None => continue,
+        };
+        let running = element.transitions.iter().position(|t| t.property == name);
+        if previous == current {
+            continue;
+        }
+
+        //A transition that is interrupted starts the new one from wherever it had got to
+        let from = match running {
+            Some(i) => transition_value(&element.transitions.remove(i), now),
+            None => previous,
+        };
+        let transition = transitions
+            .iter()
+            .rev()
+            .find(|t| t.property == name || t.property == "all");
+        let transition = match transition {
+            Some(t) if t.duration + t.delay > 0.0 && t.duration > 0.0 => t.clone(),
+            _ => continue,
+        };
+        if interpolate(&from, &current, 0.5).is_none() {
+            continue;
+        }
+        element.transitions.push(RunningTransition {
+            property: name,
+            from,
+            to: current,
+            start: now,
+            transition,
+        });
+    }
+}
+
+//Animations are matched to the running ones by name,new names start now and removed ones stop
+fn update_animations(element: &mut ElementAnimations, node: &StyleNode, now: f64) {
+    let mut running = std::mem::take(&mut element.animations);
+
+    for animation in animations(node) {
+        let mut current = match running
+            .iter()
+            .position(|r| r.animation.name == animation.name)
+        {
+            Some(i) => running.remove(i),
+            None => RunningAnimation {
+                animation: animation.clone(),
+                start: now,
+                paused_at: None,
+            },
+        };
+        match (current.paused_at, animation.paused) {
+            (None, true) => current.paused_at = Some(now),
+            (Some(paused_at), false) => {
+                current.start += now - paused_at;
+                current.paused_at = None;
+            }
+            _ => {}
+        }
+        current.animation = animation;
+        element.animations.push(current);
+    }
+}
+
+//Progress through the keyframes between 0 and 1 with the direction applied,None when the
+//animation has no effect at time now
+fn animation_progress(running: &RunningAnimation, now: f64) -> Option<f32> {
+    let animation = &running.animation;
+    let elapsed = (running.paused_at.unwrap_or(now) - running.start) as f32 - animation.delay;
+    let active = animation.duration * animation.iterations;
+
+    let overall = if elapsed < 0.0 {
+        match animation.fill_mode {
+            FillMode::Backwards | FillMode::Both => 0.0,
+            _ => return None,
+        }
+    } else if elapsed >= active {
+        match animation.fill_mode {
+            FillMode::Forwards | FillMode::Both => animation.iterations,
+            _ => return None,
+        }
+    } else if animation.duration > 0.0 {
+        elapsed / animation.duration
+    } else {
+        0.0
+    };
+    if !overall.is_finite() {
+        return None;
+    }
+
+    let mut iteration = overall.floor();
+    let mut progress = overall - iteration;
+    //The end of an iteration shows its last frame rather than the first of the next one
+    if progress == 0.0 && overall > 0.0 && elapsed >= active {
+        iteration -= 1.0;
+        progress = 1.0;
+    }
+    let odd = iteration as i64 % 2 == 1;
+    let reversed = match animation.direction {
+        AnimationDirection::Normal => false,
+        AnimationDirection::Reverse => true,
+        AnimationDirection::Alternate => odd,
+        AnimationDirection::AlternateReverse => !odd,
+    };
+    Some(if reversed { 1.0 - progress } else { progress })
+}
+
+fn transition_value(running: &RunningTransition, now: f64) -> Value {
+    let transition = &running.transition;
+    let elapsed = (now - running.start) as f32 - transition.delay;
+    let progress = (elapsed / transition.duration).clamp(0.0, 1.0);
+    let eased = ease(&transition.timing, progress);
+    interpolate(&running.from, &running.to, eased).unwrap_or_else(|| running.to.clone())
+}
+
+//Properties set by any keyframe,except the animation and transition properties themselves
+fn animated_properties(rule: &KeyframesRule) -> Vec<&str> {
+    let mut properties = Vec::new();
+    for keyframe in &rule.keyframes {
+        for declaration in &keyframe.declarations {
+            let key = declaration.key.as_str();
+            if !key.starts_with("animation")
+                && !key.starts_with("transition")
+                && !properties.contains(&key)
+            {
+                properties.push(key);
+            }
+        }
+    }
+    properties
+}
+
+//Keyframes without the property,and a missing from or to,take the element's own value
+fn keyframe_value(
+    rule: &KeyframesRule,
+    property: &str,
+    base: Option<Value>,
+    timing: TimingFunction,
+    progress: f32,
+) -> Option<Value> {
+    let mut frames = Vec::new();
+    for keyframe in &rule.keyframes {
+        let value = keyframe.declarations.iter().rev().find(|d| {
+            d.key == property
+                && match d.value {
+                    Value::Var(_) => false,
+                    Value::Keyword(k) => !CSS_WIDE_KEYWORDS.contains(&k),
+                    _ => true,
+                }
+        });
+        let value = match value {
+            Some(d) => &d.value,
+            None => continue,
+        };
+        //animation-timing-function in a keyframe applies from that keyframe to the next one
+        let keyframe_timing = keyframe
+            .declarations
+            .iter()
+            .rev()
+            .find(|d| d.key == "animation-timing-function")
+            .and_then(|d| {
+                list_items(Some(&d.value))
+                    .first()
+                    .and_then(|v| parse_timing_function(v))
+            })
+            .unwrap_or(timing);
+        for &offset in &keyframe.offsets {
+            frames.push((offset, value.clone(), keyframe_timing));
+        }
+    }
+    frames.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+
+    if frames.first().map_or(true, |f| f.0 > 0.0) {
+        frames.insert(0, (0.0, base.clone()?, timing));
+    }
+    if frames.last().map_or(true, |f| f.0 < 1.0) {
+        frames.push((1.0, base?, timing));
+    }
+
+    let index = frames.iter().rposition(|f| f.0 <= progress).unwrap_or(0);
+    let (from, to) = match frames.get(index + 1) {
+        Some(to) => (&frames[index], to),
+        None if index > 0 => (&frames[index - 1], &frames[index]),
+        None => return Some(frames[index].1.clone()),
+    };
+    let local = if to.0 > from.0 {
+        ((progress - from.0) / (to.0 - from.0)).clamp(0.0, 1.0)
+    } else {
+        1.0
+    };
+    let eased = ease(&from.2, local);
+    interpolate(&from.1, &to.1, eased).or_else(|| {
+        //Values that can not be interpolated flip halfway
+        Some(if eased < 0.5 {
+            from.1.clone()
+        } else {
+            to.1.clone()
+        })
+    })
+}
+
+//Value of property before animations,its initial value when the element does not set it
+fn base_value(node: &StyleNode, property: &str) -> Option<Value> {
+    if let Some(value) = node.get_value(property) {
+        return Some(value.clone());
+    }
+    let definition = cssproperties::get_property(property)?;
+    parse_property_value(property, definition.initial)
+        .into_iter()
+        .next()
+        .map(|(_, value)| value)
+}
+
+fn is_animatable(definition: &cssproperties::PropertyDefinition) -> bool {
+    match definition.grammar {
+        Grammar::Color | Grammar::Length { .. } | Grammar::Number => true,
+        _ => definition.name == "transform",
+    }
+}
+
+//The value a fraction progress of the way from from to to,None when the two can not be interpolated
+pub fn interpolate(from: &Value, to: &Value, progress: f32) -> Option<Value> {
+    let lerp = |a: f32, b: f32| a + (b - a) * progress;
+    match (from, to) {
+        (&Value::Number(a), &Value::Number(b)) => Some(Value::Number(lerp(a, b))),
+        (&Value::Time(a), &Value::Time(b)) => Some(Value::Time(lerp(a, b))),
+        (&Value::Angle(a), &Value::Angle(b)) => Some(Value::Angle(lerp(a, b))),
+        (&Value::Length(a, ua), &Value::Length(b, ub)) if ua == ub => {
+            Some(Value::Length(lerp(a, b), ua))
+        }
+        (&Value::Length(..), &Value::Length(..))
+        | (&Value::Length(..), &Value::Math(_))
+        | (&Value::Math(_), &Value::Length(..))
+        | (&Value::Math(_), &Value::Math(_)) => {
+            let weighted = |value: &Value, weight: f32| {
+                let expression = match *value {
+                    Value::Length(n, unit) => MathExpression::Dimension(n, unit),
+                    Value::Math(ref m) => m.clone(),
+                    _ => unreachable!(),
+                };
+                MathExpression::Product(vec![expression, MathExpression::Number(weight)])
+            };
+            Some(Value::Math(MathExpression::Sum(vec![
+                weighted(from, 1.0 - progress),
+                weighted(to, progress),
+            ])))
+        }
+        (Value::Color(a), Value::Color(b)) => Some(Value::Color(mix_colors(a, b, progress))),
+        (Value::Function(a, a_arguments), Value::Function(b, b_arguments))
+            if a == b && a_arguments.len() == b_arguments.len() =>
+        {
+            let arguments = a_arguments
+                .iter()
+                .zip(b_arguments.iter())
+                .map(|(x, y)| interpolate(x, y, progress))
+                .collect::<Option<Vec<Value>>>()?;
+            Some(Value::Function(a.clone(), arguments))
+        }
+        (Value::List(a, a_separator), Value::List(b, b_separator))
+            if a_separator == b_separator && a.len() == b.len() =>
+        {
+            let values = a
+                .iter()
+                .zip(b.iter())
+                .map(|(x, y)| interpolate(x, y, progress))
+                .collect::<Option<Vec<Value>>>()?;
+            Some(Value::List(values, *a_separator))
+        }
+        //transform: none animates from or to the identity of the other transform list
+        (Value::Ident(none), other) if none == "none" => {
+            interpolate(&identity_transform(other)?, other, progress)
+        }
+        (other, Value::Ident(none)) if none == "none" => {
+            interpolate(other, &identity_transform(other)?, progress)
+        }
+        _ if from == to => Some(from.clone()),
+        _ => None,
+    }
+}
+
+//Colors are mixed in sRGB with premultiplied alpha
+fn mix_colors(from: &Color, to: &Color, progress: f32) -> Color {
+    let lerp = |a: f32, b: f32| a + (b - a) * progress;
+    let alpha = lerp(from.a, to.a);
+    let channel = |a: f32, b: f32| match alpha {
+        alpha if alpha > 0.0 => lerp(a * from.a, b * to.a) / alpha,
+        _ => lerp(a, b),
+    };
+    Color::new(
+        channel(from.r, to.r),
+        channel(from.g, to.g),
+        channel(from.b, to.b),
+        alpha,
+    )
+}
+
+fn identity_transform(value: &Value) -> Option<Value> {
+    match *value {
+        Value::List(ref functions, ListSeparator::Space) => Some(Value::List(
+            functions
+                .iter()
+                .map(identity_transform)
+                .collect::<Option<Vec<Value>>>()?,
+            ListSeparator::Space,
+        )),
+        Value::Function(ref name, ref arguments) => {
+            let identity = match name.as_str() {
+                n if n.starts_with("translate") => Value::Length(0.0, Unit::Px),
+                n if n.starts_with("scale") => Value::Number(1.0),
+                n if n.starts_with("rotate") || n.starts_with("skew") => Value::Angle(0.0),
+                _ => return None,
+            };
+            Some(Value::Function(
+                name.clone(),
+                arguments.iter().map(|_| identity.clone()).collect(),
+            ))
+        }
+        _ => None,
+    }
+}
+
+//Maps progress between 0 and 1 through timing
+pub fn ease(timing: &TimingFunction, progress: f32) -> f32 {
+    match *timing {
+        TimingFunction::Linear => progress,
+        TimingFunction::CubicBezier(x1, y1, x2, y2) => {
+            if progress <= 0.0 || progress >= 1.0 {
+                return progress;
+            }
+            let t = solve_bezier_x(x1, x2, progress);
+            bezier(y1, y2, t)
+        }
+        TimingFunction::Steps(steps, position) => {
+            let steps = steps as f32;
+            let mut step = (progress * steps).floor();
+            if position == StepPosition::JumpStart || position == StepPosition::JumpBoth {
+                step += 1.0;
+            }
+            let jumps = match position {
+                StepPosition::JumpBoth => steps + 1.0,
+                StepPosition::JumpNone => steps - 1.0,
+                _ => steps,
+            };
+            if progress >= 0.0 && step < 0.0 {
+                step = 0.0;
+            }
+            if progress <= 1.0 && step > jumps {
+                step = jumps;
+            }
+            step / jumps
+        }
+    }
+}
+
+//One coordinate of a cubic bezier from (0,0) to (1,1) with control points p1 and p2
+fn bezier(p1: f32, p2: f32, t: f32) -> f32 {
+    let u = 1.0 - t;
+    3.0 * u * u * t * p1 + 3.0 * u * t * t * p2 + t * t * t
+}
+
+//Finds t for which the x coordinate is x,with Newton's method and bisection when that stalls
+fn solve_bezier_x(x1: f32, x2: f32, x: f32) -> f32 {
+    let mut t = x;
+    for _ in 0..8 {
+        let error = bezier(x1, x2, t) - x;
+        if error.abs() < 1e-6 {
+            return t;
+        }
+        let u = 1.0 - t;
+        let slope = 3.0 * u * u * x1 + 6.0 * u * t * (x2 - x1) + 3.0 * t * t * (1.0 - x2);
+        if slope.abs() < 1e-6 {
+            break;
+        }
+        t -= error / slope;
+    }
+
+    let (mut low, mut high) = (0.0, 1.0);
+    t = x;
+    for _ in 0..50 {
+        let value = bezier(x1, x2, t);
+        if (value - x).abs() < 1e-6 {
+            break;
+        }
+        if value < x {
+            low = t;
+        } else {
+            high = t;
+        }
+        t = (low + high) / 2.0;
+    }
+    t
+}
+
+pub fn parse_timing_function(value: &Value) -> Option<TimingFunction> {
+    match *value {
+        Value::Ident(ref name) => match name.as_str() {
+            "linear" => Some(TimingFunction::Linear),
+            "ease" => Some(EASE),
+            "ease-in" => Some(TimingFunction::CubicBezier(0.42, 0.0, 1.0, 1.0)),
+            "ease-out" => Some(TimingFunction::CubicBezier(0.0, 0.0, 0.58, 1.0)),
+            "ease-in-out" => Some(TimingFunction::CubicBezier(0.42, 0.0, 0.58, 1.0)),
+            "step-start" => Some(TimingFunction::Steps(1, StepPosition::JumpStart)),
+            "step-end" => Some(TimingFunction::Steps(1, StepPosition::JumpEnd)),
+            _ => None,
+        },
+        Value::Function(ref name, ref arguments) if name == "cubic-bezier" => {
+            match arguments.as_slice() {
+                [Value::Number(x1), Value::Number(y1), Value::Number(x2), Value::Number(y2)]
+                    if (0.0..=1.0).contains(x1) && (0.0..=1.0).contains(x2) =>
+                {
+                    Some(TimingFunction::CubicBezier(*x1, *y1, *x2, *y2))
+                }
+                _ => None,
+            }
+        }
+        Value::Function(ref name, ref arguments) if name == "steps" => {
+            let (steps, position) = match arguments.as_slice() {
+                [Value::Number(n)] => (*n, StepPosition::JumpEnd),
+                [Value::Number(n), Value::Ident(p)] => (
+                    *n,
+                    match p.as_str() {
+                        "jump-start" | "start" => StepPosition::JumpStart,
+                        "jump-end" | "end" => StepPosition::JumpEnd,
+                        "jump-none" => StepPosition::JumpNone,
+                        "jump-both" => StepPosition::JumpBoth,
+                        _ => return None,
+                    },
+                ),
+                _ => return None,
+            };
+            let minimum = if position == StepPosition::JumpNone {
+                2.0
+            } else {
+                1.0
+            };
+            if steps.fract() != 0.0 || steps < minimum {
+                return None;
+            }
+            Some(TimingFunction::Steps(steps as u32, position))
+        }
+        _ => None,
+    }
+}
+
+//The entries of a comma separated list,a single value is a list of one
+fn list_items(value: Option<&Value>) -> Vec<&Value> {
+    match value {
+        Some(&Value::List(ref items, ListSeparator::Comma)) => items.iter().collect(),
+        Some(value) => vec![value],
+        None => Vec::new(),
+    }
+}
+
+//The animations named by animation-name,the other animation-* lists repeat to match its length
+pub fn animations(node: &StyleNode) -> Vec<Animation> {
+    let names = list_items(node.get_value("animation-name"));
+    let durations = list_items(node.get_value("animation-duration"));
+    let timings = list_items(node.get_value("animation-timing-function"));
+    let delays = list_items(node.get_value("animation-delay"));
+    let iterations = list_items(node.get_value("animation-iteration-count"));
+    let directions = list_items(node.get_value("animation-direction"));
+    let fill_modes = list_items(node.get_value("animation-fill-mode"));
+    let play_states = list_items(node.get_value("animation-play-state"));
+    let nth = |list: &[&Value], i: usize| -> Option<Value> {
+        match list.len() {
+            0 => None,
+            n => Some(list[i % n].clone()),
+        }
+    };
+
+    let mut result = Vec::new();
+    for (i, name) in names.iter().enumerate() {
+        let name = match **name {
+            Value::Ident(ref n) if n != "none" => n.clone(),
+            Value::String(ref n) => n.clone(),
+            _ => continue,
+        };
+        result.push(Animation {
+            name,
+            duration: match nth(&durations, i) {
+                Some(Value::Time(t)) => t,
+                _ => 0.0,
+            },
+            timing: nth(&timings, i)
+                .and_then(|t| parse_timing_function(&t))
+                .unwrap_or(EASE),
+            delay: match nth(&delays, i) {
+                Some(Value::Time(t)) => t,
+                _ => 0.0,
+            },
+            iterations: match nth(&iterations, i) {
+                Some(Value::Number(n)) => n,
+                Some(Value::Ident(ref n)) if n == "infinite" => f32::INFINITY,
+                _ => 1.0,
+            },
+            direction: match nth(&directions, i) {
+                Some(Value::Ident(ref d)) => match d.as_str() {
+                    "reverse" => AnimationDirection::Reverse,
+                    "alternate" => AnimationDirection::Alternate,
+                    "alternate-reverse" => AnimationDirection::AlternateReverse,
+                    _ => AnimationDirection::Normal,
+                },
+                _ => AnimationDirection::Normal,
+            },
+            fill_mode: match nth(&fill_modes, i) {
+                Some(Value::Ident(ref f)) => match f.as_str() {
+                    "forwards" => FillMode::Forwards,
+                    "backwards" => FillMode::Backwards,
+                    "both" => FillMode::Both,
+                    _ => FillMode::None,
+                },
+                _ => FillMode::None,
+            },
+            paused: match nth(&play_states, i) {
+                Some(Value::Ident(ref p)) => p == "paused",
+                _ => false,
+            },
+        });
+    }
+    result
+}
+
+//The transitions named by transition-property,the other transition-* lists repeat to match its length
+pub fn transitions(node: &StyleNode) -> Vec<Transition> {
+    let properties = match node.get_value("transition-property") {
+        Some(value) => list_items(Some(value)),
+        None => return Vec::new(),
+    };
+    let durations = list_items(node.get_value("transition-duration"));
+    let timings = list_items(node.get_value("transition-timing-function"));
+    let delays = list_items(node.get_value("transition-delay"));
+    let time = |list: &[&Value], i: usize| match list.len() {
+        0 => 0.0,
+        n => match *list[i % n] {
+            Value::Time(t) => t,
+            _ => 0.0,
+        },
+    };
+
+    let mut result = Vec::new();
+    for (i, property) in properties.iter().enumerate() {
+        let property = match **property {
+            Value::Ident(ref p) if p != "none" => p.clone(),
+            _ => continue,
+        };
+        result.push(Transition {
+            property,
+            duration: time(&durations, i),
+            timing: match timings.len() {
+                0 => EASE,
+                n => parse_timing_function(timings[i % n]).unwrap_or(EASE),
+            },
+            delay: time(&delays, i),
+        });
+    }
+    result
+}
+
+pub fn is_time_list(value: &Value) -> bool {
+    each_item(value, &|v| match *v {
+        Value::Time(t) => t >= 0.0,
+        _ => false,
+    })
+}
+
+//Delays may be negative,the animation then starts part way through
+pub fn is_delay_list(value: &Value) -> bool {
+    each_item(value, &|v| match *v {
+        Value::Time(_) => true,
+        _ => false,
+    })
+}
+
+pub fn is_timing_function_list(value: &Value) -> bool {
+    each_item(value, &|v| parse_timing_function(v).is_some())
+}
+
+pub fn is_iteration_count_list(value: &Value) -> bool {
+    each_item(value, &|v| match *v {
+        Value::Number(n) => n >= 0.0,
+        _ => is_ident_in(v, &["infinite"]),
+    })
+}
+
+pub fn is_direction_list(value: &Value) -> bool {
+    each_item(value, &|v| {
+        is_ident_in(v, &["normal", "reverse", "alternate", "alternate-reverse"])
+    })
+}
+
+pub fn is_fill_mode_list(value: &Value) -> bool {
+    each_item(value, &|v| {
+        is_ident_in(v, &["none", "forwards", "backwards", "both"])
+    })
+}
+
+pub fn is_play_state_list(value: &Value) -> bool {
+    each_item(value, &|v| is_ident_in(v, &["running", "paused"]))
+}
+
+pub fn is_name_list(value: &Value) -> bool {
+    each_item(value, &|v| match *v {
+        Value::Ident(ref n) => !CSS_WIDE_KEYWORDS.contains(&n.as_str()),
+        Value::String(_) => true,
+        _ => false,
+    })
+}
+
+//none is only valid on its own
+pub fn is_property_list(value: &Value) -> bool {
+    let items = list_items(Some(value));
+    items.iter().all(|v| match **v {
+        Value::Ident(ref p) => p != "none" || items.len() == 1,
+        _ => false,
+    })
+}
+
+//none,or a space separated list of transform functions
+pub fn is_transform_list(value: &Value) -> bool {
+    match *value {
+        Value::Ident(ref none) => none == "none",
+        Value::List(ref functions, ListSeparator::Space) => {
+            functions.iter().all(is_transform_function)
+        }
+        _ => is_transform_function(value),
+    }
+}
+
+fn is_transform_function(value: &Value) -> bool {
+    let (name, arguments) = match *value {
+        Value::Function(ref name, ref arguments) => (name.as_str(), arguments),
+        _ => return false,
+    };
+    let length = |v: &Value| match *v {
+        Value::Length(..) | Value::Math(_) => true,
+        Value::Number(n) => n == 0.0,
+        _ => false,
+    };
+    let number = |v: &Value| match *v {
+        Value::Number(_) => true,
+        _ => false,
+    };
+    let angle = |v: &Value| match *v {
+        Value::Angle(_) => true,
+        Value::Number(n) => n == 0.0,
+        _ => false,
+    };
+    let count = arguments.len();
+    match name {
+        "translate" => (count == 1 || count == 2) && arguments.iter().all(length),
+        "translatex" | "translatey" => count == 1 && length(&arguments[0]),
+        "scale" => (count == 1 || count == 2) && arguments.iter().all(number),
+        "scalex" | "scaley" => count == 1 && number(&arguments[0]),
+        "rotate" | "skewx" | "skewy" => count == 1 && angle(&arguments[0]),
+        "skew" => (count == 1 || count == 2) && arguments.iter().all(angle),
+        "matrix" => count == 6 && arguments.iter().all(number),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csselements::{Declaration, Device, Keyframe, Stylesheet};
+    use crate::cssparser::cssparser;
+    use crate::dom::Node;
+    use crate::htmlparser::HtmlParser;
+    use crate::layouttree::Dimensions;
+
+    fn px(n: f32) -> Value {
+        Value::Length(n, Unit::Px)
+    }
+
+    fn started(animation: Animation) -> RunningAnimation {
+        RunningAnimation {
+            animation,
+            start: 0.0,
+            paused_at: None,
+        }
+    }
+
+    fn animation(direction: AnimationDirection, fill_mode: FillMode) -> Animation {
+        Animation {
+            name: String::from("slide"),
+            duration: 2.0,
+            timing: TimingFunction::Linear,
+            delay: 1.0,
+            iterations: 2.0,
+            direction,
+            fill_mode,
+            paused: false,
+        }
+    }
+
+    fn keyframes(frames: Vec<(f32, Value)>) -> KeyframesRule {
+        KeyframesRule {
+            name: String::from("slide"),
+            keyframes: frames
+                .into_iter()
+                .map(|(offset, value)| Keyframe {
+                    offsets: vec![offset],
+                    declarations: vec![Declaration {
+                        key: String::from("width"),
+                        value,
+                        important: false,
+                    }],
+                })
+                .collect(),
+        }
+    }
+
+    //Styles the document with css at the clock's current time,the way every frame is restyled,
+    //and returns the width of its div
+    fn frame(timeline: &mut Timeline, document: &Node, css: &str) -> Option<Value> {
+        let stylesheet: Stylesheet = cssparser::new(css).parse_stylesheet();
+        let mut viewport = Dimensions::default();
+        viewport.coordinates.width = 800.0;
+        viewport.coordinates.height = 600.0;
+        let mut root = StyleNode::new(document, &[&stylesheet], viewport);
+        timeline.tick();
+        timeline.apply(
+            &mut root,
+            &stylesheet.keyframes_for_device(&Device::new(800.0, 600.0)),
+        );
+        root.children[0].get_value("width").cloned()
+    }
+
+    fn document() -> Node {
+        HtmlParser::new("<html><div></div></html>")
+            .parse_nodes()
+            .remove(0)
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-3,
+            "{} is not {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn animation_progress_without_fill() {
+        let running = started(animation(AnimationDirection::Normal, FillMode::None));
+        assert_eq!(animation_progress(&running, 0.5), None);
+        assert_eq!(animation_progress(&running, 2.0), Some(0.5));
+        assert_eq!(animation_progress(&running, 3.5), Some(0.25));
+        assert_eq!(animation_progress(&running, 5.0), None);
+    }
+
+    #[test]
+    fn animation_progress_with_direction_and_fill() {
+        let running = started(animation(AnimationDirection::Alternate, FillMode::Both));
+        assert_eq!(animation_progress(&running, 0.5), Some(0.0));
+        assert_eq!(animation_progress(&running, 2.0), Some(0.5));
+        //The second iteration runs backwards
+        assert_eq!(animation_progress(&running, 3.5), Some(0.75));
+        //It ends on the last frame of that iteration
+        assert_eq!(animation_progress(&running, 6.0), Some(0.0));
+
+        let reverse = started(animation(AnimationDirection::Reverse, FillMode::Forwards));
+        assert_eq!(animation_progress(&reverse, 2.0), Some(0.5));
+        assert_eq!(animation_progress(&reverse, 6.0), Some(0.0));
+    }
+
+    #[test]
+    fn animation_progress_stops_while_paused() {
+        let mut running = started(animation(AnimationDirection::Normal, FillMode::None));
+        running.paused_at = Some(2.0);
+        assert_eq!(animation_progress(&running, 100.0), Some(0.5));
+    }
+
+    #[test]
+    fn keyframe_value_interpolates_between_keyframes() {
+        let rule = keyframes(vec![(0.0, px(0.0)), (0.5, px(50.0)), (1.0, px(200.0))]);
+        let value =
+            |progress| keyframe_value(&rule, "width", None, TimingFunction::Linear, progress);
+        assert_eq!(value(0.0), Some(px(0.0)));
+        assert_eq!(value(0.25), Some(px(25.0)));
+        assert_eq!(value(0.75), Some(px(125.0)));
+        assert_eq!(value(1.0), Some(px(200.0)));
+    }
+
+    #[test]
+    fn keyframe_value_takes_the_base_value_for_missing_keyframes() {
+        let rule = keyframes(vec![(0.5, px(50.0))]);
+        let value =
+            |base, progress| keyframe_value(&rule, "width", base, TimingFunction::Linear, progress);
+        assert_eq!(value(Some(px(10.0)), 0.25), Some(px(30.0)));
+        assert_eq!(value(Some(px(10.0)), 0.75), Some(px(30.0)));
+        assert_eq!(value(None, 0.25), None);
+    }
+
+    #[test]
+    fn keyframe_value_flips_values_that_do_not_interpolate() {
+        let rule = keyframes(vec![
+            (0.0, Value::Ident(String::from("a"))),
+            (1.0, Value::Ident(String::from("b"))),
+        ]);
+        let value =
+            |progress| keyframe_value(&rule, "width", None, TimingFunction::Linear, progress);
+        assert_eq!(value(0.4), Some(Value::Ident(String::from("a"))));
+        assert_eq!(value(0.6), Some(Value::Ident(String::from("b"))));
+    }
+
+    #[test]
+    fn ease_cubic_bezier() {
+        assert_close(ease(&TimingFunction::Linear, 0.3), 0.3);
+        assert_close(ease(&EASE, 0.5), 0.8024);
+        let ease_in_out = TimingFunction::CubicBezier(0.42, 0.0, 0.58, 1.0);
+        assert_close(ease(&ease_in_out, 0.5), 0.5);
+        assert_close(ease(&ease_in_out, 0.25), 1.0 - ease(&ease_in_out, 0.75));
+        assert_close(ease(&EASE, 0.0), 0.0);
+        assert_close(ease(&EASE, 1.0), 1.0);
+    }
+
+    #[test]
+    fn ease_steps() {
+        let steps =
+            |count, position, progress| ease(&TimingFunction::Steps(count, position), progress);
+        assert_close(steps(4, StepPosition::JumpEnd, 0.3), 0.25);
+        assert_close(steps(4, StepPosition::JumpEnd, 0.99), 0.75);
+        assert_close(steps(4, StepPosition::JumpEnd, 1.0), 1.0);
+        assert_close(steps(4, StepPosition::JumpStart, 0.0), 0.25);
+        assert_close(steps(4, StepPosition::JumpStart, 0.3), 0.5);
+        assert_close(steps(2, StepPosition::JumpNone, 0.4), 0.0);
+        assert_close(steps(2, StepPosition::JumpNone, 0.6), 1.0);
+        assert_close(steps(3, StepPosition::JumpBoth, 0.0), 0.25);
+        assert_close(steps(3, StepPosition::JumpBoth, 0.5), 0.5);
+        assert_close(steps(3, StepPosition::JumpBoth, 1.0), 1.0);
+    }
+
+    #[test]
+    fn animations_follow_the_clock() {
+        let clock = ManualClock::new();
+        let mut timeline = Timeline::new(Box::new(clock.clone()));
+        let document = document();
+        let css = "@keyframes grow { from { width: 0px } to { width: 100px } } div { width: 10px; animation: grow 2s linear }";
+
+        assert_eq!(frame(&mut timeline, &document, css), Some(px(0.0)));
+        clock.set(0.5);
+        assert_eq!(frame(&mut timeline, &document, css), Some(px(25.0)));
+        assert!(timeline.is_running());
+        clock.set(1.5);
+        assert_eq!(frame(&mut timeline, &document, css), Some(px(75.0)));
+        clock.set(2.5);
+        assert_eq!(frame(&mut timeline, &document, css), Some(px(10.0)));
+        assert!(!timeline.is_running());
+    }
+
+    #[test]
+    fn interrupted_transition_starts_from_its_current_value() {
+        let clock = ManualClock::new();
+        let mut timeline = Timeline::new(Box::new(clock.clone()));
+        let document = document();
+        let narrow = "div { width: 0px; transition: width 1s linear }";
+        let wide = "div { width: 100px; transition: width 1s linear }";
+
+        assert_eq!(frame(&mut timeline, &document, narrow), Some(px(0.0)));
+        assert_eq!(frame(&mut timeline, &document, wide), Some(px(0.0)));
+        clock.advance(0.5);
+        assert_eq!(frame(&mut timeline, &document, wide), Some(px(50.0)));
+
+        //Going back starts from 50px rather than from 100px
+        assert_eq!(frame(&mut timeline, &document, narrow), Some(px(50.0)));
+        clock.advance(0.5);
+        assert_eq!(frame(&mut timeline, &document, narrow), Some(px(25.0)));
+        clock.advance(1.0);
+        assert_eq!(frame(&mut timeline, &document, narrow), Some(px(0.0)));
+        assert!(!timeline.is_running());
+    }
+}
@@ -0,0 +1,1007 @@
+//Parsing of CSS Color 4/5 hex and functional notations into sRGB colors.
+//Colors from wider spaces are converted through linear sRGB and gamut mapped in OKLCh.
+use crate::csselements::{match_color_by_name, Color, ColorSpace, SourceColor};
+use crate::cssvalues::split_components;
+
+type Triple = [f64; 3];
+
+//Parses any supported color,None if the value is not a valid color
+pub fn parse_color(value: &str) -> Option<Color> {
+    let value = value.trim();
+
+    if value.starts_with('#') {
+        return parse_hex(&value[1..]);
+    }
+    let open = match value.find('(') {
+        Some(i) => i,
+        None => return parse_named(value),
+    };
+    if !value.ends_with(')') {
+        return None;
+    }
+    let name = value[..open].trim();
+    let arguments = &value[open + 1..value.len() - 1];
+
+    match name {
+        "rgb" | "rgba" => parse_rgb(arguments),
+        "hsl" | "hsla" => parse_hsl(arguments),
+        "hwb" => parse_hwb(arguments),
+        "lab" => parse_lab_like(ColorSpace::Lab, arguments),
+        "lch" => parse_lab_like(ColorSpace::Lch, arguments),
+        "oklab" => parse_lab_like(ColorSpace::Oklab, arguments),
+        "oklch" => parse_lab_like(ColorSpace::Oklch, arguments),
+        "color" => parse_color_function(arguments),
+        "color-mix" => parse_color_mix(arguments),
+        _ => None,
+    }
+}
+
+fn parse_named(value: &str) -> Option<Color> {
+    let fallback = match_color_by_name("");
+    let color = match_color_by_name(value);
+    if color == fallback && value != "black" {
+        return None;
+    }
+    Some(color)
+}
+
+//#rgb,#rgba,#rrggbb and #rrggbbaa
+fn parse_hex(digits: &str) -> Option<Color> {
+    if !digits.chars().all(|c| c.is_digit(16)) {
+        return None;
+    }
+    let channel = |start: usize, len: usize| -> f32 {
+        let n = u8::from_str_radix(&digits[start..start + len], 16).unwrap_or(0);
+        if len == 1 {
+            n as f32 / 15.0
+        } else {
+            n as f32 / 255.0
+        }
+    };
+    match digits.len() {
+        3 => Some(Color::new(channel(0, 1), channel(1, 1), channel(2, 1), 1.0)),
+        4 => Some(Color::new(
+            channel(0, 1),
+            channel(1, 1),
+            channel(2, 1),
+            channel(3, 1),
+        )),
+        6 => Some(Color::new(channel(0, 2), channel(2, 2), channel(4, 2), 1.0)),
+        8 => Some(Color::new(
+            channel(0, 2),
+            channel(2, 2),
+            channel(4, 2),
+            channel(6, 2),
+        )),
+        _ => None,
+    }
+}
+
+//A numeric function argument,`none` is kept apart so each function can decide what it means
+#[derive(Clone, Copy, PartialEq)]
+enum Component {
+    Number(f32),
+    Percentage(f32),
+    Angle(f32),
+    None,
+}
+
+//Splits the arguments of a color function into its components and optional alpha.
+//Both the legacy comma syntax and the modern space syntax with `/ alpha` are accepted.
+fn split_arguments(arguments: &str) -> Option<(Vec<Component>, Option<Component>, bool)> {
+    let legacy = arguments.contains(',');
+    let mut parts: Vec<String> = if legacy {
+        if arguments.contains('/') {
+            return None;
+        }
+        arguments.split(',').map(|p| p.trim().to_string()).collect()
+    } else {
+        let mut split = arguments.splitn(2, '/');
+        let main = split.next().unwrap_or("");
+        let mut parts: Vec<String> = main.split_whitespace().map(String::from).collect();
+        match split.next() {
+            Some(alpha) => {
+                let alpha = alpha.trim();
+                if parts.len() != 3 || alpha.is_empty() || alpha.contains(char::is_whitespace) {
+                    return None;
+                }
+                parts.push(alpha.to_string());
+            }
+            None => {}
+        }
+        parts
+    };
+
+    if parts.len() != 3 && parts.len() != 4 {
+        return None;
+    }
+    let alpha = if parts.len() == 4 {
+        let alpha = parse_component(&parts.pop().unwrap())?;
+        match alpha {
+            Component::Angle(_) => return None,
+            _ => Some(alpha),
+        }
+    } else {
+        None
+    };
+    let mut components = Vec::new();
+    for part in &parts {
+        components.push(parse_component(part)?);
+    }
+    if legacy && components.iter().any(|c| *c == Component::None) {
+        return None;
+    }
+    Some((components, alpha, legacy))
+}
+
+fn parse_component(part: &str) -> Option<Component> {
+    if part == "none" {
+        return Some(Component::None);
+    }
+    if part.ends_with('%') {
+        return parse_number(&part[..part.len() - 1]).map(Component::Percentage);
+    }
+    let units = [
+        ("grad", 0.9),
+        ("deg", 1.0),
+        ("rad", 180.0 / std::f32::consts::PI),
+        ("turn", 360.0),
+    ];
+    for &(unit, to_degrees) in units.iter() {
+        if part.ends_with(unit) {
+            return parse_number(&part[..part.len() - unit.len()])
+                .map(|n| Component::Angle(n * to_degrees));
+        }
+    }
+    parse_number(part).map(Component::Number)
+}
+
+fn parse_number(s: &str) -> Option<f32> {
+    if s.is_empty() || s.contains(|c: char| c.is_alphabetic() && c != 'e' && c != 'E') {
+        return None;
+    }
+    s.parse::<f32>().ok().filter(|n| n.is_finite())
+}
+
+fn parse_alpha(alpha: Option<Component>) -> f32 {
+    match alpha {
+        Some(Component::Number(n)) => clamp(n, 0.0, 1.0),
+        Some(Component::Percentage(p)) => clamp(p / 100.0, 0.0, 1.0),
+        Some(Component::None) => 0.0,
+        _ => 1.0,
+    }
+}
+
+fn clamp(n: f32, min: f32, max: f32) -> f32 {
+    n.max(min).min(max)
+}
+
+fn parse_rgb(arguments: &str) -> Option<Color> {
+    let (components, alpha, legacy) = split_arguments(arguments)?;
+    if legacy {
+        let percentages = components
+            .iter()
+            .filter(|c| match **c {
+                Component::Percentage(_) => true,
+                _ => false,
+            })
+            .count();
+        if percentages != 0 && percentages != 3 {
+            return None;
+        }
+    }
+
+    let mut channels = [0.0; 3];
+    for (i, component) in components.iter().enumerate() {
+        channels[i] = match *component {
+            Component::Number(n) => clamp(n / 255.0, 0.0, 1.0),
+            Component::Percentage(p) => clamp(p / 100.0, 0.0, 1.0),
+            Component::None => 0.0,
+            Component::Angle(_) => return None,
+        };
+    }
+    Some(Color::new(
+        channels[0],
+        channels[1],
+        channels[2],
+        parse_alpha(alpha),
+    ))
+}
+
+fn parse_hue(component: Component) -> Option<f32> {
+    match component {
+        Component::Number(n) | Component::Angle(n) => Some(n),
+        Component::None => Some(0.0),
+        Component::Percentage(_) => None,
+    }
+}
+
+//Saturation,lightness,whiteness and blackness as a 0 to 1 fraction
+fn parse_fraction(component: Component, legacy: bool) -> Option<f32> {
+    match component {
+        Component::Percentage(p) => Some(clamp(p / 100.0, 0.0, 1.0)),
+        Component::Number(n) if !legacy => Some(clamp(n / 100.0, 0.0, 1.0)),
+        Component::None => Some(0.0),
+        _ => None,
+    }
+}
+
+fn parse_hsl(arguments: &str) -> Option<Color> {
+    let (components, alpha, legacy) = split_arguments(arguments)?;
+    let hue = parse_hue(components[0])?;
+    let saturation = parse_fraction(components[1], legacy)?;
+    let lightness = parse_fraction(components[2], legacy)?;
+
+    Some(build_color(
+        ColorSpace::Hsl,
+        [hue, saturation * 100.0, lightness * 100.0],
+        parse_alpha(alpha),
+    ))
+}
+
+fn parse_hwb(arguments: &str) -> Option<Color> {
+    let (components, alpha, legacy) = split_arguments(arguments)?;
+    if legacy {
+        return None;
+    }
+    let hue = parse_hue(components[0])?;
+    let whiteness = parse_fraction(components[1], false)?;
+    let blackness = parse_fraction(components[2], false)?;
+
+    Some(build_color(
+        ColorSpace::Hwb,
+        [hue, whiteness * 100.0, blackness * 100.0],
+        parse_alpha(alpha),
+    ))
+}
+
+pub fn hsl_to_rgb(hue: f32, saturation: f32, lightness: f32) -> (f32, f32, f32) {
+    let hue = hue.rem_euclid(360.0);
+    let f = |n: f32| {
+        let k = (n + hue / 30.0) % 12.0;
+        let a = saturation * lightness.min(1.0 - lightness);
+        lightness - a * (k - 3.0).min(9.0 - k).min(1.0).max(-1.0)
+    };
+    (f(0.0), f(8.0), f(4.0))
+}
+
+pub fn hwb_to_rgb(hue: f32, whiteness: f32, blackness: f32) -> (f32, f32, f32) {
+    if whiteness + blackness >= 1.0 {
+        let gray = whiteness / (whiteness + blackness);
+        return (gray, gray, gray);
+    }
+    let (r, g, b) = hsl_to_rgb(hue, 1.0, 0.5);
+    let scale = 1.0 - whiteness - blackness;
+    (
+        r * scale + whiteness,
+        g * scale + whiteness,
+        b * scale + whiteness,
+    )
+}
+
+//Reference ranges that 100% maps to,per component,for lab(),lch(),oklab() and oklch()
+fn percent_references(space: ColorSpace) -> [f32; 3] {
+    match space {
+        ColorSpace::Lab => [100.0, 125.0, 125.0],
+        ColorSpace::Lch => [100.0, 150.0, 0.0],
+        ColorSpace::Oklab => [1.0, 0.4, 0.4],
+        ColorSpace::Oklch => [1.0, 0.4, 0.0],
+        _ => [1.0, 1.0, 1.0],
+    }
+}
+
+fn parse_lab_like(space: ColorSpace, arguments: &str) -> Option<Color> {
+    let (components, alpha, legacy) = split_arguments(arguments)?;
+    if legacy {
+        return None;
+    }
+    let references = percent_references(space);
+    let is_polar = space == ColorSpace::Lch || space == ColorSpace::Oklch;
+
+    let mut values = [0.0; 3];
+    for (i, component) in components.iter().enumerate() {
+        values[i] = match *component {
+            Component::Number(n) => n,
+            Component::Percentage(p) if !(is_polar && i == 2) => p / 100.0 * references[i],
+            Component::Angle(a) if is_polar && i == 2 => a,
+            Component::None => 0.0,
+            _ => return None,
+        };
+    }
+    values[0] = clamp(values[0], 0.0, references[0]);
+    if is_polar {
+        values[1] = values[1].max(0.0);
+    }
+    Some(build_color(space, values, parse_alpha(alpha)))
+}
+
+//color(<space> c1 c2 c3 [/ alpha])
+fn parse_color_function(arguments: &str) -> Option<Color> {
+    let arguments = arguments.trim();
+    let split = arguments.find(char::is_whitespace)?;
+    let space = match &arguments[..split] {
+        "srgb" => ColorSpace::Srgb,
+        "srgb-linear" => ColorSpace::SrgbLinear,
+        "display-p3" => ColorSpace::DisplayP3,
+        "a98-rgb" => ColorSpace::A98Rgb,
+        "prophoto-rgb" => ColorSpace::ProphotoRgb,
+        "rec2020" => ColorSpace::Rec2020,
+        "xyz" | "xyz-d65" => ColorSpace::XyzD65,
+        "xyz-d50" => ColorSpace::XyzD50,
+        _ => return None,
+    };
+    let (components, alpha, legacy) = split_arguments(&arguments[split..])?;
+    if legacy {
+        return None;
+    }
+
+    let mut values = [0.0; 3];
+    for (i, component) in components.iter().enumerate() {
+        values[i] = match *component {
+            Component::Number(n) => n,
+            Component::Percentage(p) => p / 100.0,
+            Component::None => 0.0,
+            Component::Angle(_) => return None,
+        };
+    }
+    Some(build_color(space, values, parse_alpha(alpha)))
+}
+
+//color-mix(in <space> [<hue method> hue]?, <color> <percentage>?, <color> <percentage>?)
+fn parse_color_mix(arguments: &str) -> Option<Color> {
+    let parts = split_top_level_commas(arguments);
+    if parts.len() != 3 {
+        return None;
+    }
+
+    let method: Vec<&str> = parts[0].split_whitespace().collect();
+    if method.len() < 2 || method[0] != "in" {
+        return None;
+    }
+    let space = match method[1] {
+        "srgb" => ColorSpace::Srgb,
+        "srgb-linear" => ColorSpace::SrgbLinear,
+        "display-p3" => ColorSpace::DisplayP3,
+        "a98-rgb" => ColorSpace::A98Rgb,
+        "prophoto-rgb" => ColorSpace::ProphotoRgb,
+        "rec2020" => ColorSpace::Rec2020,
+        "xyz" | "xyz-d65" => ColorSpace::XyzD65,
+        "xyz-d50" => ColorSpace::XyzD50,
+        "lab" => ColorSpace::Lab,
+        "lch" => ColorSpace::Lch,
+        "oklab" => ColorSpace::Oklab,
+        "oklch" => ColorSpace::Oklch,
+        "hsl" => ColorSpace::Hsl,
+        "hwb" => ColorSpace::Hwb,
+        _ => return None,
+    };
+    let hue_index = hue_index(space);
+    let hue_method = match &method[2..] {
+        [] => "shorter",
+        [m, "hue"] if hue_index.is_some() => match *m {
+            "shorter" | "longer" | "increasing" | "decreasing" => *m,
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    let (first, first_percentage) = parse_mix_color(&parts[1])?;
+    let (second, second_percentage) = parse_mix_color(&parts[2])?;
+    let (p1, p2) = match (first_percentage, second_percentage) {
+        (None, None) => (0.5, 0.5),
+        (Some(p), None) => (p, 1.0 - p),
+        (None, Some(p)) => (1.0 - p, p),
+        (Some(a), Some(b)) => (a, b),
+    };
+    let sum = p1 + p2;
+    if sum <= 0.0 {
+        return None;
+    }
+    let t = (p2 / sum) as f64;
+    let alpha_multiplier = if sum < 1.0 { sum } else { 1.0 };
+
+    let mut a = from_linear_srgb(space, color_to_linear(&first));
+    let mut b = from_linear_srgb(space, color_to_linear(&second));
+    let alpha_a = first.a as f64;
+    let alpha_b = second.a as f64;
+
+    if let Some(h) = hue_index {
+        let (h1, h2) = fix_hues(a[h], b[h], hue_method);
+        a[h] = h1;
+        b[h] = h2;
+    }
+
+    let alpha = alpha_a * (1.0 - t) + alpha_b * t;
+    let mut mixed = [0.0; 3];
+    for i in 0..3 {
+        mixed[i] = if Some(i) == hue_index {
+            a[i] * (1.0 - t) + b[i] * t
+        } else {
+            let premultiplied = a[i] * alpha_a * (1.0 - t) + b[i] * alpha_b * t;
+            if alpha > 0.0 {
+                premultiplied / alpha
+            } else {
+                0.0
+            }
+        };
+    }
+    if let Some(h) = hue_index {
+        mixed[h] = mixed[h].rem_euclid(360.0);
+    }
+
+    //Only the mixed sRGB color is kept,the source of a color-mix() is not its expression
+    let mut color = build_color(
+        space,
+        [mixed[0] as f32, mixed[1] as f32, mixed[2] as f32],
+        alpha as f32 * alpha_multiplier,
+    );
+    color.source = None;
+    Some(color)
+}
+
+fn hue_index(space: ColorSpace) -> Option<usize> {
+    match space {
+        ColorSpace::Lch | ColorSpace::Oklch => Some(2),
+        ColorSpace::Hsl | ColorSpace::Hwb => Some(0),
+        _ => None,
+    }
+}
+
+//Adjusts two hues so plain linear interpolation follows the requested hue method
+fn fix_hues(h1: f64, h2: f64, method: &str) -> (f64, f64) {
+    let h1 = h1.rem_euclid(360.0);
+    let mut h2 = h2.rem_euclid(360.0);
+    let difference = h2 - h1;
+    match method {
+        "shorter" if difference > 180.0 => h2 -= 360.0,
+        "shorter" if difference < -180.0 => h2 += 360.0,
+        "longer" if difference > 0.0 && difference < 180.0 => h2 -= 360.0,
+        "longer" if difference > -180.0 && difference <= 0.0 => h2 += 360.0,
+        "increasing" if difference < 0.0 => h2 += 360.0,
+        "decreasing" if difference > 0.0 => h2 -= 360.0,
+        _ => {}
+    }
+    (h1, h2)
+}
+
+//A color-mix() argument: a color with an optional percentage before or after it
+fn parse_mix_color(part: &str) -> Option<(Color, Option<f32>)> {
+    let mut components = split_components(part);
+    let is_percentage = |c: &String| c.ends_with('%') && !c.contains('(');
+
+    let percentage = if components.first().map_or(false, is_percentage) {
+        Some(components.remove(0))
+    } else if components.last().map_or(false, is_percentage) {
+        components.pop()
+    } else {
+        None
+    };
+    let percentage = match percentage {
+        Some(p) => {
+            let p = parse_number(&p[..p.len() - 1])?;
+            if p < 0.0 || p > 100.0 {
+                return None;
+            }
+            Some(p / 100.0)
+        }
+        None => None,
+    };
+    let color = parse_color(&components.join(" "))?;
+    Some((color, percentage))
+}
+
+fn split_top_level_commas(value: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0;
+    for c in value.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(current.trim().to_string());
+                current.clear();
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    parts.push(current.trim().to_string());
+    parts
+}
+
+//Builds an sRGB color from components in space,remembering the original for serialization
+pub fn build_color(space: ColorSpace, components: [f32; 3], alpha: f32) -> Color {
+    let linear = to_linear_srgb(
+        space,
+        [
+            components[0] as f64,
+            components[1] as f64,
+            components[2] as f64,
+        ],
+    );
+    let rgb = gamut_map_srgb(linear);
+    let mut color = Color::new(rgb[0] as f32, rgb[1] as f32, rgb[2] as f32, alpha);
+    color.source = Some(SourceColor {
+        space,
+        components,
+        alpha,
+    });
+    color
+}
+
+//Unclamped linear sRGB of a parsed color,using the source space when there is one
+fn color_to_linear(color: &Color) -> Triple {
+    match color.source {
+        Some(ref source) => to_linear_srgb(
+            source.space,
+            [
+                source.components[0] as f64,
+                source.components[1] as f64,
+                source.components[2] as f64,
+            ],
+        ),
+        None => [
+            srgb_to_linear(color.r as f64),
+            srgb_to_linear(color.g as f64),
+            srgb_to_linear(color.b as f64),
+        ],
+    }
+}
+
+const LIN_SRGB_TO_XYZ: [Triple; 3] = [
+    [0.41239079926595934, 0.357584339383878, 0.1804807884018343],
+    [0.21263900587151027, 0.715168678767756, 0.07219231536073371],
+    [0.01933081871559182, 0.11919477979462598, 0.9505321522496607],
+];
+const XYZ_TO_LIN_SRGB: [Triple; 3] = [
+    [3.2409699419045226, -1.537383177570094, -0.4986107602930034],
+    [-0.9692436362808796, 1.8759675015077202, 0.04155505740717559],
+    [0.05563007969699366, -0.20397695888897652, 1.0569715142428786],
+];
+const LIN_P3_TO_XYZ: [Triple; 3] = [
+    [0.4865709486482162, 0.26566769316909306, 0.1982172852343625],
+    [0.2289745640697488, 0.6917385218365064, 0.079286914093745],
+    [0.0, 0.04511338185890264, 1.043944368900976],
+];
+const LIN_A98_TO_XYZ: [Triple; 3] = [
+    [0.5766690429101305, 0.1855582379065463, 0.1882286462349947],
+    [0.29734497525053605, 0.6273635662554661, 0.07529145849399788],
+    [0.02703136138641234, 0.07068885253582723, 0.9913375368376388],
+];
+const LIN_PROPHOTO_TO_XYZ_D50: [Triple; 3] = [
+    [0.7977604896723027, 0.13518583717574031, 0.0313493495815248],
+    [0.2880711282292934, 0.7118432178101014, 0.00008565396060525902],
+    [0.0, 0.0, 0.8251046025104601],
+];
+const LIN_REC2020_TO_XYZ: [Triple; 3] = [
+    [0.6369580483012914, 0.14461690358620832, 0.1688809751641721],
+    [0.2627002120112671, 0.6779980715188708, 0.05930171646986196],
+    [0.0, 0.028072693049087428, 1.060985057710791],
+];
+const D50_TO_D65: [Triple; 3] = [
+    [0.9554734527042182, -0.023098536874261423, 0.0632593086610217],
+    [-0.028369706963208136, 1.0099954580058226, 0.021041398966943008],
+    [0.012314001688319899, -0.020507696433477912, 1.3303659366080753],
+];
+const D65_TO_D50: [Triple; 3] = [
+    [1.0479298208405488, 0.022946793341019088, -0.05019222954313557],
+    [0.029627815688159344, 0.990434484573249, -0.01707382502938514],
+    [-0.009243058152591178, 0.015055144896577895, 0.7518742899580008],
+];
+const D50_WHITE: Triple = [0.3457 / 0.3585, 1.0, (1.0 - 0.3457 - 0.3585) / 0.3585];
+
+fn to_linear_srgb(space: ColorSpace, c: Triple) -> Triple {
+    let xyz = match space {
+        ColorSpace::Srgb => return map(c, srgb_to_linear),
+        ColorSpace::SrgbLinear => return c,
+        ColorSpace::Oklab => return oklab_to_linear_srgb(c),
+        ColorSpace::Oklch => return oklab_to_linear_srgb(polar_to_rectangular(c)),
+        ColorSpace::Hsl | ColorSpace::Hwb => {
+            let (r, g, b) = if space == ColorSpace::Hsl {
+                hsl_to_rgb(c[0] as f32, c[1] as f32 / 100.0, c[2] as f32 / 100.0)
+            } else {
+                hwb_to_rgb(c[0] as f32, c[1] as f32 / 100.0, c[2] as f32 / 100.0)
+            };
+            return map([r as f64, g as f64, b as f64], srgb_to_linear);
+        }
+        ColorSpace::DisplayP3 => multiply(&LIN_P3_TO_XYZ, map(c, srgb_to_linear)),
+        ColorSpace::A98Rgb => multiply(&LIN_A98_TO_XYZ, map(c, a98_to_linear)),
+        ColorSpace::ProphotoRgb => multiply(
+            &D50_TO_D65,
+            multiply(&LIN_PROPHOTO_TO_XYZ_D50, map(c, prophoto_to_linear)),
+        ),
+        ColorSpace::Rec2020 => multiply(&LIN_REC2020_TO_XYZ, map(c, rec2020_to_linear)),
+        ColorSpace::XyzD65 => c,
+        ColorSpace::XyzD50 => multiply(&D50_TO_D65, c),
+        ColorSpace::Lab => multiply(&D50_TO_D65, lab_to_xyz_d50(c)),
+        ColorSpace::Lch => multiply(&D50_TO_D65, lab_to_xyz_d50(polar_to_rectangular(c))),
+    };
+    multiply(&XYZ_TO_LIN_SRGB, xyz)
+}
+
+fn from_linear_srgb(space: ColorSpace, c: Triple) -> Triple {
+    let xyz = multiply(&LIN_SRGB_TO_XYZ, c);
+    match space {
+        ColorSpace::Srgb => map(c, linear_to_srgb),
+        ColorSpace::SrgbLinear => c,
+        ColorSpace::Oklab => linear_srgb_to_oklab(c),
+        ColorSpace::Oklch => rectangular_to_polar(linear_srgb_to_oklab(c)),
+        ColorSpace::Hsl => rgb_to_hsl(map(c, linear_to_srgb)),
+        ColorSpace::Hwb => rgb_to_hwb(map(c, linear_to_srgb)),
+        ColorSpace::DisplayP3 => map(multiply(&invert(&LIN_P3_TO_XYZ), xyz), linear_to_srgb),
+        ColorSpace::A98Rgb => map(multiply(&invert(&LIN_A98_TO_XYZ), xyz), linear_to_a98),
+        ColorSpace::ProphotoRgb => map(
+            multiply(
+                &invert(&LIN_PROPHOTO_TO_XYZ_D50),
+                multiply(&D65_TO_D50, xyz),
+            ),
+            linear_to_prophoto,
+        ),
+        ColorSpace::Rec2020 => map(
+            multiply(&invert(&LIN_REC2020_TO_XYZ), xyz),
+            linear_to_rec2020,
+        ),
+        ColorSpace::XyzD65 => xyz,
+        ColorSpace::XyzD50 => multiply(&D65_TO_D50, xyz),
+        ColorSpace::Lab => xyz_d50_to_lab(multiply(&D65_TO_D50, xyz)),
+        ColorSpace::Lch => rectangular_to_polar(xyz_d50_to_lab(multiply(&D65_TO_D50, xyz))),
+    }
+}
+
+//CSS Color 4 gamut mapping: reduce OKLCh chroma until clipping is no longer noticeable
+fn gamut_map_srgb(linear: Triple) -> Triple {
+    const JND: f64 = 0.02;
+    const EPSILON: f64 = 0.0001;
+
+    let rgb = map(linear, linear_to_srgb);
+    if in_gamut(rgb) {
+        return map(rgb, |v| v.max(0.0).min(1.0));
+    }
+    let mut current = rectangular_to_polar(linear_srgb_to_oklab(linear));
+    if current[0] >= 1.0 {
+        return [1.0, 1.0, 1.0];
+    }
+    if current[0] <= 0.0 {
+        return [0.0, 0.0, 0.0];
+    }
+
+    let to_srgb = |lch: Triple| {
+        map(
+            oklab_to_linear_srgb(polar_to_rectangular(lch)),
+            linear_to_srgb,
+        )
+    };
+    let clip = |rgb: Triple| map(rgb, |v| v.max(0.0).min(1.0));
+    let delta_eok = |rgb: Triple, lch: Triple| {
+        let a = linear_srgb_to_oklab(map(rgb, srgb_to_linear));
+        let b = polar_to_rectangular(lch);
+        ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt()
+    };
+
+    let mut clipped = clip(to_srgb(current));
+    if delta_eok(clipped, current) < JND {
+        return clipped;
+    }
+    let mut min = 0.0;
+    let mut max = current[1];
+    let mut min_in_gamut = true;
+    while max - min > EPSILON {
+        let chroma = (min + max) / 2.0;
+        current[1] = chroma;
+        let rgb = to_srgb(current);
+        if min_in_gamut && in_gamut(rgb) {
+            min = chroma;
+            continue;
+        }
+        clipped = clip(rgb);
+        let e = delta_eok(clipped, current);
+        if e < JND {
+            if JND - e < EPSILON {
+                return clipped;
+            }
+            min_in_gamut = false;
+            min = chroma;
+        } else {
+            max = chroma;
+        }
+    }
+    clipped
+}
+
+fn in_gamut(rgb: Triple) -> bool {
+    rgb.iter().all(|v| *v >= -0.00001 && *v <= 1.00001)
+}
+
+fn map<F: Fn(f64) -> f64>(c: Triple, f: F) -> Triple {
+    [f(c[0]), f(c[1]), f(c[2])]
+}
+
+fn multiply(m: &[Triple; 3], c: Triple) -> Triple {
+    let row = |r: &Triple| r[0] * c[0] + r[1] * c[1] + r[2] * c[2];
+    [row(&m[0]), row(&m[1]), row(&m[2])]
+}
+
+fn invert(m: &[Triple; 3]) -> [Triple; 3] {
+    let cofactor = |r1: usize, r2: usize, c1: usize, c2: usize| {
+        m[r1][c1] * m[r2][c2] - m[r1][c2] * m[r2][c1]
+    };
+    let determinant = m[0][0] * cofactor(1, 2, 1, 2) - m[0][1] * cofactor(1, 2, 0, 2)
+        + m[0][2] * cofactor(1, 2, 0, 1);
+    [
+        [
+            cofactor(1, 2, 1, 2) / determinant,
+            -cofactor(0, 2, 1, 2) / determinant,
+            cofactor(0, 1, 1, 2) / determinant,
+        ],
+        [
+            -cofactor(1, 2, 0, 2) / determinant,
+            cofactor(0, 2, 0, 2) / determinant,
+            -cofactor(0, 1, 0, 2) / determinant,
+        ],
+        [
+            cofactor(1, 2, 0, 1) / determinant,
+            -cofactor(0, 2, 0, 1) / determinant,
+            cofactor(0, 1, 0, 1) / determinant,
+        ],
+    ]
+}
+
+fn srgb_to_linear(v: f64) -> f64 {
+    if v.abs() <= 0.04045 {
+        v / 12.92
+    } else {
+        v.signum() * ((v.abs() + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(v: f64) -> f64 {
+    if v.abs() > 0.0031308 {
+        v.signum() * (1.055 * v.abs().powf(1.0 / 2.4) - 0.055)
+    } else {
+        12.92 * v
+    }
+}
+
+fn a98_to_linear(v: f64) -> f64 {
+    v.signum() * v.abs().powf(563.0 / 256.0)
+}
+
+fn linear_to_a98(v: f64) -> f64 {
+    v.signum() * v.abs().powf(256.0 / 563.0)
+}
+
+fn prophoto_to_linear(v: f64) -> f64 {
+    if v.abs() <= 16.0 / 512.0 {
+        v / 16.0
+    } else {
+        v.signum() * v.abs().powf(1.8)
+    }
+}
+
+fn linear_to_prophoto(v: f64) -> f64 {
+    if v.abs() >= 1.0 / 512.0 {
+        v.signum() * v.abs().powf(1.0 / 1.8)
+    } else {
+        16.0 * v
+    }
+}
+
+const REC2020_ALPHA: f64 = 1.09929682680944;
+const REC2020_BETA: f64 = 0.018053968510807;
+
+fn rec2020_to_linear(v: f64) -> f64 {
+    if v.abs() < REC2020_BETA * 4.5 {
+        v / 4.5
+    } else {
+        v.signum() * ((v.abs() + REC2020_ALPHA - 1.0) / REC2020_ALPHA).powf(1.0 / 0.45)
+    }
+}
+
+fn linear_to_rec2020(v: f64) -> f64 {
+    if v.abs() > REC2020_BETA {
+        v.signum() * (REC2020_ALPHA * v.abs().powf(0.45) - (REC2020_ALPHA - 1.0))
+    } else {
+        4.5 * v
+    }
+}
+
+const LAB_KAPPA: f64 = 24389.0 / 27.0;
+const LAB_EPSILON: f64 = 216.0 / 24389.0;
+
+fn lab_to_xyz_d50(lab: Triple) -> Triple {
+    let f1 = (lab[0] + 16.0) / 116.0;
+    let f0 = lab[1] / 500.0 + f1;
+    let f2 = f1 - lab[2] / 200.0;
+    let x = if f0.powi(3) > LAB_EPSILON {
+        f0.powi(3)
+    } else {
+        (116.0 * f0 - 16.0) / LAB_KAPPA
+    };
+    let y = if lab[0] > LAB_KAPPA * LAB_EPSILON {
+        f1.powi(3)
+    } else {
+        lab[0] / LAB_KAPPA
+    };
+    let z = if f2.powi(3) > LAB_EPSILON {
+        f2.powi(3)
+    } else {
+        (116.0 * f2 - 16.0) / LAB_KAPPA
+    };
+    [x * D50_WHITE[0], y * D50_WHITE[1], z * D50_WHITE[2]]
+}
+
+fn xyz_d50_to_lab(xyz: Triple) -> Triple {
+    let f = |v: f64| {
+        if v > LAB_EPSILON {
+            v.cbrt()
+        } else {
+            (LAB_KAPPA * v + 16.0) / 116.0
+        }
+    };
+    let f0 = f(xyz[0] / D50_WHITE[0]);
+    let f1 = f(xyz[1] / D50_WHITE[1]);
+    let f2 = f(xyz[2] / D50_WHITE[2]);
+    [116.0 * f1 - 16.0, 500.0 * (f0 - f1), 200.0 * (f1 - f2)]
+}
+
+fn oklab_to_linear_srgb(lab: Triple) -> Triple {
+    let l = (lab[0] + 0.3963377774 * lab[1] + 0.2158037573 * lab[2]).powi(3);
+    let m = (lab[0] - 0.1055613458 * lab[1] - 0.0638541728 * lab[2]).powi(3);
+    let s = (lab[0] - 0.0894841775 * lab[1] - 1.2914855480 * lab[2]).powi(3);
+    [
+        4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+        -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+        -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s,
+    ]
+}
+
+fn linear_srgb_to_oklab(c: Triple) -> Triple {
+    let l = (0.4122214708 * c[0] + 0.5363137843 * c[1] + 0.0514459929 * c[2]).cbrt();
+    let m = (0.2119034982 * c[0] + 0.6806995451 * c[1] + 0.1073969566 * c[2]).cbrt();
+    let s = (0.0883024619 * c[0] + 0.2817188376 * c[1] + 0.6299787005 * c[2]).cbrt();
+    [
+        0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+        1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+        0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+    ]
+}
+
+//(L,C,H) to (L,a,b),hue in degrees
+fn polar_to_rectangular(lch: Triple) -> Triple {
+    let hue = lch[2].to_radians();
+    [lch[0], lch[1] * hue.cos(), lch[1] * hue.sin()]
+}
+
+fn rectangular_to_polar(lab: Triple) -> Triple {
+    let hue = lab[2].atan2(lab[1]).to_degrees().rem_euclid(360.0);
+    [lab[0], (lab[1] * lab[1] + lab[2] * lab[2]).sqrt(), hue]
+}
+
+//Gamma encoded sRGB to (hue,saturation%,lightness%)
+fn rgb_to_hsl(rgb: Triple) -> Triple {
+    let max = rgb[0].max(rgb[1]).max(rgb[2]);
+    let min = rgb[0].min(rgb[1]).min(rgb[2]);
+    let lightness = (max + min) / 2.0;
+    let delta = max - min;
+    if delta == 0.0 {
+        return [0.0, 0.0, lightness * 100.0];
+    }
+    let saturation = if lightness == 0.0 || lightness == 1.0 {
+        0.0
+    } else {
+        (max - lightness) / lightness.min(1.0 - lightness)
+    };
+    let hue = if max == rgb[0] {
+        (rgb[1] - rgb[2]) / delta + if rgb[1] < rgb[2] { 6.0 } else { 0.0 }
+    } else if max == rgb[1] {
+        (rgb[2] - rgb[0]) / delta + 2.0
+    } else {
+        (rgb[0] - rgb[1]) / delta + 4.0
+    };
+    [hue * 60.0, saturation * 100.0, lightness * 100.0]
+}
+
+//Gamma encoded sRGB to (hue,whiteness%,blackness%)
+fn rgb_to_hwb(rgb: Triple) -> Triple {
+    let hsl = rgb_to_hsl(rgb);
+    let whiteness = rgb[0].min(rgb[1]).min(rgb[2]);
+    let blackness = 1.0 - rgb[0].max(rgb[1]).max(rgb[2]);
+    [hsl[0], whiteness * 100.0, blackness * 100.0]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //Channels of a color in 0-255,rounded
+    fn rgba(value: &str) -> Option<[u8; 4]> {
+        let c = parse_color(value)?;
+        let channel = |n: f32| (n * 255.0).round() as u8;
+        Some([channel(c.r), channel(c.g), channel(c.b), channel(c.a)])
+    }
+
+    #[test]
+    fn hex_and_named_colors() {
+        assert_eq!(rgba("#f00"), Some([255, 0, 0, 255]));
+        assert_eq!(rgba("#ff000080"), Some([255, 0, 0, 128]));
+        assert_eq!(rgba("#0f08"), Some([0, 255, 0, 136]));
+        assert_eq!(rgba("black"), Some([0, 0, 0, 255]));
+        assert_eq!(rgba("#ff00"), Some([255, 255, 0, 0]));
+        assert_eq!(rgba("#ff0000f"), None);
+        assert_eq!(rgba("#ggg"), None);
+        assert_eq!(rgba("notacolor"), None);
+    }
+
+    #[test]
+    fn rgb_syntaxes() {
+        assert_eq!(rgba("rgb(255, 0, 0)"), Some([255, 0, 0, 255]));
+        assert_eq!(rgba("rgba(255, 0, 0, 0.5)"), Some([255, 0, 0, 128]));
+        assert_eq!(rgba("rgb(255 0 0 / 50%)"), Some([255, 0, 0, 128]));
+        assert_eq!(rgba("rgb(100% 0% none)"), Some([255, 0, 0, 255]));
+        assert_eq!(rgba("rgb(300, -5, 0)"), Some([255, 0, 0, 255]));
+        assert_eq!(rgba("rgb(100%, 0, 0)"), None);
+        assert_eq!(rgba("rgb(255, 0 0)"), None);
+        assert_eq!(rgba("rgb(none, 0, 0)"), None);
+    }
+
+    #[test]
+    fn hsl_and_hwb_keep_their_space() {
+        assert_eq!(rgba("hsl(120, 100%, 50%)"), Some([0, 255, 0, 255]));
+        assert_eq!(rgba("hsl(0.5turn 100 50 / 0.5)"), Some([0, 255, 255, 128]));
+        assert_eq!(rgba("hwb(240 0% 0%)"), Some([0, 0, 255, 255]));
+        assert_eq!(rgba("hwb(0 60% 60%)"), Some([128, 128, 128, 255]));
+        assert_eq!(rgba("hwb(0, 0%, 0%)"), None);
+
+        let source = parse_color("hsl(120 100% 25%)").unwrap().source.unwrap();
+        assert_eq!(source.space, ColorSpace::Hsl);
+        assert_eq!(source.components, [120.0, 100.0, 25.0]);
+        let source = parse_color("hwb(90deg 10% 20%)").unwrap().source.unwrap();
+        assert_eq!(source.space, ColorSpace::Hwb);
+        assert_eq!(source.components, [90.0, 10.0, 20.0]);
+    }
+
+    #[test]
+    fn wide_gamut_colors_are_mapped_into_srgb() {
+        assert_eq!(rgba("lab(100 0 0)"), Some([255, 255, 255, 255]));
+        assert_eq!(rgba("oklch(0.628 0.2577 29.23)"), Some([255, 0, 0, 255]));
+        assert_eq!(rgba("color(srgb 1 0 0)"), Some([255, 0, 0, 255]));
+        let p3 = parse_color("color(display-p3 0 1 0)").unwrap();
+        assert!(p3.r >= 0.0 && p3.g <= 1.0 && p3.b >= 0.0);
+        assert_eq!(p3.source.unwrap().space, ColorSpace::DisplayP3);
+        assert_eq!(rgba("lab(50 0)"), None);
+        assert_eq!(rgba("color(unknown 1 0 0)"), None);
+    }
+
+    #[test]
+    fn color_mix_blends_in_the_given_space() {
+        assert_eq!(
+            rgba("color-mix(in srgb, red, blue)"),
+            Some([128, 0, 128, 255])
+        );
+        assert_eq!(
+            rgba("color-mix(in srgb, red 25%, blue)"),
+            Some([64, 0, 191, 255])
+        );
+        assert_eq!(
+            rgba("color-mix(in srgb, red 20%, blue 20%)"),
+            Some([128, 0, 128, 102])
+        );
+        assert_eq!(
+            rgba("color-mix(in hsl, red, lime)"),
+            Some([255, 255, 0, 255])
+        );
+        assert_eq!(
+            rgba("color-mix(in hsl longer hue, red, lime)"),
+            Some([0, 0, 255, 255])
+        );
+        assert!(parse_color("color-mix(in oklab, red, blue)")
+            .unwrap()
+            .source
+            .is_none());
+        assert_eq!(rgba("color-mix(in srgb longer hue, red, blue)"), None);
+        assert_eq!(rgba("color-mix(srgb, red, blue)"), None);
+        assert_eq!(rgba("color-mix(in srgb, red 0%, blue 0%)"), None);
+    }
+}
@@ -1,33 +1,306 @@
+use std::collections::HashMap;
 use std::fmt;
 use std::default::Default;
 
 pub struct Stylesheet{
-    pub css:Vec<Rule>,
+    pub css:Vec<CssRule>,
+    //Prefixes declared with @namespace,mapped to their namespace url
+    pub namespaces:HashMap<String,String>,
+    pub default_namespace:Option<String>,
+    pub origin:Origin,
+}
+//Cascade origin of a stylesheet.Normal declarations of a later origin win,
+//the order is reversed for declarations marked !important.
+#[derive(Clone,Copy,PartialEq,Eq,PartialOrd,Ord,Debug)]
+pub enum Origin{
+    UserAgent,
+    User,
+    Author,
+}
+//Top level or nested entry of a stylesheet,in source order
+pub enum CssRule{
+    Style(Rule),
+    Media(MediaRule),
+    Import(ImportRule),
+    FontFace(FontFace),
+    Keyframes(KeyframesRule),
+    Supports(SupportsRule),
 }
 pub struct Rule{
     pub selectors:Vec<Selector>,
     pub declarations:Vec<Declaration>,
 }
-#[derive(PartialEq,Eq)]
+//@media <queries> { <rules> }
+pub struct MediaRule{
+    pub queries:MediaQueryList,
+    pub rules:Vec<CssRule>,
+}
+//@import url(...) <queries>;,replaced by the imported rules once loaded
+pub struct ImportRule{
+    pub url:String,
+    pub queries:MediaQueryList,
+}
+//@font-face,url sources are resolved against the stylesheet's location when it is loaded
+#[derive(Debug)]
+pub struct FontFace{
+    pub family:String,
+    pub sources:Vec<FontSource>,
+    //Inclusive weight range,a single weight has equal ends
+    pub weight:(f32,f32),
+    pub style:FontStyle,
+    //Inclusive code point ranges
+    pub unicode_range:Vec<(u32,u32)>,
+}
+#[derive(Debug)]
+pub enum FontSource{
+    Local(String),
+    Url{url:String,format:Option<String>},
+}
+//Oblique holds an angle range in degrees
+#[derive(PartialEq,Clone,Copy,Debug)]
+pub enum FontStyle{
+    Normal,
+    Italic,
+    Oblique(f32,f32),
+}
+//@supports condition { rules },supported is decided once when the stylesheet is parsed
+pub struct SupportsRule{
+    pub condition:SupportsCondition,
+    pub supported:bool,
+    pub rules:Vec<CssRule>,
+}
+pub enum SupportsCondition{
+    //(property: value)
+    Declaration(String,String),
+    //selector(...)
+    Selector(String),
+    Not(Box<SupportsCondition>),
+    And(Vec<SupportsCondition>),
+    Or(Vec<SupportsCondition>),
+    //Anything else in parentheses or a function,kept as written and never supported
+    Unknown(String),
+}
+//@keyframes name { from {...} 50% {...} to {...} }
+pub struct KeyframesRule{
+    pub name:String,
+    pub keyframes:Vec<Keyframe>,
+}
+pub struct Keyframe{
+    //Positions in the animation between 0 and 1,from is 0 and to is 1
+    pub offsets:Vec<f32>,
+    pub declarations:Vec<Declaration>,
+}
+//Easing of animations and transitions,maps the input progress to the output progress
+#[derive(PartialEq,Clone,Copy,Debug)]
+pub enum TimingFunction{
+    Linear,
+    CubicBezier(f32,f32,f32,f32),
+    Steps(u32,StepPosition),
+}
+#[derive(PartialEq,Clone,Copy,Debug)]
+pub enum StepPosition{
+    JumpStart,
+    JumpEnd,
+    JumpNone,
+    JumpBoth,
+}
+#[derive(PartialEq,Clone,Copy,Debug)]
+pub enum AnimationDirection{
+    Normal,
+    Reverse,
+    Alternate,
+    AlternateReverse,
+}
+#[derive(PartialEq,Clone,Copy,Debug)]
+pub enum FillMode{
+    None,
+    Forwards,
+    Backwards,
+    Both,
+}
+//One entry of the animation-* lists of an element,times are in seconds
+#[derive(PartialEq,Clone,Debug)]
+pub struct Animation{
+    pub name:String,
+    pub duration:f32,
+    pub timing:TimingFunction,
+    pub delay:f32,
+    //f32::INFINITY for infinite
+    pub iterations:f32,
+    pub direction:AnimationDirection,
+    pub fill_mode:FillMode,
+    pub paused:bool,
+}
+//One entry of the transition-* lists of an element,property is "all" for every property
+#[derive(PartialEq,Clone,Debug)]
+pub struct Transition{
+    pub property:String,
+    pub duration:f32,
+    pub timing:TimingFunction,
+    pub delay:f32,
+}
+//Comma separated queries,the list matches when any of them does
+pub type MediaQueryList=Vec<MediaQuery>;
+pub struct MediaQuery{
+    pub negated:bool,
+    pub media_type:MediaType,
+    pub condition:Option<MediaCondition>,
+}
+#[derive(PartialEq,Clone,Debug)]
+pub enum MediaType{
+    All,
+    Screen,
+    Print,
+    Other(String),
+}
+pub enum MediaCondition{
+    Feature(MediaFeature),
+    Not(Box<MediaCondition>),
+    And(Vec<MediaCondition>),
+    Or(Vec<MediaCondition>),
+}
+//Range features are normalized to `name op value`,min-/max- prefixes included
+pub enum MediaFeature{
+    Range{name:String,comparisons:Vec<(Comparison,f32)>},
+    Discrete{name:String,value:String},
+    Boolean(String),
+}
+#[derive(PartialEq,Clone,Copy,Debug)]
+pub enum Comparison{
+    Less,
+    LessEqual,
+    Equal,
+    GreaterEqual,
+    Greater,
+}
+//What media queries are evaluated against
+#[derive(Clone,Debug)]
+pub struct Device{
+    pub width:f32,
+    pub height:f32,
+    pub media_type:MediaType,
+    pub dark_color_scheme:bool,
+}
+#[derive(PartialEq,Eq,Clone)]
 pub struct Selector{
     pub simple:Vec<SimpleSelector>,
     pub combinators:Vec<char>,
 }
-#[derive(PartialEq,Eq)]
+#[derive(PartialEq,Eq,Clone)]
 pub struct SimpleSelector{
     pub tag_name:Option<String>,
+    pub universal:bool,
+    //None matches any namespace,Some("") only elements without one
+    pub namespace:Option<String>,
     pub id :Option<String>,
     pub classes:Vec<String>, 
+    pub pseudo_classes:Vec<PseudoClass>,
+}
+//Selectors Level 4 logical pseudo-classes, each holding its argument list,
+//and the tree-structural and user-action ones
+#[derive(PartialEq,Eq,Clone)]
+pub enum PseudoClass{
+    Not(Vec<Selector>),
+    Is(Vec<Selector>),
+    Where(Vec<Selector>),
+    Has(Vec<RelativeSelector>),
+    Root,
+    Empty,
+    //:nth-child(An+B) and its variants,:first-child is :nth-child(1) and so on.
+    //of_type only counts the siblings with the same name,from_end counts from the last sibling.
+    Nth{a:i32,b:i32,of_type:bool,from_end:bool},
+    //:only-child and :only-of-type
+    Only{of_type:bool},
+    //:hover,:focus,:checked and the other states of an element that are not tracked,
+    //they never match
+    State(String),
 }
+//Argument of :has(),anchored to the element :has() is attached to
+#[derive(PartialEq,Eq,Clone)]
+pub struct RelativeSelector{
+    pub combinator:char,
+    pub selector:Selector,
+}
+//(ids,classes and pseudo-classes,type selectors)
+pub type Specificity=(usize,usize,usize);
+#[derive(PartialEq)]
 pub struct Declaration{
     pub key:String,
     pub value:Value,
+    pub important:bool,
 }
+#[derive(PartialEq,Clone)]
 pub enum Value{
     Color(Color),
     Length(f32,Unit),
     Other(String),
-
+    //Value of a --custom property,kept unparsed as written
+    Custom(String),
+    //Value containing var(),parsed once the references are substituted
+    Var(VarValue),
+    //calc(),min(),max() or clamp() that mixes units,resolved during layout
+    Math(MathExpression),
+    //Identifier from the property's grammar in the registry
+    Keyword(&'static str),
+    Display(Display),
+    Number(f32),
+    //Identifier outside of the property's known keywords,such as a font family name
+    Ident(String),
+    String(String),
+    Url(String),
+    Function(String,Vec<Value>),
+    List(Vec<Value>,ListSeparator),
+    //In seconds
+    Time(f32),
+    //In degrees
+    Angle(f32),
+}
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum ListSeparator{
+    Space,Comma
+}
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Display{
+    Block,InlineBlock,Inline,None,
+    Contents,FlowRoot,ListItem,
+    Flex,InlineFlex,Grid,InlineGrid,
+    Table,InlineTable,TableRowGroup,TableHeaderGroup,TableFooterGroup,TableRow,TableCell,
+    TableColumnGroup,TableColumn,TableCaption,
+}
+const DISPLAY_KEYWORDS:&[(&str,Display)]=&[
+    ("block",Display::Block),("inline-block",Display::InlineBlock),("inline",Display::Inline),("none",Display::None),
+    ("contents",Display::Contents),("flow-root",Display::FlowRoot),("list-item",Display::ListItem),
+    ("flex",Display::Flex),("inline-flex",Display::InlineFlex),("grid",Display::Grid),("inline-grid",Display::InlineGrid),
+    ("table",Display::Table),("inline-table",Display::InlineTable),("table-row-group",Display::TableRowGroup),
+    ("table-header-group",Display::TableHeaderGroup),("table-footer-group",Display::TableFooterGroup),
+    ("table-row",Display::TableRow),("table-cell",Display::TableCell),("table-column-group",Display::TableColumnGroup),
+    ("table-column",Display::TableColumn),("table-caption",Display::TableCaption),
+];
+//shorthand is set when the declaration was written on a shorthand and this is one of its longhands
+#[derive(PartialEq,Clone)]
+pub struct VarValue{
+    pub css:String,
+    pub shorthand:Option<String>,
+}
+//a - b is kept as Sum[a,Negate(b)] and a / b as Product[a,Invert(b)]
+#[derive(PartialEq, Clone, Debug)]
+pub enum MathExpression{
+    Number(f32),
+    Dimension(f32,Unit),
+    Sum(Vec<MathExpression>),
+    Negate(Box<MathExpression>),
+    Product(Vec<MathExpression>),
+    Invert(Box<MathExpression>),
+    Min(Vec<MathExpression>),
+    Max(Vec<MathExpression>),
+    Clamp(Box<MathExpression>,Box<MathExpression>,Box<MathExpression>),
+}
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum MathType{
+    Number,
+    Length,
+    Percentage,
+    LengthPercentage,
 }
 #[derive(PartialEq, Clone)]
 pub struct Color {
@@ -35,18 +308,102 @@ pub struct Color {
     pub g: f32,
     pub b: f32,
     pub a: f32,
+    //Color as written when it was given outside sRGB,r g b hold its gamut mapped sRGB form
+    pub source: Option<SourceColor>,
+}
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum ColorSpace {
+    Srgb,
+    SrgbLinear,
+    DisplayP3,
+    A98Rgb,
+    ProphotoRgb,
+    Rec2020,
+    XyzD50,
+    XyzD65,
+    Lab,
+    Lch,
+    Oklab,
+    Oklch,
+    Hsl,
+    Hwb,
+}
+#[derive(PartialEq, Clone, Debug)]
+pub struct SourceColor {
+    pub space: ColorSpace,
+    pub components: [f32; 3],
+    pub alpha: f32,
 }
 
 impl Stylesheet{
-    pub fn new(css:Vec<Rule>)->Stylesheet{
+    pub fn new(css:Vec<CssRule>)->Stylesheet{
         Stylesheet{
-            css
+            css,namespaces:HashMap::new(),default_namespace:None,origin:Origin::Author
+        }
+    }
+    //Style rules that apply on device,in source order with @media blocks flattened
+    pub fn rules_for_device(&self,device:&Device)->Vec<&Rule>{
+        let mut rules=Vec::new();
+        collect_rules(&self.css,device,&mut rules);
+        rules
+    }
+    pub fn font_faces_for_device(&self,device:&Device)->Vec<&FontFace>{
+        let mut faces=Vec::new();
+        collect_font_faces(&self.css,device,&mut faces);
+        faces
+    }
+    //@keyframes rules by name,a later rule replaces an earlier one with the same name
+    pub fn keyframes_for_device(&self,device:&Device)->HashMap<&str,&KeyframesRule>{
+        let mut keyframes=HashMap::new();
+        collect_keyframes(&self.css,device,&mut keyframes);
+        keyframes
+    }
+}
+fn collect_keyframes<'a>(css:&'a [CssRule],device:&Device,keyframes:&mut HashMap<&'a str,&'a KeyframesRule>){
+    for rule in css{
+        match *rule{
+            CssRule::Keyframes(ref k)=>{keyframes.insert(&k.name,k);},
+            CssRule::Media(ref m)=>if media_list_matches(&m.queries,device){
+                collect_keyframes(&m.rules,device,keyframes);
+            },
+            CssRule::Supports(ref s)=>if s.supported{
+                collect_keyframes(&s.rules,device,keyframes);
+            },
+            _=>{}
+        }
+    }
+}
+fn collect_font_faces<'a>(css:&'a [CssRule],device:&Device,faces:&mut Vec<&'a FontFace>){
+    for rule in css{
+        match *rule{
+            CssRule::FontFace(ref f)=>faces.push(f),
+            CssRule::Media(ref m)=>if media_list_matches(&m.queries,device){
+                collect_font_faces(&m.rules,device,faces);
+            },
+            CssRule::Supports(ref s)=>if s.supported{
+                collect_font_faces(&s.rules,device,faces);
+            },
+            _=>{}
+        }
+    }
+}
+fn collect_rules<'a>(css:&'a [CssRule],device:&Device,rules:&mut Vec<&'a Rule>){
+    for rule in css{
+        match *rule{
+            CssRule::Style(ref r)=>rules.push(r),
+            CssRule::Media(ref m)=>if media_list_matches(&m.queries,device){
+                collect_rules(&m.rules,device,rules);
+            },
+            CssRule::Supports(ref s)=>if s.supported{
+                collect_rules(&s.rules,device,rules);
+            },
+            CssRule::Import(_)|CssRule::FontFace(_)|CssRule::Keyframes(_)=>{}
         }
     }
 }
 impl Default for Stylesheet{
    fn default()->Self{
-       Stylesheet{css:Vec::new()}
+       Stylesheet::new(Vec::new())
    }
 }
 impl fmt::Debug for Stylesheet{
@@ -62,6 +419,209 @@ impl fmt::Debug for Stylesheet{
     }
 }
 
+impl fmt::Debug for CssRule{
+    fn fmt(&self,f:&mut fmt::Formatter)->fmt::Result{
+        match *self{
+            CssRule::Style(ref r)=>write!(f,"{:?}",r),
+            CssRule::Media(ref m)=>write!(f,"{:?}",m),
+            CssRule::Import(ref i)=>write!(f,"@import {:?} {:?};",i.url,i.queries),
+            CssRule::FontFace(ref face)=>write!(f,"@font-face {:?}",face),
+            CssRule::Keyframes(ref k)=>write!(f,"{:?}",k),
+            CssRule::Supports(ref s)=>{
+                write!(f,"@supports {:?} ({}) {{\n",s.condition,if s.supported {"supported"} else {"unsupported"})?;
+                for rule in &s.rules{
+                    write!(f,"{:?}\n",rule)?;
+                }
+                write!(f,"}}")
+            }
+        }
+    }
+}
+impl fmt::Debug for SupportsCondition{
+    fn fmt(&self,f:&mut fmt::Formatter)->fmt::Result{
+        match *self{
+            SupportsCondition::Declaration(ref name,ref value)=>write!(f,"({}: {})",name,value),
+            SupportsCondition::Selector(ref selector)=>write!(f,"selector({})",selector),
+            SupportsCondition::Not(ref c)=>write!(f,"not {:?}",c),
+            SupportsCondition::And(ref list)=>write!(f,"and{:?}",list),
+            SupportsCondition::Or(ref list)=>write!(f,"or{:?}",list),
+            SupportsCondition::Unknown(ref text)=>write!(f,"{}",text),
+        }
+    }
+}
+impl fmt::Debug for KeyframesRule{
+    fn fmt(&self,f:&mut fmt::Formatter)->fmt::Result{
+        write!(f,"@keyframes {:?} {{\n",self.name)?;
+        for keyframe in &self.keyframes{
+            let offsets=keyframe.offsets.iter().map(|o| format!("{}%",o*100.0)).collect::<Vec<String>>();
+            write!(f,"{} {:?}\n",offsets.join(","),keyframe.declarations)?;
+        }
+        write!(f,"}}")
+    }
+}
+
+impl fmt::Debug for MediaRule{
+    fn fmt(&self,f:&mut fmt::Formatter)->fmt::Result{
+        write!(f,"@media {:?} {{\n",self.queries)?;
+        for rule in &self.rules{
+            write!(f,"{:?}\n",rule)?;
+        }
+        write!(f,"}}")
+    }
+}
+
+impl fmt::Debug for MediaQuery{
+    fn fmt(&self,f:&mut fmt::Formatter)->fmt::Result{
+        if self.negated{
+            write!(f,"not ")?;
+        }
+        write!(f,"{:?}",self.media_type)?;
+        match self.condition{
+            Some(ref c)=>write!(f," and {:?}",c),
+            None=>Ok(()),
+        }
+    }
+}
+
+impl fmt::Debug for MediaCondition{
+    fn fmt(&self,f:&mut fmt::Formatter)->fmt::Result{
+        match *self{
+            MediaCondition::Feature(ref feature)=>write!(f,"{:?}",feature),
+            MediaCondition::Not(ref c)=>write!(f,"not {:?}",c),
+            MediaCondition::And(ref list)=>write!(f,"and{:?}",list),
+            MediaCondition::Or(ref list)=>write!(f,"or{:?}",list),
+        }
+    }
+}
+
+impl fmt::Debug for MediaFeature{
+    fn fmt(&self,f:&mut fmt::Formatter)->fmt::Result{
+        match *self{
+            MediaFeature::Range{ref name,ref comparisons}=>write!(f,"({} {:?})",name,comparisons),
+            MediaFeature::Discrete{ref name,ref value}=>write!(f,"({}: {})",name,value),
+            MediaFeature::Boolean(ref name)=>write!(f,"({})",name),
+        }
+    }
+}
+
+impl MediaQuery{
+    //What an invalid query becomes,it never matches
+    pub fn not_all()->MediaQuery{
+        MediaQuery{negated:true,media_type:MediaType::All,condition:None}
+    }
+    pub fn matches(&self,device:&Device)->bool{
+        let type_matches=match self.media_type{
+            MediaType::All=>true,
+            MediaType::Other(_)=>false,
+            ref t=>*t==device.media_type,
+        };
+        let result=match self.condition{
+            Some(ref c)=>if type_matches{c.evaluate(device)}else{Some(false)},
+            None=>Some(type_matches),
+        };
+        match result{
+            Some(r)=>r!=self.negated,
+            None=>false,
+        }
+    }
+}
+
+pub fn media_list_matches(queries:&[MediaQuery],device:&Device)->bool{
+    queries.is_empty()||queries.iter().any(|q| q.matches(device))
+}
+
+impl MediaCondition{
+    //Three valued,None stands for an unknown feature
+    pub fn evaluate(&self,device:&Device)->Option<bool>{
+        match *self{
+            MediaCondition::Feature(ref feature)=>feature.evaluate(device),
+            MediaCondition::Not(ref c)=>c.evaluate(device).map(|r| !r),
+            MediaCondition::And(ref list)=>{
+                let mut result=Some(true);
+                for c in list{
+                    match c.evaluate(device){
+                        Some(false)=>return Some(false),
+                        None=>result=None,
+                        _=>{}
+                    }
+                }
+                result
+            }
+            MediaCondition::Or(ref list)=>{
+                let mut result=Some(false);
+                for c in list{
+                    match c.evaluate(device){
+                        Some(true)=>return Some(true),
+                        None=>result=None,
+                        _=>{}
+                    }
+                }
+                result
+            }
+        }
+    }
+}
+
+impl MediaFeature{
+    pub fn evaluate(&self,device:&Device)->Option<bool>{
+        match *self{
+            MediaFeature::Range{ref name,ref comparisons}=>{
+                let actual=device.range_value(name)?;
+                Some(comparisons.iter().all(|&(op,value)| match op{
+                    Comparison::Less=>actual<value,
+                    Comparison::LessEqual=>actual<=value,
+                    Comparison::Equal=>actual==value,
+                    Comparison::GreaterEqual=>actual>=value,
+                    Comparison::Greater=>actual>value,
+                }))
+            }
+            MediaFeature::Discrete{ref name,ref value}=>match name.as_ref(){
+                "orientation"=>Some(value==if device.height>=device.width{"portrait"}else{"landscape"}),
+                "prefers-color-scheme"=>Some(value==if device.dark_color_scheme{"dark"}else{"light"}),
+                _=>None,
+            },
+            MediaFeature::Boolean(ref name)=>match name.as_ref(){
+                "orientation"|"prefers-color-scheme"=>Some(true),
+                _=>device.range_value(name).map(|v| v!=0.0),
+            },
+        }
+    }
+}
+
+impl Device{
+    pub fn new(width:f32,height:f32)->Device{
+        Device{width,height,media_type:MediaType::Screen,dark_color_scheme:false}
+    }
+    //Value of a range feature in the units the parser normalizes to,px for lengths
+    fn range_value(&self,name:&str)->Option<f32>{
+        match name{
+            "width"=>Some(self.width),
+            "height"=>Some(self.height),
+            "aspect-ratio"=>if self.height>0.0{Some(self.width/self.height)}else{None},
+            "resolution"=>Some(1.0),
+            "color"=>Some(8.0),
+            "monochrome"=>Some(0.0),
+            _=>None,
+        }
+    }
+}
+
+impl Display{
+    pub fn from_keyword(keyword:&str)->Option<Display>{
+        DISPLAY_KEYWORDS.iter().find(|k| k.0==keyword).map(|k| k.1)
+    }
+    pub fn keyword(self)->&'static str{
+        DISPLAY_KEYWORDS.iter().find(|k| k.1==self).map_or("inline",|k| k.0)
+    }
+    //Flex,grid and table layout are not implemented,their boxes are laid out as blocks
+    //or inline blocks depending on how they sit in their parent
+    pub fn is_inline_level(self)->bool{
+        match self{
+            Display::Inline|Display::InlineBlock|Display::InlineFlex|Display::InlineGrid|Display::InlineTable=>true,
+            _=>false
+        }
+    }
+}
 impl Rule{
     pub fn new(selectors:Vec<Selector>,declarations:Vec<Declaration>)->Rule{
         Rule{selectors,declarations}
@@ -100,6 +660,14 @@ impl Selector{
     pub fn new(simple:Vec<SimpleSelector>,combinators:Vec<char>)->Selector{
         Selector{simple,combinators}
     }
+    pub fn specificity(&self)->Specificity{
+        let mut result=(0,0,0);
+        for simple in &self.simple{
+            let s=simple.specificity();
+            result=(result.0+s.0,result.1+s.1,result.2+s.2);
+        }
+        result
+    }
 }
 impl Default for Selector{
     fn default()->Self{
@@ -110,9 +678,12 @@ impl Default for Selector{
 impl fmt::Debug for Selector{
     fn fmt(&self,f:&mut fmt::Formatter)->fmt::Result{
         let mut result=String::new();
-        for sel in &self.simple{
-            if result.len() >0{
-                result.push(',');
+        for (i,sel) in self.simple.iter().enumerate(){
+            if i>0{
+                match self.combinators.get(i-1){
+                    Some(&' ')|None=>result.push(' '),
+                    Some(c)=>result.push_str(&format!(" {} ",c)),
+                }
             }
             result.push_str(&format!("{:?}",sel));
         }   
@@ -124,18 +695,46 @@ impl fmt::Debug for Selector{
 
 impl SimpleSelector{
     pub fn new(tag_name:Option<String>,id:Option<String>,classes:Vec<String>)->SimpleSelector{
-        SimpleSelector{tag_name,id,classes}
+        SimpleSelector{tag_name,universal:false,namespace:None,id,classes,pseudo_classes:Vec::new()}
+    }
+    pub fn specificity(&self)->Specificity{
+        let mut result=(
+            if self.id.is_some(){1}else{0},
+            self.classes.len(),
+            if self.tag_name.is_some(){1}else{0},
+        );
+        for pseudo in &self.pseudo_classes{
+            let s=match *pseudo{
+                PseudoClass::Where(_)=>(0,0,0),
+                PseudoClass::Not(ref list)|PseudoClass::Is(ref list)=>max_specificity(list),
+                PseudoClass::Has(ref list)=>list.iter().map(|r| r.selector.specificity()).max().unwrap_or((0,0,0)),
+                _=>(0,1,0),
+            };
+            result=(result.0+s.0,result.1+s.1,result.2+s.2);
+        }
+        result
     }
 }
 impl Default for SimpleSelector{
     fn default()->Self{
-        SimpleSelector{tag_name:Option::None,id:Option::None,classes:Vec::new()}
+        SimpleSelector::new(Option::None,Option::None,Vec::new())
     }
 }
+//Specificity of the most specific selector in a list,as used by :is() and :not()
+pub fn max_specificity(list:&[Selector])->Specificity{
+    list.iter().map(|s| s.specificity()).max().unwrap_or((0,0,0))
+}
 
 impl fmt::Debug for SimpleSelector{
     fn fmt(&self,f:&mut fmt::Formatter)->fmt::Result{
         let mut result=String::new();
+        match self.namespace{
+            Some(ref n)=>result.push_str(&format!("{}|",n)),
+            None=>{}
+        }
+        if self.universal{
+            result.push('*');
+        }
         match self.tag_name{
             Some(ref t)=>result.push_str(t),
             None=>{}
@@ -149,26 +748,85 @@ impl fmt::Debug for SimpleSelector{
             result.push_str(class);
 
         }
+        for pseudo in &self.pseudo_classes{
+            result.push_str(&format!("{:?}",pseudo));
+        }
         write!(f,"{}",result)
     }
 }
 
+impl PseudoClass{
+    //Name of a pseudo-class without a selector argument,as written in a selector
+    pub fn name(&self)->Option<String>{
+        let name=match *self{
+            PseudoClass::Root=>String::from("root"),
+            PseudoClass::Empty=>String::from("empty"),
+            PseudoClass::Only{of_type}=>String::from(if of_type{"only-of-type"}else{"only-child"}),
+            PseudoClass::Nth{a,b,of_type,from_end}=>{
+                let prefix=if from_end{"last"}else{"first"};
+                let suffix=if of_type{"of-type"}else{"child"};
+                match (a,b){
+                    (0,1)=>format!("{}-{}",prefix,suffix),
+                    _=>format!("nth-{}{}({})",if from_end{"last-"}else{""},suffix,an_plus_b(a,b)),
+                }
+            },
+            PseudoClass::State(ref name)=>name.clone(),
+            _=>return None,
+        };
+        Some(name)
+    }
+}
+//Shortest form of An+B,such as 2n+1,-n+3 or 4
+fn an_plus_b(a:i32,b:i32)->String{
+    let a_part=match a{
+        0=>return b.to_string(),
+        1=>String::from("n"),
+        -1=>String::from("-n"),
+        a=>format!("{}n",a),
+    };
+    match b{
+        0=>a_part,
+        b if b>0=>format!("{}+{}",a_part,b),
+        b=>format!("{}{}",a_part,b),
+    }
+}
+impl fmt::Debug for PseudoClass{
+    fn fmt(&self,f:&mut fmt::Formatter)->fmt::Result{
+        match *self{
+            PseudoClass::Not(ref list)=>write!(f,":not({:?})",list),
+            PseudoClass::Is(ref list)=>write!(f,":is({:?})",list),
+            PseudoClass::Where(ref list)=>write!(f,":where({:?})",list),
+            PseudoClass::Has(ref list)=>write!(f,":has({:?})",list),
+            ref simple=>write!(f,":{}",simple.name().unwrap_or_default()),
+        }
+    }
+}
+impl fmt::Debug for RelativeSelector{
+    fn fmt(&self,f:&mut fmt::Formatter)->fmt::Result{
+        write!(f,"{} {:?}",self.combinator,self.selector)
+    }
+}
+
 
 impl Declaration{
     pub fn new(key:String,value:Value)->Declaration{
-        Declaration{key,value}
+        Declaration{key,value,important:false}
     }
 }
 
 impl Default for Declaration{
     fn default()->Self{
-        Declaration{key:String::new(),value:Value::Other(String::from("")),}
+        Declaration::new(String::new(),Value::Other(String::from("")))
     }
 }
 
 impl fmt::Debug for Declaration{
     fn fmt(&self,f:&mut fmt::Formatter)->fmt::Result{
-        write!(f,"{}:{:?}",self.key,self.value)
+        write!(f,"{}:{:?}",self.key,self.value)?;
+        if self.important{
+            write!(f," !important")?;
+        }
+        Ok(())
     }
 }
 
@@ -178,12 +836,34 @@ impl fmt::Debug for Value{
             Value::Color(ref c)=>write!(f,"{:?}",c),
             Value::Length(l,_)=>write!(f,"{:?}",l),
             Value::Other(ref s)=>write!(f,"{:?}",s),
+            Value::Custom(ref s)=>write!(f,"{:?}",s),
+            Value::Var(ref v)=>write!(f,"{:?}",v.css),
+            Value::Math(ref m)=>write!(f,"{:?}",m),
+            Value::Keyword(k)=>write!(f,"{:?}",k),
+            Value::Display(d)=>write!(f,"{:?}",d),
+            Value::Number(n)=>write!(f,"{:?}",n),
+            Value::Ident(ref s)=>write!(f,"{:?}",s),
+            Value::String(ref s)=>write!(f,"string({:?})",s),
+            Value::Url(ref u)=>write!(f,"url({:?})",u),
+            Value::Function(ref name,ref arguments)=>{
+                let arguments=arguments.iter().map(|a| format!("{:?}",a)).collect::<Vec<String>>();
+                write!(f,"{}({})",name,arguments.join(", "))
+            }
+            Value::List(ref values,separator)=>{
+                let values=values.iter().map(|v| format!("{:?}",v)).collect::<Vec<String>>();
+                match separator{
+                    ListSeparator::Space=>write!(f,"[{}]",values.join(" ")),
+                    ListSeparator::Comma=>write!(f,"[{}]",values.join(", ")),
+                }
+            }
+            Value::Time(t)=>write!(f,"{:?}s",t),
+            Value::Angle(a)=>write!(f,"{:?}deg",a),
         }
     }
 }
 impl Color{
     pub fn new(r:f32,g:f32,b:f32,a:f32)->Color{
-        Color{r,g,b,a}
+        Color{r,g,b,a,source:None}
     }
 }
 impl Default for Color{
@@ -198,7 +878,7 @@ impl fmt::Debug for Color{
 }
 
 
-#[derive(PartialEq)]
+#[derive(PartialEq, Clone, Copy, Debug)]
 pub enum Unit {
     Em,
     Ex,
@@ -217,8 +897,100 @@ pub enum Unit {
     Pc,
     Percent,
 }
-pub fn match_unit(s:&str)->Unit{
-    match s{
+impl MathExpression{
+    //None when the operands can not be combined,like 1px + 2 or 1px * 1px
+    pub fn math_type(&self)->Option<MathType>{
+        match *self{
+            MathExpression::Number(_)=>Some(MathType::Number),
+            MathExpression::Dimension(_,Unit::Percent)=>Some(MathType::Percentage),
+            MathExpression::Dimension(..)=>Some(MathType::Length),
+            MathExpression::Negate(ref e)=>e.math_type(),
+            MathExpression::Invert(ref e)=>match e.math_type()?{
+                MathType::Number=>Some(MathType::Number),
+                _=>None,
+            },
+            MathExpression::Product(ref factors)=>{
+                let mut result=MathType::Number;
+                for factor in factors{
+                    result=match (result,factor.math_type()?){
+                        (MathType::Number,t)|(t,MathType::Number)=>t,
+                        _=>return None,
+                    };
+                }
+                Some(result)
+            }
+            MathExpression::Sum(ref list)|MathExpression::Min(ref list)|MathExpression::Max(ref list)=>combine_math_types(list.iter()),
+            MathExpression::Clamp(ref low,ref value,ref high)=>combine_math_types(vec![&**low,&**value,&**high].into_iter()),
+        }
+    }
+    //True when every dimension is in px,the expression then folds to a plain length
+    pub fn is_absolute(&self)->bool{
+        match *self{
+            MathExpression::Number(_)=>true,
+            MathExpression::Dimension(_,unit)=>unit==Unit::Px,
+            MathExpression::Negate(ref e)|MathExpression::Invert(ref e)=>e.is_absolute(),
+            MathExpression::Sum(ref list)|MathExpression::Product(ref list)|MathExpression::Min(ref list)|MathExpression::Max(ref list)=>list.iter().all(|e| e.is_absolute()),
+            MathExpression::Clamp(ref low,ref value,ref high)=>low.is_absolute()&&value.is_absolute()&&high.is_absolute(),
+        }
+    }
+    //Replaces the dimensions resolve turns into px,the others are kept as they are
+    pub fn resolve_units(&self,resolve:&dyn Fn(f32,Unit)->Option<f32>)->MathExpression{
+        let all=|list:&[MathExpression]| list.iter().map(|e| e.resolve_units(resolve)).collect();
+        match *self{
+            MathExpression::Number(n)=>MathExpression::Number(n),
+            MathExpression::Dimension(n,unit)=>match resolve(n,unit){
+                Some(px)=>MathExpression::Dimension(px,Unit::Px),
+                None=>MathExpression::Dimension(n,unit),
+            },
+            MathExpression::Sum(ref list)=>MathExpression::Sum(all(list)),
+            MathExpression::Negate(ref e)=>MathExpression::Negate(Box::new(e.resolve_units(resolve))),
+            MathExpression::Product(ref list)=>MathExpression::Product(all(list)),
+            MathExpression::Invert(ref e)=>MathExpression::Invert(Box::new(e.resolve_units(resolve))),
+            MathExpression::Min(ref list)=>MathExpression::Min(all(list)),
+            MathExpression::Max(ref list)=>MathExpression::Max(all(list)),
+            MathExpression::Clamp(ref low,ref value,ref high)=>MathExpression::Clamp(
+                Box::new(low.resolve_units(resolve)),
+                Box::new(value.resolve_units(resolve)),
+                Box::new(high.resolve_units(resolve)),
+            ),
+        }
+    }
+    //resolve turns a dimension into px
+    pub fn evaluate(&self,resolve:&dyn Fn(f32,Unit)->f32)->f32{
+        match *self{
+            MathExpression::Number(n)=>n,
+            MathExpression::Dimension(n,unit)=>resolve(n,unit),
+            MathExpression::Sum(ref list)=>list.iter().map(|e| e.evaluate(resolve)).sum(),
+            MathExpression::Negate(ref e)=>-e.evaluate(resolve),
+            MathExpression::Product(ref list)=>list.iter().map(|e| e.evaluate(resolve)).product(),
+            MathExpression::Invert(ref e)=>1.0/e.evaluate(resolve),
+            MathExpression::Min(ref list)=>list.iter().map(|e| e.evaluate(resolve)).fold(std::f32::INFINITY,f32::min),
+            MathExpression::Max(ref list)=>list.iter().map(|e| e.evaluate(resolve)).fold(std::f32::NEG_INFINITY,f32::max),
+            MathExpression::Clamp(ref low,ref value,ref high)=>{
+                let low=low.evaluate(resolve);
+                low.max(value.evaluate(resolve).min(high.evaluate(resolve)))
+            }
+        }
+    }
+}
+//Operands of +,-,min(),max() and clamp() must agree,lengths and percentages mix into a length-percentage
+fn combine_math_types<'a,I>(operands:I)->Option<MathType> where I:Iterator<Item=&'a MathExpression>{
+    let mut result=None;
+    for operand in operands{
+        let t=operand.math_type()?;
+        result=match (result,t){
+            (None,t)=>Some(t),
+            (Some(r),t) if r==t=>Some(t),
+            (Some(MathType::Number),_)|(_,MathType::Number)=>return None,
+            _=>Some(MathType::LengthPercentage),
+        };
+    }
+    result
+}
+//None for anything that is not a known length unit,so callers can reject the value
+pub fn match_unit(s:&str)->Option<Unit>{
+    let unit=match s{
+        "px" => Unit::Px,
         "em" => Unit::Em,
         "ex" =>  Unit::Ex,
         "ch" =>  Unit::Ch,
@@ -234,9 +1006,10 @@ pub fn match_unit(s:&str)->Unit{
         "pt" => Unit::Pt,
         "pc" => Unit::Pc,
         "%" => Unit::Percent,
-        _ => Unit::Px,
+        _ => return None,
 
-    }
+    };
+    Some(unit)
 }
 
 pub fn match_color_by_name(color:&str)->Color{
@@ -732,6 +1505,7 @@ pub fn match_color_by_name(color:&str)->Color{
             1.0,
         ),
         "rebeccapurple" => Color::new(0.4, 0.2, 0.6, 1.0),
+        "transparent" => Color::new(0.0, 0.0, 0.0, 0.0),
         _ => Color::new(0.0, 0.0, 0.0, 1.0),
     }
 }
\ No newline at end of file
@@ -0,0 +1,634 @@
+//@font-face descriptors,font matching (CSS Fonts 4) and loading of the matched font files
+use crate::csselements::{FontFace, FontSource, FontStyle, ListSeparator, Unit, Value};
+use crate::cssvalues::{each_item, is_ident_in, is_length_percentage, parse_component_list};
+use crate::dom::{Node, NodeType};
+use crate::resourceloader::ResourceLoader;
+use crate::styletree::{ComputedStyle, StyleNode};
+
+use std::cell::RefCell;
+use std::fs;
+use std::path::Path;
+use std::ptr;
+use std::rc::Rc;
+
+//Formats the renderer can read,other src entries are skipped
+const SUPPORTED_FORMATS: [&str; 4] = [
+    "truetype",
+    "opentype",
+    "truetype-variations",
+    "opentype-variations",
+];
+
+//Searched for local() sources
+const FONT_DIRECTORIES: [&str; 5] = [
+    "/usr/share/fonts",
+    "/usr/local/share/fonts",
+    "/Library/Fonts",
+    "/System/Library/Fonts",
+    "C:\\Windows\\Fonts",
+];
+
+//Builds a FontFace from the descriptors of an @font-face block,None without a valid family and src
+pub fn parse_font_face(descriptors: &[(String, String)]) -> Option<FontFace> {
+    let mut family = None;
+    let mut sources = Vec::new();
+    let mut weight = (400.0, 400.0);
+    let mut style = FontStyle::Normal;
+    let mut unicode_range = vec![(0, 0x10FFFF)];
+
+    for (name, value) in descriptors {
+        match name.as_ref() {
+            "font-family" => family = parse_family_name(value).or(family),
+            "src" => sources = parse_sources(value),
+            "font-weight" => weight = parse_weight_range(value).unwrap_or(weight),
+            "font-style" => style = parse_style(value).unwrap_or(style),
+            "unicode-range" => unicode_range = parse_unicode_range(value).unwrap_or(unicode_range),
+            _ => {}
+        }
+    }
+    if sources.is_empty() {
+        return None;
+    }
+    Some(FontFace {
+        family: family?,
+        sources,
+        weight,
+        style,
+        unicode_range,
+    })
+}
+
+//A quoted name,or a sequence of identifiers joined by single spaces.Names keep the case they are
+//written in and are compared case-insensitively.
+fn parse_family_name(value: &str) -> Option<String> {
+    parse_family_name_value(&parse_component_list(value)?)
+}
+
+//url(...) [format(...)]? | local(...),entries that do not parse are skipped
+fn parse_sources(value: &str) -> Vec<FontSource> {
+    let entries = match parse_component_list(value) {
+        Some(Value::List(entries, ListSeparator::Comma)) => entries,
+        Some(entry) => vec![entry],
+        None => return Vec::new(),
+    };
+
+    let mut sources = Vec::new();
+    for entry in entries {
+        let source = match entry {
+            Value::Url(url) => FontSource::Url { url, format: None },
+            Value::Function(ref name, ref arguments)
+                if name.eq_ignore_ascii_case("local") && arguments.len() == 1 =>
+            {
+                match parse_family_name_value(&arguments[0]) {
+                    Some(n) => FontSource::Local(n),
+                    None => continue,
+                }
+            }
+            Value::List(ref parts, ListSeparator::Space) if parts.len() == 2 => {
+                match (&parts[0], &parts[1]) {
+                    (Value::Url(url), Value::Function(name, arguments))
+                        if name.eq_ignore_ascii_case("format") && arguments.len() == 1 =>
+                    {
+                        let format = match arguments[0] {
+                            Value::String(ref f) | Value::Ident(ref f) => f.to_lowercase(),
+                            _ => continue,
+                        };
+                        FontSource::Url {
+                            url: url.clone(),
+                            format: Some(format),
+                        }
+                    }
+                    _ => continue,
+                }
+            }
+            _ => continue,
+        };
+        sources.push(source);
+    }
+    sources
+}
+
+fn parse_family_name_value(value: &Value) -> Option<String> {
+    match *value {
+        Value::String(ref s) | Value::Ident(ref s) => Some(s.clone()),
+        Value::List(ref parts, ListSeparator::Space) => parts
+            .iter()
+            .map(|p| match *p {
+                Value::Ident(ref n) => Some(n.clone()),
+                _ => None,
+            })
+            .collect::<Option<Vec<String>>>()
+            .map(|names| names.join(" ")),
+        _ => None,
+    }
+}
+
+//Grammars of the font properties,checked on their component values
+pub fn is_family_list(value: &Value) -> bool {
+    each_item(value, &|v| parse_family_name_value(v).is_some())
+}
+
+pub fn is_font_weight(value: &Value) -> bool {
+    match *value {
+        Value::Number(n) => (1.0..=1000.0).contains(&n),
+        _ => is_ident_in(value, &["normal", "bold", "bolder", "lighter"]),
+    }
+}
+
+pub fn is_font_stretch(value: &Value) -> bool {
+    match *value {
+        Value::Length(n, Unit::Percent) => n >= 0.0,
+        _ => is_ident_in(
+            value,
+            &[
+                "normal",
+                "ultra-condensed",
+                "extra-condensed",
+                "condensed",
+                "semi-condensed",
+                "semi-expanded",
+                "expanded",
+                "extra-expanded",
+                "ultra-expanded",
+            ],
+        ),
+    }
+}
+
+pub fn is_line_height(value: &Value) -> bool {
+    match *value {
+        Value::Number(n) => n >= 0.0,
+        _ => is_ident_in(value, &["normal"]) || is_length_percentage(value, false),
+    }
+}
+
+fn parse_weight(value: &str) -> Option<f32> {
+    let weight = match value {
+        "normal" => 400.0,
+        "bold" => 700.0,
+        n => n.parse::<f32>().ok()?,
+    };
+    if !(1.0..=1000.0).contains(&weight) {
+        return None;
+    }
+    Some(weight)
+}
+
+fn parse_weight_range(value: &str) -> Option<(f32, f32)> {
+    let value = value.trim().to_lowercase();
+    let parts = value.split_whitespace().collect::<Vec<&str>>();
+    match parts.as_slice() {
+        ["auto"] => Some((1.0, 1000.0)),
+        [w] => parse_weight(w).map(|w| (w, w)),
+        [low, high] => {
+            let (low, high) = (parse_weight(low)?, parse_weight(high)?);
+            Some((low.min(high), low.max(high)))
+        }
+        _ => None,
+    }
+}
+
+fn parse_angle(value: &str) -> Option<f32> {
+    value.strip_suffix("deg")?.parse().ok()
+}
+
+fn parse_style(value: &str) -> Option<FontStyle> {
+    let value = value.trim().to_lowercase();
+    let parts = value.split_whitespace().collect::<Vec<&str>>();
+    match parts.as_slice() {
+        ["normal"] => Some(FontStyle::Normal),
+        ["italic"] => Some(FontStyle::Italic),
+        ["oblique"] => Some(FontStyle::Oblique(14.0, 14.0)),
+        ["oblique", a] => parse_angle(a).map(|a| FontStyle::Oblique(a, a)),
+        ["oblique", a, b] => {
+            let (a, b) = (parse_angle(a)?, parse_angle(b)?);
+            Some(FontStyle::Oblique(a.min(b), a.max(b)))
+        }
+        _ => None,
+    }
+}
+
+//U+0025-00FF,U+4??,U+20AC
+fn parse_unicode_range(value: &str) -> Option<Vec<(u32, u32)>> {
+    let mut ranges = Vec::new();
+    for part in value.split(',') {
+        let part = part.trim().to_lowercase();
+        if !part.starts_with("u+") {
+            return None;
+        }
+        let part = &part[2..];
+        let range = match part.find('-') {
+            Some(i) => (
+                u32::from_str_radix(&part[..i], 16).ok()?,
+                u32::from_str_radix(&part[i + 1..], 16).ok()?,
+            ),
+            None if part.contains('?') => (
+                u32::from_str_radix(&part.replace('?', "0"), 16).ok()?,
+                u32::from_str_radix(&part.replace('?', "f"), 16).ok()?,
+            ),
+            None => {
+                let c = u32::from_str_radix(part, 16).ok()?;
+                (c, c)
+            }
+        };
+        if range.0 > range.1 || range.1 > 0x10FFFF {
+            return None;
+        }
+        ranges.push(range);
+    }
+    Some(ranges)
+}
+
+//Picks the face for the first family in families that has one,following the CSS Fonts 4
+//style then weight rules.With a character only faces whose unicode-range covers it count.
+pub fn match_font_face<'a>(
+    faces: &[&'a FontFace],
+    families: &[String],
+    weight: f32,
+    style: FontStyle,
+    character: Option<char>,
+) -> Option<&'a FontFace> {
+    for family in families {
+        let candidates = faces
+            .iter()
+            .cloned()
+            .filter(|f| f.family.to_lowercase() == family.to_lowercase())
+            .filter(|f| character.map_or(true, |c| covers(f, c)))
+            .collect::<Vec<&FontFace>>();
+
+        for wanted in style_preference(style).iter() {
+            let same_style = candidates
+                .iter()
+                .cloned()
+                .filter(|f| style_kind(f.style) == *wanted)
+                .collect::<Vec<&FontFace>>();
+            if !same_style.is_empty() {
+                return closest_weight(&same_style, weight);
+            }
+        }
+    }
+    None
+}
+
+fn covers(face: &FontFace, character: char) -> bool {
+    let c = character as u32;
+    face.unicode_range
+        .iter()
+        .any(|&(low, high)| low <= c && c <= high)
+}
+
+//0 normal,1 italic,2 oblique
+fn style_kind(style: FontStyle) -> u8 {
+    match style {
+        FontStyle::Normal => 0,
+        FontStyle::Italic => 1,
+        FontStyle::Oblique(..) => 2,
+    }
+}
+
+fn style_preference(style: FontStyle) -> [u8; 3] {
+    match style {
+        FontStyle::Italic => [1, 2, 0],
+        FontStyle::Oblique(..) => [2, 1, 0],
+        FontStyle::Normal => [0, 2, 1],
+    }
+}
+
+//Weights between 400 and 500 look up to 500 first,lighter weights look down,bolder ones up
+fn closest_weight<'a>(faces: &[&'a FontFace], desired: f32) -> Option<&'a FontFace> {
+    let rank = |face: &FontFace| -> (u8, f32) {
+        let (low, high) = face.weight;
+        if low <= desired && desired <= high {
+            return (0, 0.0);
+        }
+        let above = low > desired;
+        let distance = if above { low - desired } else { desired - high };
+        let tier = if (400.0..=500.0).contains(&desired) {
+            match (above, low <= 500.0) {
+                (true, true) => 1,
+                (false, _) => 2,
+                (true, false) => 3,
+            }
+        } else if desired < 400.0 {
+            if above {
+                2
+            } else {
+                1
+            }
+        } else if above {
+            1
+        } else {
+            2
+        };
+        (tier, distance)
+    };
+    faces.iter().cloned().min_by(|a, b| {
+        let (a, b) = (rank(a), rank(b));
+        a.0.cmp(&b.0)
+            .then(a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+    })
+}
+
+//Reads the first source of face that can be loaded
+pub fn load_font_face(loader: &dyn ResourceLoader, face: &FontFace) -> Option<Vec<u8>> {
+    for source in &face.sources {
+        let data = match *source {
+            FontSource::Url {
+                ref url,
+                ref format,
+            } => match *format {
+                Some(ref f) if !SUPPORTED_FORMATS.contains(&f.as_str()) => None,
+                _ => loader.load_bytes(url),
+            },
+            FontSource::Local(ref name) => find_local_font(name),
+        };
+        if data.is_some() {
+            return data;
+        }
+    }
+    None
+}
+
+//Installed font files are matched on their file name,ignoring case,spaces and dashes
+fn find_local_font(name: &str) -> Option<Vec<u8>> {
+    let normalize = |s: &str| {
+        s.chars()
+            .filter(|c| *c != ' ' && *c != '-' && *c != '_')
+            .collect::<String>()
+            .to_lowercase()
+    };
+    let wanted = normalize(name);
+    FONT_DIRECTORIES
+        .iter()
+        .filter_map(|d| search_directory(Path::new(d), &wanted, &normalize, 0))
+        .next()
+}
+
+fn search_directory(
+    directory: &Path,
+    wanted: &str,
+    normalize: &dyn Fn(&str) -> String,
+    depth: usize,
+) -> Option<Vec<u8>> {
+    if depth > 4 {
+        return None;
+    }
+    for entry in fs::read_dir(directory).ok()?.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if path.is_dir() {
+            if let Some(data) = search_directory(&path, wanted, normalize, depth + 1) {
+                return Some(data);
+            }
+            continue;
+        }
+        let extension = path
+            .extension()
+            .map_or(String::new(), |e| e.to_string_lossy().to_lowercase());
+        let stem = path
+            .file_stem()
+            .map_or(String::new(), |s| normalize(&s.to_string_lossy()));
+        if (extension == "ttf" || extension == "otf") && stem == wanted {
+            return fs::read(&path).ok();
+        }
+    }
+    None
+}
+
+//The face matching the font-family,font-weight and font-style of style,None when only the
+//renderer's default font applies
+pub fn face_for_style<'a>(style: &ComputedStyle, faces: &[&'a FontFace]) -> Option<&'a FontFace> {
+    let families = match style.get_by_name("font-family") {
+        Some(&Value::List(ref list, ListSeparator::Comma)) => list
+            .iter()
+            .filter_map(parse_family_name_value)
+            .collect::<Vec<String>>(),
+        Some(value) => parse_family_name_value(value).into_iter().collect(),
+        None => return None,
+    };
+    let weight = match style.get_by_name("font-weight") {
+        Some(&Value::Number(n)) => n,
+        Some(&Value::Ident(ref w)) if w == "bold" || w == "bolder" => 700.0,
+        Some(&Value::Ident(ref w)) if w == "lighter" => 100.0,
+        _ => 400.0,
+    };
+    let font_style = match style.get_by_name("font-style") {
+        Some(&Value::Keyword("italic")) => FontStyle::Italic,
+        Some(&Value::Keyword("oblique")) => FontStyle::Oblique(14.0, 14.0),
+        _ => FontStyle::Normal,
+    };
+
+    match_font_face(faces, &families, weight, font_style, None)
+}
+
+//Font data for the style of node
+pub fn font_for_style(
+    node: &StyleNode,
+    faces: &[&FontFace],
+    loader: &dyn ResourceLoader,
+) -> Option<Vec<u8>> {
+    load_font_face(loader, face_for_style(&node.style, faces)?)
+}
+
+//The renderer draws all text in one font,the face of the body element's style.
+//document is the root element and root its style node,its own style is used without a body.
+pub fn document_face<'a>(
+    document: &Node,
+    root: &StyleNode,
+    faces: &[&'a FontFace],
+) -> Option<&'a FontFace> {
+    //Style nodes are only made for elements,in document order
+    let body = document
+        .children
+        .iter()
+        .filter(|child| matches!(child.node_type, NodeType::Element(_)))
+        .zip(root.children.iter())
+        .find(|&(child, _)| match child.node_type {
+            NodeType::Element(ref e) => e.tag_name.eq_ignore_ascii_case("body"),
+            _ => false,
+        })
+        .map_or(root, |(_, style)| style);
+    face_for_style(&body.style, faces)
+}
+
+//Sizes ex and ch refer to,as fractions of the em
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FontMetrics {
+    //sxHeight of the OS/2 table,or the height of the "x" glyph
+    pub x_height: Option<f32>,
+    //Advance width of the glyph for "0"
+    pub zero_advance: Option<f32>,
+}
+
+//Font files loaded so far,each face is read and measured once
+#[derive(Default)]
+pub struct FontCache<'a> {
+    fonts: RefCell<Vec<(&'a FontFace, Option<LoadedFont>)>>,
+}
+
+#[derive(Clone)]
+pub struct LoadedFont {
+    pub data: Rc<Vec<u8>>,
+    pub metrics: Option<FontMetrics>,
+}
+
+impl<'a> FontCache<'a> {
+    pub fn load(&self, loader: &dyn ResourceLoader, face: &'a FontFace) -> Option<LoadedFont> {
+        if let Some((_, font)) = self.fonts.borrow().iter().find(|&&(f, _)| ptr::eq(f, face)) {
+            return font.clone();
+        }
+        let font = load_font_face(loader, face).map(|data| LoadedFont {
+            metrics: font_metrics(&data),
+            data: Rc::new(data),
+        });
+        self.fonts.borrow_mut().push((face, font.clone()));
+        font
+    }
+}
+
+//Reads the metrics of a TrueType/OpenType font,None when data is not one.
+//Font collections are not read.
+pub fn font_metrics(data: &[u8]) -> Option<FontMetrics> {
+    match read_u32(data, 0)? {
+        0x0001_0000 | 0x4F54_544F | 0x7472_7565 => {}
+        _ => return None,
+    }
+    let table = |tag: &[u8]| -> Option<&[u8]> {
+        let count = read_u16(data, 4)? as usize;
+        let record = (0..count)
+            .map(|i| 12 + i * 16)
+            .find(|&record| data.get(record..record + 4) == Some(tag))?;
+        let offset = read_u32(data, record + 8)? as usize;
+        let length = read_u32(data, record + 12)? as usize;
+        data.get(offset..offset.checked_add(length)?)
+    };
+
+    let head = table(b"head")?;
+    let units_per_em = read_u16(head, 18)? as f32;
+    if units_per_em == 0.0 {
+        return None;
+    }
+    //sxHeight was added in version 2 of the OS/2 table,older TrueType fonts are measured
+    let x_height = table(b"OS/2")
+        .filter(|os2| read_u16(os2, 0).map_or(false, |version| version >= 2))
+        .and_then(|os2| read_i16(os2, 86))
+        .or_else(|| {
+            let glyph = glyph_index(table(b"cmap")?, 'x')?;
+            glyph_top(head, table(b"loca")?, table(b"glyf")?, glyph)
+        })
+        .filter(|&height| height > 0)
+        .map(|height| height as f32 / units_per_em);
+    let zero_advance = table(b"cmap")
+        .and_then(|cmap| glyph_index(cmap, '0'))
+        .and_then(|glyph| advance_width(table(b"hhea")?, table(b"hmtx")?, glyph))
+        .map(|advance| advance as f32 / units_per_em);
+    Some(FontMetrics {
+        x_height,
+        zero_advance,
+    })
+}
+
+//Glyph of character from the first Unicode subtable of cmap that maps it
+fn glyph_index(cmap: &[u8], character: char) -> Option<u16> {
+    let count = read_u16(cmap, 2)? as usize;
+    for record in (0..count).map(|i| 4 + i * 8) {
+        let platform = read_u16(cmap, record)?;
+        let encoding = read_u16(cmap, record + 2)?;
+        if !(platform == 0 || platform == 3 && (encoding == 1 || encoding == 10)) {
+            continue;
+        }
+        let subtable = cmap.get(read_u32(cmap, record + 4)? as usize..)?;
+        if let Some(glyph) = subtable_glyph(subtable, character as u32) {
+            return Some(glyph);
+        }
+    }
+    None
+}
+
+//Formats 0,4 and 12,the ones Unicode subtables are written in.Glyph 0 is the missing glyph.
+fn subtable_glyph(subtable: &[u8], c: u32) -> Option<u16> {
+    let glyph = match read_u16(subtable, 0)? {
+        0 if c < 256 => *subtable.get(6 + c as usize)? as u16,
+        4 => {
+            let segments = read_u16(subtable, 6)? as usize / 2;
+            let ends = 14;
+            let starts = ends + segments * 2 + 2;
+            let deltas = starts + segments * 2;
+            let range_offsets = deltas + segments * 2;
+            let segment = (0..segments)
+                .find(|&i| read_u16(subtable, ends + i * 2).map_or(false, |end| c <= end as u32))?;
+            let start = read_u16(subtable, starts + segment * 2)? as u32;
+            if c < start {
+                return None;
+            }
+            let delta = read_u16(subtable, deltas + segment * 2)?;
+            let range_offset_at = range_offsets + segment * 2;
+            let range_offset = read_u16(subtable, range_offset_at)? as usize;
+            if range_offset == 0 {
+                (c as u16).wrapping_add(delta)
+            } else {
+                let glyph = read_u16(
+                    subtable,
+                    range_offset_at + range_offset + (c - start) as usize * 2,
+                )?;
+                if glyph == 0 {
+                    return None;
+                }
+                glyph.wrapping_add(delta)
+            }
+        }
+        12 => {
+            let groups = read_u32(subtable, 12)? as usize;
+            let group = (0..groups).map(|i| 16 + i * 12).find(|&group| {
+                read_u32(subtable, group).map_or(false, |start| start <= c)
+                    && read_u32(subtable, group + 4).map_or(false, |end| c <= end)
+            })?;
+            (read_u32(subtable, group + 8)? + c - read_u32(subtable, group)?) as u16
+        }
+        _ => return None,
+    };
+    Some(glyph).filter(|&g| g != 0)
+}
+
+//In font units,glyphs past the last long metric have its advance
+fn advance_width(hhea: &[u8], hmtx: &[u8], glyph: u16) -> Option<u16> {
+    let metrics = read_u16(hhea, 34)? as usize;
+    if metrics == 0 {
+        return None;
+    }
+    read_u16(hmtx, (glyph as usize).min(metrics - 1) * 4)
+}
+
+//yMax of the outline of glyph,in font units
+fn glyph_top(head: &[u8], loca: &[u8], glyf: &[u8], glyph: u16) -> Option<i16> {
+    let index = glyph as usize;
+    //indexToLocFormat,0 for offsets that are stored halved in 16 bits
+    let (start, end) = match read_i16(head, 50)? {
+        0 => (
+            read_u16(loca, index * 2)? as usize * 2,
+            read_u16(loca, index * 2 + 2)? as usize * 2,
+        ),
+        _ => (
+            read_u32(loca, index * 4)? as usize,
+            read_u32(loca, index * 4 + 4)? as usize,
+        ),
+    };
+    //A glyph without an outline has no data
+    if end <= start {
+        return None;
+    }
+    read_i16(glyf.get(start..end)?, 8)
+}
+
+fn read_u16(data: &[u8], at: usize) -> Option<u16> {
+    data.get(at..at + 2)
+        .map(|b| u16::from_be_bytes([b[0], b[1]]))
+}
+
+fn read_i16(data: &[u8], at: usize) -> Option<i16> {
+    read_u16(data, at).map(|v| v as i16)
+}
+
+fn read_u32(data: &[u8], at: usize) -> Option<u32> {
+    data.get(at..at + 4)
+        .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
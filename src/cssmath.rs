@@ -0,0 +1,306 @@
+//Parser for the math functions calc(),min(),max() and clamp()
+use crate::csselements::{match_unit, MathExpression, Unit};
+
+#[derive(Clone, PartialEq, Debug)]
+enum Token {
+    Number(f32),
+    Dimension(f32, Unit),
+    Function(String),
+    Open,
+    Close,
+    Comma,
+    Delim(char),
+    Space,
+}
+
+pub fn is_math_function(value: &str) -> bool {
+    ["calc(", "min(", "max(", "clamp("]
+        .iter()
+        .any(|f| value.starts_with(f))
+}
+
+//Parses a whole value such as `calc(100% - 2em)`,None on a syntax error.
+//The result is not type checked,see MathExpression::math_type.
+pub fn parse_math_function(value: &str) -> Option<MathExpression> {
+    let tokens = tokenize(value.trim())?;
+    let mut parser = MathParser {
+        tokens: &tokens,
+        position: 0,
+    };
+    let expression = match parser.next()? {
+        Token::Function(name) => parser.parse_function(&name)?,
+        _ => return None,
+    };
+    if parser.position != tokens.len() {
+        return None;
+    }
+    Some(expression)
+}
+
+struct MathParser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+}
+
+impl<'a> MathParser<'a> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn skip_space(&mut self) -> bool {
+        let mut skipped = false;
+        while self.peek() == Some(&Token::Space) {
+            self.position += 1;
+            skipped = true;
+        }
+        skipped
+    }
+
+    //Called after the opening parenthesis of name
+    fn parse_function(&mut self, name: &str) -> Option<MathExpression> {
+        let mut arguments = vec![self.parse_sum()?];
+        while self.peek() == Some(&Token::Comma) {
+            self.next();
+            arguments.push(self.parse_sum()?);
+        }
+        if self.next()? != Token::Close {
+            return None;
+        }
+
+        match (name, arguments.len()) {
+            ("calc", 1) => arguments.pop(),
+            ("min", _) => Some(MathExpression::Min(arguments)),
+            ("max", _) => Some(MathExpression::Max(arguments)),
+            ("clamp", 3) => {
+                let high = Box::new(arguments.pop()?);
+                let value = Box::new(arguments.pop()?);
+                let low = Box::new(arguments.pop()?);
+                Some(MathExpression::Clamp(low, value, high))
+            }
+            _ => None,
+        }
+    }
+
+    //<product> [ ' + ' | ' - ' <product> ]*,the operators need whitespace on both sides
+    fn parse_sum(&mut self) -> Option<MathExpression> {
+        self.skip_space();
+        let mut terms = vec![self.parse_product()?];
+
+        loop {
+            let before = self.position;
+            let spaced = self.skip_space();
+            let operator = match self.peek() {
+                Some(&Token::Delim(c)) if spaced && (c == '+' || c == '-') => c,
+                _ => {
+                    self.position = before;
+                    break;
+                }
+            };
+            self.next();
+            if !self.skip_space() {
+                return None;
+            }
+            let term = self.parse_product()?;
+            terms.push(match operator {
+                '-' => MathExpression::Negate(Box::new(term)),
+                _ => term,
+            });
+        }
+        self.skip_space();
+
+        if terms.len() == 1 {
+            terms.pop()
+        } else {
+            Some(MathExpression::Sum(terms))
+        }
+    }
+
+    fn parse_product(&mut self) -> Option<MathExpression> {
+        let mut factors = vec![self.parse_value()?];
+
+        loop {
+            let before = self.position;
+            self.skip_space();
+            let operator = match self.peek() {
+                Some(&Token::Delim(c)) if c == '*' || c == '/' => c,
+                _ => {
+                    self.position = before;
+                    break;
+                }
+            };
+            self.next();
+            self.skip_space();
+            let factor = self.parse_value()?;
+            factors.push(match operator {
+                '/' => MathExpression::Invert(Box::new(factor)),
+                _ => factor,
+            });
+        }
+
+        if factors.len() == 1 {
+            factors.pop()
+        } else {
+            Some(MathExpression::Product(factors))
+        }
+    }
+
+    fn parse_value(&mut self) -> Option<MathExpression> {
+        match self.next()? {
+            Token::Number(n) => Some(MathExpression::Number(n)),
+            Token::Dimension(n, unit) => Some(MathExpression::Dimension(n, unit)),
+            Token::Open => {
+                let sum = self.parse_sum()?;
+                if self.next()? != Token::Close {
+                    return None;
+                }
+                Some(sum)
+            }
+            Token::Function(name) => self.parse_function(&name),
+            _ => None,
+        }
+    }
+}
+
+fn tokenize(value: &str) -> Option<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = value.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        let signed_number = (c == '+' || c == '-' || c == '.') && {
+            let mut lookahead = chars.clone();
+            lookahead.next();
+            match lookahead.next() {
+                Some(d) if d.is_digit(10) => true,
+                Some('.') if c != '.' => lookahead.next().map_or(false, |d| d.is_digit(10)),
+                _ => false,
+            }
+        };
+
+        if c.is_whitespace() {
+            while chars.peek().map_or(false, |c| c.is_whitespace()) {
+                chars.next();
+            }
+            tokens.push(Token::Space);
+        } else if c.is_digit(10) || signed_number {
+            let mut number = String::new();
+            number.push(c);
+            chars.next();
+            while let Some(&d) = chars.peek() {
+                if d.is_digit(10) || d == '.' {
+                    number.push(d);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            let number = number.parse::<f32>().ok()?;
+
+            let mut unit = String::new();
+            while let Some(&u) = chars.peek() {
+                if u.is_alphabetic() || (u == '%' && unit.is_empty()) {
+                    unit.push(u);
+                    chars.next();
+                    if u == '%' {
+                        break;
+                    }
+                } else {
+                    break;
+                }
+            }
+            tokens.push(match unit.as_ref() {
+                "" => Token::Number(number),
+                u => Token::Dimension(number, match_unit(u)?),
+            });
+        } else if c.is_alphabetic() {
+            let mut name = String::new();
+            while let Some(&n) = chars.peek() {
+                if n.is_alphanumeric() || n == '-' {
+                    name.push(n);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            if chars.next() != Some('(') {
+                return None;
+            }
+            tokens.push(Token::Function(name));
+        } else {
+            chars.next();
+            tokens.push(match c {
+                '(' => Token::Open,
+                ')' => Token::Close,
+                ',' => Token::Comma,
+                '+' | '-' | '*' | '/' => Token::Delim(c),
+                _ => return None,
+            });
+        }
+    }
+    Some(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csselements::MathType;
+
+    fn px(value: &str) -> Option<f32> {
+        let expression = parse_math_function(value)?;
+        Some(expression.evaluate(&|n, unit| match unit {
+            Unit::Percent => n * 2.0,
+            _ => n,
+        }))
+    }
+
+    fn math_type(value: &str) -> Option<MathType> {
+        parse_math_function(value)?.math_type()
+    }
+
+    #[test]
+    fn operators_follow_precedence() {
+        assert_eq!(px("calc(10px + 2 * 5px)"), Some(20.0));
+        assert_eq!(px("calc((10px + 2px) * 2)"), Some(24.0));
+        assert_eq!(px("calc(100px / 4 - 5px)"), Some(20.0));
+        assert_eq!(px("calc(-5px + 10px)"), Some(5.0));
+        assert_eq!(px("calc(50% - 10px)"), Some(90.0));
+    }
+
+    #[test]
+    fn comparison_functions() {
+        assert_eq!(px("min(10px, 5px, 20px)"), Some(5.0));
+        assert_eq!(px("max(10px, 50%)"), Some(100.0));
+        assert_eq!(px("clamp(10px, 5px, 20px)"), Some(10.0));
+        assert_eq!(px("clamp(10px, 50px, 20px)"), Some(20.0));
+        assert_eq!(px("calc(min(10px, 20px) + 1px)"), Some(11.0));
+    }
+
+    #[test]
+    fn syntax_errors_are_rejected() {
+        assert!(parse_math_function("calc(1px +2px)").is_none());
+        assert!(parse_math_function("calc(1px+ 2px)").is_none());
+        assert!(parse_math_function("calc(1px, 2px)").is_none());
+        assert!(parse_math_function("clamp(1px, 2px)").is_none());
+        assert!(parse_math_function("calc(1px").is_none());
+        assert!(parse_math_function("calc(1foo)").is_none());
+        assert!(parse_math_function("calc(1px) 2px").is_none());
+    }
+
+    #[test]
+    fn operands_are_type_checked() {
+        assert_eq!(math_type("calc(1px + 2em)"), Some(MathType::Length));
+        assert_eq!(
+            math_type("calc(50% - 1px)"),
+            Some(MathType::LengthPercentage)
+        );
+        assert_eq!(math_type("calc(2 * 3)"), Some(MathType::Number));
+        assert_eq!(math_type("calc(1px + 2)"), None);
+        assert_eq!(math_type("calc(1px * 1px)"), None);
+        assert_eq!(math_type("calc(1 / 1px)"), None);
+    }
+}
@@ -0,0 +1,417 @@
+//Parser for Media Queries Level 4 preludes such as `screen and (400px <= width < 800px)`
+use crate::csselements::{
+    Comparison, MediaCondition, MediaFeature, MediaQuery, MediaQueryList, MediaType,
+};
+
+#[derive(Clone, PartialEq, Debug)]
+enum Token {
+    Ident(String),
+    Number(f32, String),
+    Colon,
+    Slash,
+    Comma,
+    Open,
+    Close,
+    Compare(Comparison),
+}
+
+//Parses a comma separated query list,invalid queries become `not all`.
+//An empty list matches every device.
+pub fn parse_media_query_list(prelude: &str) -> MediaQueryList {
+    if prelude.trim().is_empty() {
+        return Vec::new();
+    }
+    let tokens = match tokenize(prelude) {
+        Some(t) => t,
+        None => return vec![MediaQuery::not_all()],
+    };
+
+    let mut queries = Vec::new();
+    for part in tokens.split(|t| *t == Token::Comma) {
+        let mut parser = MediaParser {
+            tokens: part,
+            position: 0,
+        };
+        let query = match parser.parse_query() {
+            Some(q) if parser.position == part.len() => q,
+            _ => MediaQuery::not_all(),
+        };
+        queries.push(query);
+    }
+    queries
+}
+
+struct MediaParser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+}
+
+impl<'a> MediaParser<'a> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn peek_ident(&self, name: &str) -> bool {
+        match self.peek() {
+            Some(&Token::Ident(ref i)) => i == name,
+            _ => false,
+        }
+    }
+
+    fn parse_query(&mut self) -> Option<MediaQuery> {
+        if self.peek() == Some(&Token::Open) || self.peek_ident("not") && self.is_condition_not() {
+            return Some(MediaQuery {
+                negated: false,
+                media_type: MediaType::All,
+                condition: Some(self.parse_condition(true)?),
+            });
+        }
+
+        let mut negated = false;
+        if self.peek_ident("not") {
+            negated = true;
+            self.next();
+        } else if self.peek_ident("only") {
+            self.next();
+        }
+        let media_type = match self.next()? {
+            Token::Ident(ref t) => match t.as_ref() {
+                "all" => MediaType::All,
+                "screen" => MediaType::Screen,
+                "print" => MediaType::Print,
+                "and" | "or" | "not" | "only" => return None,
+                other => MediaType::Other(other.to_string()),
+            },
+            _ => return None,
+        };
+
+        let condition = if self.peek_ident("and") {
+            self.next();
+            Some(self.parse_condition(false)?)
+        } else {
+            None
+        };
+        Some(MediaQuery {
+            negated,
+            media_type,
+            condition,
+        })
+    }
+
+    //`not (` starts a condition,`not screen` negates a media type
+    fn is_condition_not(&self) -> bool {
+        self.tokens.get(self.position + 1) == Some(&Token::Open)
+    }
+
+    //<condition> = not <in-parens> | <in-parens> [and <in-parens>]* | <in-parens> [or <in-parens>]*
+    fn parse_condition(&mut self, allow_or: bool) -> Option<MediaCondition> {
+        if self.peek_ident("not") {
+            self.next();
+            return Some(MediaCondition::Not(Box::new(self.parse_in_parens()?)));
+        }
+
+        let first = self.parse_in_parens()?;
+        let joiner = match self.peek() {
+            Some(&Token::Ident(ref i)) if i == "and" || (i == "or" && allow_or) => i.clone(),
+            _ => return Some(first),
+        };
+        let mut conditions = vec![first];
+        while self.peek_ident(&joiner) {
+            self.next();
+            conditions.push(self.parse_in_parens()?);
+        }
+        if joiner == "and" {
+            Some(MediaCondition::And(conditions))
+        } else {
+            Some(MediaCondition::Or(conditions))
+        }
+    }
+
+    fn parse_in_parens(&mut self) -> Option<MediaCondition> {
+        if self.next()? != Token::Open {
+            return None;
+        }
+        let condition = match (self.peek().cloned(), self.tokens.get(self.position + 1)) {
+            (Some(Token::Open), _) | (Some(Token::Ident(_)), Some(&Token::Open)) => {
+                self.parse_condition(true)?
+            }
+            (Some(Token::Ident(ref i)), _) if i == "not" => self.parse_condition(true)?,
+            _ => MediaCondition::Feature(self.parse_feature()?),
+        };
+        if self.next()? != Token::Close {
+            return None;
+        }
+        Some(condition)
+    }
+
+    fn parse_feature(&mut self) -> Option<MediaFeature> {
+        match self.peek().cloned()? {
+            Token::Ident(name) => {
+                self.next();
+                match self.peek() {
+                    Some(&Token::Close) => Some(MediaFeature::Boolean(name)),
+                    Some(&Token::Colon) => {
+                        self.next();
+                        self.parse_plain_feature(name)
+                    }
+                    Some(&Token::Compare(op)) => {
+                        self.next();
+                        let value = self.parse_value()?;
+                        Some(MediaFeature::Range {
+                            name,
+                            comparisons: vec![(op, value)],
+                        })
+                    }
+                    _ => None,
+                }
+            }
+            _ => {
+                //<value> <op> <name> [<op> <value>]
+                let low = self.parse_value()?;
+                let first = match self.next()? {
+                    Token::Compare(op) => op,
+                    _ => return None,
+                };
+                let name = match self.next()? {
+                    Token::Ident(n) => n,
+                    _ => return None,
+                };
+                let mut comparisons = vec![(flip(first), low)];
+                if let Some(&Token::Compare(second)) = self.peek() {
+                    self.next();
+                    let high = self.parse_value()?;
+                    let ascending = |op| op == Comparison::Less || op == Comparison::LessEqual;
+                    if ascending(first) != ascending(second)
+                        || first == Comparison::Equal
+                        || second == Comparison::Equal
+                    {
+                        return None;
+                    }
+                    comparisons.push((second, high));
+                }
+                Some(MediaFeature::Range { name, comparisons })
+            }
+        }
+    }
+
+    //`name: value`,with min-/max- prefixes turned into range comparisons
+    fn parse_plain_feature(&mut self, name: String) -> Option<MediaFeature> {
+        if let Some(&Token::Ident(ref value)) = self.peek() {
+            let value = value.clone();
+            self.next();
+            return Some(MediaFeature::Discrete { name, value });
+        }
+        let value = self.parse_value()?;
+        let (name, op) = if name.starts_with("min-") {
+            (name[4..].to_string(), Comparison::GreaterEqual)
+        } else if name.starts_with("max-") {
+            (name[4..].to_string(), Comparison::LessEqual)
+        } else {
+            (name, Comparison::Equal)
+        };
+        Some(MediaFeature::Range {
+            name,
+            comparisons: vec![(op, value)],
+        })
+    }
+
+    //A length in px,a resolution in dppx,a ratio or a plain number
+    fn parse_value(&mut self) -> Option<f32> {
+        let (number, unit) = match self.next()? {
+            Token::Number(n, u) => (n, u),
+            _ => return None,
+        };
+        if unit.is_empty() && self.peek() == Some(&Token::Slash) {
+            self.next();
+            return match self.next()? {
+                Token::Number(d, ref u) if u.is_empty() && d != 0.0 => Some(number / d),
+                _ => None,
+            };
+        }
+        let factor = match unit.as_ref() {
+            "" | "px" | "dppx" | "x" => 1.0,
+            "em" | "rem" => 16.0,
+            "in" => 96.0,
+            "cm" => 96.0 / 2.54,
+            "mm" => 96.0 / 25.4,
+            "q" => 96.0 / 101.6,
+            "pt" => 96.0 / 72.0,
+            "pc" => 16.0,
+            "dpi" => 1.0 / 96.0,
+            "dpcm" => 2.54 / 96.0,
+            _ => return None,
+        };
+        Some(number * factor)
+    }
+}
+
+//`400px < width` is `width > 400px`
+fn flip(op: Comparison) -> Comparison {
+    match op {
+        Comparison::Less => Comparison::Greater,
+        Comparison::LessEqual => Comparison::GreaterEqual,
+        Comparison::Greater => Comparison::Less,
+        Comparison::GreaterEqual => Comparison::LessEqual,
+        Comparison::Equal => Comparison::Equal,
+    }
+}
+
+fn tokenize(prelude: &str) -> Option<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = prelude.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' | ')' | ':' | '/' | ',' => {
+                chars.next();
+                tokens.push(match c {
+                    '(' => Token::Open,
+                    ')' => Token::Close,
+                    ':' => Token::Colon,
+                    '/' => Token::Slash,
+                    _ => Token::Comma,
+                });
+            }
+            '<' | '>' | '=' => {
+                chars.next();
+                let equal = c != '=' && chars.peek() == Some(&'=');
+                if equal {
+                    chars.next();
+                }
+                tokens.push(Token::Compare(match (c, equal) {
+                    ('<', false) => Comparison::Less,
+                    ('<', true) => Comparison::LessEqual,
+                    ('>', false) => Comparison::Greater,
+                    ('>', true) => Comparison::GreaterEqual,
+                    _ => Comparison::Equal,
+                }));
+            }
+            c if c.is_digit(10) || c == '.' || c == '-' || c == '+' => {
+                let mut number = String::new();
+                number.push(c);
+                chars.next();
+                while let Some(&d) = chars.peek() {
+                    if d.is_digit(10) || d == '.' {
+                        number.push(d);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                if number == "-" || number == "+" {
+                    return None;
+                }
+                let mut unit = String::new();
+                while let Some(&u) = chars.peek() {
+                    if u.is_alphabetic() {
+                        unit.push(u.to_ascii_lowercase());
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                tokens.push(Token::Number(number.parse().ok()?, unit));
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut ident = String::new();
+                while let Some(&i) = chars.peek() {
+                    if i.is_alphanumeric() || i == '-' || i == '_' {
+                        ident.push(i.to_ascii_lowercase());
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                tokens.push(Token::Ident(ident));
+            }
+            _ => return None,
+        }
+    }
+    Some(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csselements::{media_list_matches, Device};
+    use crate::cssparser::cssparser;
+
+    fn matches(queries: &str, width: f32, height: f32) -> bool {
+        media_list_matches(
+            &parse_media_query_list(queries),
+            &Device::new(width, height),
+        )
+    }
+
+    #[test]
+    fn media_types_and_ranges() {
+        assert!(matches("screen", 800.0, 600.0));
+        assert!(!matches("print", 800.0, 600.0));
+        assert!(matches("not print", 800.0, 600.0));
+        assert!(matches(
+            "(min-width: 600px) and (max-width: 800px)",
+            800.0,
+            600.0
+        ));
+        assert!(!matches("(min-width: 801px)", 800.0, 600.0));
+        assert!(matches("(400px < width <= 800px)", 800.0, 600.0));
+        assert!(!matches("(width > 800px)", 800.0, 600.0));
+        assert!(matches("(min-width: 40em)", 640.0, 600.0));
+    }
+
+    #[test]
+    fn conditions_and_lists() {
+        assert!(matches("print, (orientation: landscape)", 800.0, 600.0));
+        assert!(!matches("(orientation: portrait)", 800.0, 600.0));
+        assert!(matches("not all and (min-width: 900px)", 800.0, 600.0));
+        assert!(matches("(width < 500px) or (height < 700px)", 800.0, 600.0));
+        assert!(matches("(not (width < 500px))", 800.0, 600.0));
+        assert!(matches("", 800.0, 600.0));
+    }
+
+    #[test]
+    fn unknown_and_invalid_queries_do_not_match() {
+        assert!(!matches("(unknown-feature: 1)", 800.0, 600.0));
+        assert!(!matches("not (unknown-feature: 1)", 800.0, 600.0));
+        assert!(!matches("(min-width: red)", 800.0, 600.0));
+        assert!(matches("(min-width: red), screen", 800.0, 600.0));
+    }
+
+    #[test]
+    fn color_scheme_follows_the_device() {
+        let queries = parse_media_query_list("(prefers-color-scheme: dark)");
+        let mut device = Device::new(800.0, 600.0);
+        assert!(!media_list_matches(&queries, &device));
+        device.dark_color_scheme = true;
+        assert!(media_list_matches(&queries, &device));
+    }
+
+    #[test]
+    fn media_rules_apply_to_the_matching_device() {
+        let stylesheet = cssparser::new(
+            "p { width: 1px } @media (max-width: 500px) { p { width: 2px } div { width: 3px } }",
+        )
+        .parse_stylesheet();
+        assert_eq!(
+            stylesheet
+                .rules_for_device(&Device::new(800.0, 600.0))
+                .len(),
+            1
+        );
+        assert_eq!(
+            stylesheet
+                .rules_for_device(&Device::new(400.0, 600.0))
+                .len(),
+            3
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use crate::csselements::{Color,Declaration,Rule,Selector,SimpleSelector,Stylesheet,Unit,Value,match_color_by_name,match_unit};
+use crate::csselements::{Color,CssRule,Declaration,Rule,Selector,SimpleSelector,Stylesheet,Value,match_color_by_name,match_unit};
 
 use std::iter::Peekable;
 use std::str::Chars;
@@ -19,7 +19,7 @@ pub fn parse_stylesheet(&mut self)->Stylesheet{
         let styles=self.parse_declarations();
         let rule=Rule::new(selectors, styles);
 
-        stylesheet.css.push(rule);
+        stylesheet.css.push(CssRule::Style(rule));
     }
     stylesheet
 }
@@ -112,7 +112,7 @@ fn parse_declarations(&mut self)->Vec<Declaration>{
         let value=self.consume_while(|c| c!=';' && c!='\n' && c!='}').to_lowercase();
         let value_enum=match property.as_ref(){
             "background-color" | "border-color" | "color"=>{
-                Value::Color(translate_color(&value))
+                Some(Value::Color(translate_color(&value)))
             }
             "margin-right" |
             "margin-bottom" |
@@ -128,18 +128,19 @@ fn parse_declarations(&mut self)->Vec<Declaration>{
             "border-top-width" |
             "height" |
             "width" => translate_length(&value),
-            _=>Value::Other(value)
+            _=>Some(Value::Other(value))
         };
-        let declaration=Declaration::new(property, value_enum);
+        //A length that does not parse drops its declaration
+        let declaration=value_enum.map(|v| Declaration::new(property, v));
 
         if self.check_current_char(false, |c| *c == ';'){
-            declarations.push(declaration);
+            declarations.extend(declaration);
             self.chars.next();
         }
         else{
             self.parse_until_whitespace();
             if self.check_current_char(false, |c| *c == '}'){
-                declarations.push(declaration)
+                declarations.extend(declaration)
             }
         }
         self.parse_until_whitespace();
@@ -167,7 +168,7 @@ fn consume_while<F>(&mut self,condition:F)->String where F: Fn(char)->bool,{
 } //End of implementation
 
 
-fn translate_length(value:&str)->Value{
+fn translate_length(value:&str)->Option<Value>{
     let mut num_str=String::new();
     let mut unit=String::new();
     let mut parsing_num=true;
@@ -181,8 +182,8 @@ fn translate_length(value:&str)->Value{
             parsing_num=false;
         }
     }
-    let parsed_number=num_str.parse().unwrap_or(0.0);
-    Value::Length(parsed_number,match_unit(unit.as_ref()))
+    let parsed_number=num_str.parse().ok()?;
+    Some(Value::Length(parsed_number,match_unit(unit.as_ref())?))
 }
 
 fn translate_color(color:&str)->Color{
@@ -1,70 +1,479 @@
+use crate::csscolor;
 use crate::csselements::{
-    match_color_by_name, match_unit, Color, Declaration, Rule, Selector, SimpleSelector,
-    Stylesheet, Unit, Value,
+    match_unit, CssRule, Declaration, Display, ImportRule, Keyframe, KeyframesRule, MathType,
+    MediaRule, PseudoClass, RelativeSelector, Rule, Selector, SimpleSelector, Stylesheet,
+    SupportsCondition, SupportsRule, Unit, Value, VarValue,
 };
+use crate::cssfonts;
+use crate::cssmath;
+use crate::cssmedia;
+use crate::cssproperties::{self, Grammar, CSS_WIDE_KEYWORDS};
+use crate::cssshorthands;
+use crate::csssupports;
+use crate::cssvalues;
+use crate::cssvariables;
 
+use std::collections::HashMap;
 use std::iter::Peekable;
+use std::mem;
 use std::str::Chars;
 
 pub struct cssparser<'a> {
     chars: Peekable<Chars<'a>>,
+    namespaces: HashMap<String, String>,
+    default_namespace: Option<String>,
+    //@import is only valid before any other rule
+    imports_allowed: bool,
+    //Selectors of the style rule whose block is being parsed,& refers to them
+    nesting_parent: Option<Vec<Selector>>,
+    nesting_used: bool,
 }
 impl<'a> cssparser<'a> {
     pub fn new(full_css: &str) -> cssparser {
         cssparser {
             chars: full_css.chars().peekable(),
+            namespaces: HashMap::new(),
+            default_namespace: None,
+            imports_allowed: true,
+            nesting_parent: None,
+            nesting_used: false,
         }
     }
 
     pub fn parse_stylesheet(&mut self) -> Stylesheet {
         let mut stylesheet = Stylesheet::default();
 
-        while self.chars.peek().is_some() {
-            let selectors = self.parse_selectors();
-            let styles = self.parse_declarations();
-            let rule = Rule::new(selectors, styles);
+        stylesheet.css = self.parse_rules(false);
+        stylesheet.namespaces = self.namespaces.clone();
+        stylesheet.default_namespace = self.default_namespace.clone();
+        stylesheet
+    }
+
+    //Parses rules up to the end of input,or up to the closing brace of a nested block
+    fn parse_rules(&mut self, nested: bool) -> Vec<CssRule> {
+        let mut rules = Vec::new();
+
+        loop {
+            self.consume_while(char::is_whitespace);
+            match self.chars.peek() {
+                None => break,
+                Some(&'}') if nested => {
+                    self.chars.next();
+                    break;
+                }
+                Some(&'@') => {
+                    self.chars.next();
+                    if let Some(rule) = self.parse_at_rule() {
+                        rules.push(rule);
+                    }
+                }
+                _ => {
+                    self.imports_allowed = false;
+                    let selectors = self.parse_selectors();
+                    rules.append(&mut self.parse_style_block(selectors));
+                }
+            }
+        }
+        rules
+    }
 
-            stylesheet.css.push(rule);
+    fn parse_at_rule(&mut self) -> Option<CssRule> {
+        let name = self.parse_identifier();
+        self.consume_while(char::is_whitespace);
+
+        match name.as_ref() {
+            "import" if self.imports_allowed => return self.parse_import_rule(),
+            "charset" => {
+                self.skip_at_rule();
+                return None;
+            }
+            _ => {}
         }
-        stylesheet
+        self.imports_allowed = false;
+
+        match name.as_ref() {
+            "namespace" => {
+                self.parse_namespace_rule();
+                None
+            }
+            "media" => self.parse_media_rule(None),
+            "supports" => self.parse_supports_rule(None),
+            "font-face" => self.parse_font_face_rule(),
+            "keyframes" | "-webkit-keyframes" => self.parse_keyframes_rule(),
+            _ => {
+                self.skip_at_rule();
+                None
+            }
+        }
+    }
+
+    //@import url(...)|"..." <media query list>;
+    fn parse_import_rule(&mut self) -> Option<CssRule> {
+        let url = match self.chars.peek() {
+            Some(&'"') | Some(&'\'') => Some(self.parse_string()),
+            Some(&c) if is_valid_start_indent(c) => {
+                if self.parse_identifier() == "url" {
+                    self.parse_url_function()
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+        let prelude = self.consume_while(|c| c != ';' && c != '{');
+        if self.chars.peek() == Some(&'{') {
+            self.skip_at_rule();
+            return None;
+        }
+        self.chars.next();
+
+        let queries = if prelude.trim().is_empty() {
+            Vec::new()
+        } else {
+            cssmedia::parse_media_query_list(&prelude)
+        };
+        url.map(|url| CssRule::Import(ImportRule { url, queries }))
+    }
+
+    //parent is set for a rule nested in a style rule,see parse_block_contents
+    fn parse_media_rule(&mut self, parent: Option<&[Selector]>) -> Option<CssRule> {
+        let prelude = self.consume_while(|c| c != '{' && c != ';');
+        if self.chars.next() != Some('{') {
+            return None;
+        }
+        let queries = cssmedia::parse_media_query_list(&prelude);
+        let rules = self.parse_block_contents(parent);
+        Some(CssRule::Media(MediaRule { queries, rules }))
+    }
+
+    //@supports <condition> { rules },a prelude that does not parse drops the whole block
+    fn parse_supports_rule(&mut self, parent: Option<&[Selector]>) -> Option<CssRule> {
+        let prelude = self.consume_while(|c| c != '{' && c != ';');
+        if self.chars.next() != Some('{') {
+            return None;
+        }
+        let rules = self.parse_block_contents(parent);
+        let condition = csssupports::parse_supports_condition(&prelude)?;
+        let supported = self.supports(&condition);
+        Some(CssRule::Supports(SupportsRule {
+            condition,
+            supported,
+            rules,
+        }))
+    }
+
+    //A declaration is supported when the registry accepts its value,a selector when it parses
+    //into a selector that can be matched
+    pub fn supports(&self, condition: &SupportsCondition) -> bool {
+        match *condition {
+            SupportsCondition::Declaration(ref name, ref value) => {
+                let mut value = value.clone();
+                strip_important(&mut value);
+                if cssvariables::is_custom_property(name) {
+                    return true;
+                }
+                let name = name.to_lowercase();
+                if cssvariables::contains_var(&value) {
+                    return is_known_property(&name);
+                }
+                let expected = cssshorthands::get_longhands(&name).map_or(1, |l| l.len());
+                let parsed = parse_property_value(&name, &cssvalues::fold_case(&value));
+                !parsed.is_empty() && parsed.len() == expected
+            }
+            SupportsCondition::Selector(ref text) => {
+                let mut parser = cssparser::new(text);
+                parser.namespaces = self.namespaces.clone();
+                parser.default_namespace = self.default_namespace.clone();
+                let selector = parser.parse_single_selector();
+                parser.consume_while(char::is_whitespace);
+                selector != Selector::default() && parser.chars.peek().is_none()
+            }
+            SupportsCondition::Not(ref c) => !self.supports(c),
+            SupportsCondition::And(ref list) => list.iter().all(|c| self.supports(c)),
+            SupportsCondition::Or(ref list) => list.iter().any(|c| self.supports(c)),
+            SupportsCondition::Unknown(_) => false,
+        }
+    }
+
+    //Inside a style rule the block of a conditional rule holds declarations for the parent
+    //selectors and further nested rules,at the top level it holds ordinary rules
+    fn parse_block_contents(&mut self, parent: Option<&[Selector]>) -> Vec<CssRule> {
+        match parent {
+            Some(selectors) => self.parse_style_block(Some(selectors.to_vec())),
+            None => self.parse_rules(true),
+        }
+    }
+
+    //@font-face { descriptor: value; ... },a face without a family or a usable src is dropped
+    fn parse_font_face_rule(&mut self) -> Option<CssRule> {
+        self.consume_while(|c| c != '{' && c != ';');
+        if self.chars.next() != Some('{') {
+            return None;
+        }
+        let mut descriptors = Vec::new();
+        loop {
+            self.consume_while(|c| c.is_whitespace() || c == ';');
+            match self.chars.peek() {
+                None => break,
+                Some(&'}') => {
+                    self.chars.next();
+                    break;
+                }
+                _ => {}
+            }
+            let name = self.consume_while(|c| c != ':' && c != ';' && c != '}');
+            if self.chars.peek() != Some(&':') {
+                continue;
+            }
+            self.chars.next();
+            let value = self.consume_while(|c| c != ';' && c != '}');
+            descriptors.push((name.trim().to_lowercase(), value.trim().to_string()));
+        }
+        cssfonts::parse_font_face(&descriptors).map(CssRule::FontFace)
+    }
+
+    //@keyframes name { <keyframe selectors> { declarations } ... }
+    fn parse_keyframes_rule(&mut self) -> Option<CssRule> {
+        let prelude = self.consume_while(|c| c != '{' && c != ';');
+        if self.chars.next() != Some('{') {
+            return None;
+        }
+        //Folded like the identifiers of animation-name,so that the two keep matching
+        let name = match cssvalues::parse_component_list(&cssvalues::fold_case(prelude.trim())) {
+            Some(Value::Ident(ref n)) if n != "none" && !CSS_WIDE_KEYWORDS.contains(&n.as_str()) => {
+                Some(n.clone())
+            }
+            Some(Value::String(n)) => Some(n),
+            _ => None,
+        };
+
+        let mut keyframes = Vec::new();
+        loop {
+            self.consume_while(char::is_whitespace);
+            match self.chars.peek() {
+                None => break,
+                Some(&'}') => {
+                    self.chars.next();
+                    break;
+                }
+                _ => {}
+            }
+            let selectors = self.consume_while(|c| c != '{' && c != '}');
+            if self.chars.next() != Some('{') {
+                break;
+            }
+            //Declarations marked !important are ignored in keyframes
+            let declarations = self
+                .parse_declarations()
+                .into_iter()
+                .filter(|d| !d.important)
+                .collect();
+            let offsets = selectors
+                .split(',')
+                .map(|s| match s.trim().to_lowercase().as_ref() {
+                    "from" => Some(0.0),
+                    "to" => Some(1.0),
+                    s => match s.strip_suffix('%')?.parse::<f32>() {
+                        Ok(p) if (0.0..=100.0).contains(&p) => Some(p / 100.0),
+                        _ => None,
+                    },
+                })
+                .collect::<Option<Vec<f32>>>();
+            if let Some(offsets) = offsets {
+                keyframes.push(Keyframe {
+                    offsets,
+                    declarations,
+                });
+            }
+        }
+        name.map(|name| CssRule::Keyframes(KeyframesRule { name, keyframes }))
+    }
+
+    //@namespace prefix? url(...)|"...";
+    fn parse_namespace_rule(&mut self) {
+        let mut prefix = None;
+        let mut url = None;
+
+        if self.chars.peek().map_or(false, |c| is_valid_start_indent(*c)) {
+            let ident = self.parse_identifier();
+            if ident == "url" && self.chars.peek().map_or(false, |c| *c == '(') {
+                url = self.parse_url_function();
+            } else {
+                prefix = Some(ident);
+            }
+        }
+        self.consume_while(char::is_whitespace);
+        if url.is_none() {
+            url = match self.chars.peek() {
+                Some(&'"') | Some(&'\'') => Some(self.parse_string()),
+                Some(&'u') | Some(&'U') => {
+                    self.parse_identifier();
+                    self.parse_url_function()
+                }
+                _ => None,
+            };
+        }
+        self.consume_while(|c| c != ';');
+        self.chars.next();
+
+        match (prefix, url) {
+            (Some(p), Some(u)) => {
+                self.namespaces.insert(p, u);
+            }
+            (None, Some(u)) => self.default_namespace = Some(u),
+            _ => {}
+        }
+    }
+
+    //Skips an unsupported at-rule,up to its ';' or the end of its block
+    fn skip_at_rule(&mut self) {
+        self.consume_while(|c| c != ';' && c != '{');
+        if self.chars.next() == Some('{') {
+            self.skip_block();
+        }
+    }
+
+    //Skips the rest of a block whose opening brace has been consumed
+    fn skip_block(&mut self) {
+        let mut depth = 1;
+        while depth > 0 {
+            match self.chars.next() {
+                Some('{') => depth += 1,
+                Some('}') => depth -= 1,
+                Some(_) => {}
+                None => break,
+            }
+        }
+    }
+
+    //Reads the argument of url(...) once the name has been consumed
+    fn parse_url_function(&mut self) -> Option<String> {
+        if self.chars.peek().map_or(true, |c| *c != '(') {
+            return None;
+        }
+        self.chars.next();
+        self.consume_while(char::is_whitespace);
+        let url = match self.chars.peek() {
+            Some(&'"') | Some(&'\'') => self.parse_string(),
+            _ => self
+                .consume_while(|c| c != ')' && !c.is_whitespace())
+                .to_string(),
+        };
+        self.consume_while(|c| c != ')');
+        self.chars.next();
+        Some(url)
     }
-    fn parse_selectors(&mut self) -> Vec<Selector> {
+
+    fn parse_string(&mut self) -> String {
+        let quote = self.chars.next().unwrap_or('"');
+        let result = self.consume_while(|c| c != quote);
+        self.chars.next();
+        result
+    }
+    //None when the list is empty or one of its selectors is invalid,the whole rule is then dropped
+    fn parse_selectors(&mut self) -> Option<Vec<Selector>> {
         let mut selectors = Vec::new();
+        let mut valid = true;
 
         while self.chars.peek().map_or(false, |c| *c != '{') {
-            let selector = self.parse_single_selector();
+            let selector = match self.nesting_parent.clone() {
+                Some(parent) => self.parse_nested_selector(&parent),
+                None => self.parse_single_selector(),
+            };
 
             if selector != Selector::default() {
                 selectors.push(selector);
+            } else {
+                valid = false;
+                self.consume_while(|c| c != ',' && c != '{');
             }
 
             self.consume_while(char::is_whitespace);
             if self.chars.peek().map_or(false, |c| *c == ',') {
                 self.chars.next();
+                self.consume_while(char::is_whitespace);
+                //A trailing comma leaves an empty selector in front of the block
+                if self.chars.peek().map_or(true, |c| *c == '{') {
+                    valid = false;
+                }
             }
         }
 
         self.chars.next();
-        selectors
+        if valid && !selectors.is_empty() {
+            Some(selectors)
+        } else {
+            None
+        }
+    }
+
+    //A nested selector that does not contain & or starts with a combinator is relative to the
+    //parent,as if it was written after "& ".& stands for :is(<parent selectors>).
+    fn parse_nested_selector(&mut self, parent: &[Selector]) -> Selector {
+        self.consume_while(char::is_whitespace);
+        let combinator = match self.chars.peek() {
+            Some(&c) if c == '>' || c == '+' || c == '~' => {
+                self.chars.next();
+                Some(c)
+            }
+            _ => None,
+        };
+        self.nesting_used = false;
+        let mut selector = self.parse_single_selector();
+        if selector == Selector::default() {
+            return selector;
+        }
+        if combinator.is_some() || !self.nesting_used {
+            let mut nesting = SimpleSelector::default();
+            nesting
+                .pseudo_classes
+                .push(PseudoClass::Is(parent.to_vec()));
+            selector.simple.insert(0, nesting);
+            selector.combinators.insert(0, combinator.unwrap_or(' '));
+        }
+        desugar_nesting(selector, parent)
     }
 
     fn parse_single_selector(&mut self) -> Selector {
-        let mut sselector = SimpleSelector::default();
         let mut selector = Selector::default();
 
-        self.consume_while(char::is_whitespace);
+        loop {
+            self.consume_while(char::is_whitespace);
+            let sselector = match self.parse_compound_selector() {
+                Some(s) => s,
+                None => return Selector::default(),
+            };
+            selector.simple.push(sselector);
 
-        sselector.tag_name = match self.chars.peek() {
-            Some(&c) if is_valid_start_indent(c) => Some(self.parse_identifier()),
-            _ => None,
-        };
+            let whitespace = self.consume_while(char::is_whitespace);
+            match self.chars.peek() {
+                Some(&c) if c == '>' || c == '+' || c == '~' => {
+                    self.chars.next();
+                    selector.combinators.push(c);
+                }
+                Some(&c) if c != ',' && c != '{' && c != ')' && whitespace.len() > 0 => {
+                    selector.combinators.push(' ');
+                }
+                _ => break,
+            }
+        }
+
+        selector
+    }
+
+    //Parses one compound selector such as div#main.a:not(.b),returns None if it is invalid
+    fn parse_compound_selector(&mut self) -> Option<SimpleSelector> {
+        let mut sselector = SimpleSelector::default();
+
+        if !self.parse_type_selector(&mut sselector) {
+            self.consume_while(|c| c != ',' && c != '{');
+            return None;
+        }
 
         let mut multiple_ids = false;
-        while self
-            .chars
-            .peek()
-            .map_or(false, |c| *c != ',' && *c != '{' && !(*c).is_whitespace())
-        {
+        while self.chars.peek().map_or(false, |c| {
+            !is_selector_separator(*c) && !(*c).is_whitespace()
+        }) {
             match self.chars.peek() {
                 Some(&c) if c == '#' => {
                     self.chars.next();
@@ -84,17 +493,200 @@ impl<'a> cssparser<'a> {
                         sselector.classes.push(class_name);
                     }
                 }
+                Some(&c) if c == ':' => {
+                    self.chars.next();
+                    let pseudo = self.parse_pseudo_class()?;
+                    sselector.pseudo_classes.push(pseudo);
+                }
+                Some(&'&') if self.nesting_parent.is_some() => {
+                    self.chars.next();
+                    let parent = self.nesting_parent.clone().unwrap_or_default();
+                    sselector.pseudo_classes.push(PseudoClass::Is(parent));
+                    self.nesting_used = true;
+                }
                 _ => {
                     self.consume_while(|c| c != ',' && c != '{');
+                    return None;
                 }
             }
         }
 
-        if sselector != SimpleSelector::default() {
-            selector.simple.push(sselector);
+        if !sselector.universal
+            && sselector.tag_name.is_none()
+            && sselector.id.is_none()
+            && sselector.classes.is_empty()
+            && sselector.pseudo_classes.is_empty()
+        {
+            return None;
         }
+        Some(sselector)
+    }
 
-        selector
+    //Parses an optional ns|type or * prefix,returns false for an undeclared prefix
+    fn parse_type_selector(&mut self, sselector: &mut SimpleSelector) -> bool {
+        let first = match self.chars.peek() {
+            Some(&'*') => {
+                self.chars.next();
+                Some(String::from("*"))
+            }
+            Some(&c) if is_valid_start_indent(c) => Some(self.parse_identifier()),
+            Some(&'|') => Some(String::new()),
+            _ => None,
+        };
+        let first = match first {
+            Some(f) => f,
+            None => {
+                sselector.namespace = self.default_namespace.clone();
+                return true;
+            }
+        };
+
+        let name = if self.chars.peek().map_or(false, |c| *c == '|') {
+            self.chars.next();
+            sselector.namespace = match first.as_ref() {
+                "*" => None,
+                "" => Some(String::new()),
+                prefix => match self.namespaces.get(prefix) {
+                    Some(url) => Some(url.clone()),
+                    None => return false,
+                },
+            };
+            match self.chars.peek() {
+                Some(&'*') => {
+                    self.chars.next();
+                    String::from("*")
+                }
+                Some(&c) if is_valid_start_indent(c) => self.parse_identifier(),
+                _ => return false,
+            }
+        } else if first.is_empty() {
+            return false;
+        } else {
+            sselector.namespace = self.default_namespace.clone();
+            first
+        };
+
+        if name == "*" {
+            sselector.universal = true;
+        } else {
+            sselector.tag_name = Some(name);
+        }
+        true
+    }
+
+    //An unknown pseudo-class makes the selector invalid
+    fn parse_pseudo_class(&mut self) -> Option<PseudoClass> {
+        let name = self.parse_identifier();
+        if self.chars.peek().map_or(true, |c| *c != '(') {
+            let nth = |of_type, from_end| PseudoClass::Nth {
+                a: 0,
+                b: 1,
+                of_type,
+                from_end,
+            };
+            return match name.as_ref() {
+                "root" => Some(PseudoClass::Root),
+                "empty" => Some(PseudoClass::Empty),
+                "first-child" => Some(nth(false, false)),
+                "last-child" => Some(nth(false, true)),
+                "first-of-type" => Some(nth(true, false)),
+                "last-of-type" => Some(nth(true, true)),
+                "only-child" => Some(PseudoClass::Only { of_type: false }),
+                "only-of-type" => Some(PseudoClass::Only { of_type: true }),
+                state if UNTRACKED_STATES.contains(&state) => Some(PseudoClass::State(name)),
+                _ => None,
+            };
+        }
+        self.chars.next();
+
+        let pseudo = match name.as_ref() {
+            "not" => PseudoClass::Not(self.parse_selector_arguments(false)?),
+            "is" | "matches" => PseudoClass::Is(self.parse_selector_arguments(true)?),
+            "where" => PseudoClass::Where(self.parse_selector_arguments(true)?),
+            "has" => PseudoClass::Has(self.parse_relative_arguments()?),
+            "nth-child" | "nth-last-child" | "nth-of-type" | "nth-last-of-type" => {
+                let argument = self.consume_while(|c| c != ')');
+                self.chars.next();
+                let (a, b) = parse_an_plus_b(&argument)?;
+                PseudoClass::Nth {
+                    a,
+                    b,
+                    of_type: name.ends_with("of-type"),
+                    from_end: name.starts_with("nth-last"),
+                }
+            }
+            _ => {
+                self.consume_while(|c| c != ')');
+                self.chars.next();
+                return None;
+            }
+        };
+        Some(pseudo)
+    }
+
+    //Parses a selector list up to the closing parenthesis.
+    //Forgiving lists (:is,:where) drop invalid entries,other lists fail as a whole.
+    fn parse_selector_arguments(&mut self, forgiving: bool) -> Option<Vec<Selector>> {
+        let mut selectors = Vec::new();
+        let mut valid = true;
+
+        loop {
+            let selector = self.parse_single_selector();
+            if selector != Selector::default() {
+                selectors.push(selector);
+            } else {
+                valid = false;
+                self.consume_while(|c| c != ',' && c != ')' && c != '{');
+            }
+
+            self.consume_while(char::is_whitespace);
+            match self.chars.peek() {
+                Some(&',') => {
+                    self.chars.next();
+                }
+                Some(&')') => {
+                    self.chars.next();
+                    break;
+                }
+                _ => return None,
+            }
+        }
+
+        if !valid && !forgiving {
+            return None;
+        }
+        Some(selectors)
+    }
+
+    fn parse_relative_arguments(&mut self) -> Option<Vec<RelativeSelector>> {
+        let mut selectors = Vec::new();
+
+        loop {
+            self.consume_while(char::is_whitespace);
+            let combinator = match self.chars.peek() {
+                Some(&c) if c == '>' || c == '+' || c == '~' => {
+                    self.chars.next();
+                    c
+                }
+                _ => ' ',
+            };
+            let selector = self.parse_single_selector();
+            if selector == Selector::default() {
+                return None;
+            }
+            selectors.push(RelativeSelector {
+                combinator,
+                selector,
+            });
+
+            self.consume_while(char::is_whitespace);
+            match self.chars.next() {
+                Some(',') => {}
+                Some(')') => break,
+                _ => return None,
+            }
+        }
+        Some(selectors)
     }
 
     fn parse_identifier(&mut self) -> String {
@@ -118,58 +710,185 @@ impl<'a> cssparser<'a> {
         }
     }
 
-    fn parse_declarations(&mut self) -> Vec<Declaration> {
-        let mut declarations = Vec::<Declaration>::new();
+    //Parses the block of a style rule up to its closing brace.Nested style rules and
+    //conditional rules are desugared into flat rules that follow the parent rule,
+    //declarations that come after them are kept in place in a rule of their own.
+    //A rule one of whose selectors is invalid is dropped with its block.
+    fn parse_style_block(&mut self, selectors: Option<Vec<Selector>>) -> Vec<CssRule> {
+        let selectors = match selectors {
+            Some(selectors) => selectors,
+            None => {
+                self.skip_block();
+                return Vec::new();
+            }
+        };
+        let mut rules = Vec::new();
+        let mut declarations = Vec::new();
 
-        while self.chars.peek().map_or(false, |c| *c != '}') {
+        loop {
             self.consume_while(char::is_whitespace);
+            let nested = match self.chars.peek().cloned() {
+                None => break,
+                Some('}') => {
+                    self.chars.next();
+                    break;
+                }
+                Some('@') => {
+                    self.chars.next();
+                    let name = self.parse_identifier();
+                    self.consume_while(char::is_whitespace);
+                    match name.as_ref() {
+                        "media" => self.parse_media_rule(Some(&selectors)).map(|r| vec![r]),
+                        "supports" => self.parse_supports_rule(Some(&selectors)).map(|r| vec![r]),
+                        _ => {
+                            self.skip_at_rule();
+                            None
+                        }
+                    }
+                }
+                _ if self.at_nested_rule() => {
+                    let outer = self.nesting_parent.replace(selectors.clone());
+                    let nested_selectors = self.parse_selectors();
+                    self.nesting_parent = outer;
+                    let nested_rules = self.parse_style_block(nested_selectors);
+                    if nested_rules.is_empty() {
+                        None
+                    } else {
+                        Some(nested_rules)
+                    }
+                }
+                _ => {
+                    self.parse_declaration(&mut declarations);
+                    continue;
+                }
+            };
+            if let Some(mut nested) = nested {
+                if !declarations.is_empty() {
+                    let rule = Rule::new(selectors.clone(), mem::take(&mut declarations));
+                    rules.push(CssRule::Style(rule));
+                }
+                rules.append(&mut nested);
+            }
+        }
 
-            let property = self.consume_while(|x| x != ':').to_lowercase();
+        if !declarations.is_empty() {
+            rules.push(CssRule::Style(Rule::new(selectors, declarations)));
+        }
+        rules
+    }
 
-            self.chars.next();
-            self.consume_while(char::is_whitespace);
+    //A nested style rule reaches its block before anything could end a declaration
+    fn at_nested_rule(&self) -> bool {
+        let mut chars = self.chars.clone();
+        if chars.clone().take(2).eq("--".chars()) {
+            return false;
+        }
+        let mut depth = 0;
+        while let Some(c) = chars.next() {
+            match c {
+                '"' | '\'' => while chars.next().map_or(false, |n| n != c) {},
+                '(' | '[' => depth += 1,
+                ')' | ']' => depth -= 1,
+                '{' if depth == 0 => return true,
+                ';' | '}' if depth == 0 => return false,
+                _ => {}
+            }
+        }
+        false
+    }
 
-            let value = self
-                .consume_while(|x| x != ';' && x != '\n' && x != '}')
-                .to_lowercase();
+    fn parse_declarations(&mut self) -> Vec<Declaration> {
+        let mut declarations = Vec::<Declaration>::new();
 
-            let value_enum = match property.as_ref() {
-                "background-color" | "border-color" | "color" => {
-                    Value::Color(translate_color(&value))
-                }
-                "margin-right"
-                | "margin-bottom"
-                | "margin-left"
-                | "margin-top"
-                | "padding-right"
-                | "padding-bottom"
-                | "padding-left"
-                | "padding-top"
-                | "border-right-width"
-                | "border-bottom-width"
-                | "border-left-width"
-                | "border-top-width"
-                | "height"
-                | "width" => translate_length(&value),
-                _ => Value::Other(value),
-            };
+        while self.chars.peek().map_or(false, |c| *c != '}') {
+            self.parse_declaration(&mut declarations);
+        }
 
-            let declaration = Declaration::new(property, value_enum);
+        self.chars.next();
+        declarations
+    }
 
-            if self.chars.peek().map_or(false, |c| *c == ';') {
-                declarations.push(declaration);
+    //Parses one property: value pair and appends its longhands to declarations
+    fn parse_declaration(&mut self, declarations: &mut Vec<Declaration>) {
+        self.consume_while(char::is_whitespace);
+
+        let mut property = self
+            .consume_while(|x| x != ':' && x != ';' && x != '}')
+            .trim_end()
+            .to_string();
+        if self.chars.peek() != Some(&':') {
+            if self.chars.peek() == Some(&';') {
                 self.chars.next();
-            } else {
-                self.consume_while(char::is_whitespace);
-                if self.chars.peek().map_or(false, |c| *c == '}') {
-                    declarations.push(declaration);
-                }
             }
             self.consume_while(char::is_whitespace);
+            return;
+        }
+        if !cssvariables::is_custom_property(&property) {
+            property = property.to_lowercase();
         }
 
         self.chars.next();
-        declarations
+        self.consume_while(char::is_whitespace);
+
+        //Custom properties and var() references are case sensitive,so they are kept as written
+        let mut value = self
+            .consume_while(|x| x != ';' && x != '\n' && x != '}')
+            .trim_end()
+            .to_string();
+        let important = strip_important(&mut value);
+
+        let longhands = if cssvariables::is_custom_property(&property) {
+            vec![(property, Value::Custom(value))]
+        } else if cssvariables::contains_var(&value) && is_known_property(&property) {
+            let shorthand = cssshorthands::get_longhands(&property).map(|l| (property.clone(), l));
+            match shorthand {
+                Some((shorthand, longhands)) => longhands
+                    .into_iter()
+                    .map(|longhand| {
+                        let var = VarValue {
+                            css: value.clone(),
+                            shorthand: Some(shorthand.clone()),
+                        };
+                        (longhand, Value::Var(var))
+                    })
+                    .collect(),
+                None => {
+                    let var = VarValue {
+                        css: value,
+                        shorthand: None,
+                    };
+                    vec![(property, Value::Var(var))]
+                }
+            }
+        } else if !is_known_property(&property) {
+            //Kept as written for the serializer,the cascade ignores properties it does not know
+            match cssvalues::is_identifier(&property) && !value.is_empty() {
+                true => vec![(property, Value::Other(value))],
+                false => Vec::new(),
+            }
+        } else {
+            parse_property_value(&property, &cssvalues::fold_case(&value))
+        };
+        let mut declaration = longhands
+            .into_iter()
+            .map(|(name, value)| {
+                let mut declaration = Declaration::new(name, value);
+                declaration.important = important;
+                declaration
+            })
+            .collect::<Vec<Declaration>>();
+
+        if self.chars.peek().map_or(false, |c| *c == ';') {
+            declarations.append(&mut declaration);
+            self.chars.next();
+        } else {
+            //The end of input closes a style attribute,or an unfinished block
+            self.consume_while(char::is_whitespace);
+            if self.chars.peek().map_or(true, |c| *c == '}') {
+                declarations.append(&mut declaration);
+            }
+        }
+        self.consume_while(char::is_whitespace);
     }
 
     fn consume_while<F>(&mut self, condition: F) -> String
@@ -185,63 +904,270 @@ impl<'a> cssparser<'a> {
     }
 }
 
-fn translate_length(value: &str) -> Value {
-    let mut num_str = String::new();
-    let mut unit = String::new();
-    let mut parsing_num = true;
+//Removes a trailing !important from value and reports whether it was there
+fn strip_important(value: &mut String) -> bool {
+    let trimmed = value.trim_end().to_string();
+    match trimmed.rfind('!') {
+        Some(i)
+            if trimmed[i + 1..]
+                .trim_start()
+                .eq_ignore_ascii_case("important") =>
+        {
+            *value = trimmed[..i].trim_end().to_string();
+            true
+        }
+        _ => false,
+    }
+}
+
+//Declarations of a style attribute,which are not wrapped in braces
+pub fn parse_declaration_list(css: &str) -> Vec<Declaration> {
+    cssparser::new(css).parse_declarations()
+}
+
+//Parses a declaration value into the values of its longhands,empty if the value is invalid
+pub fn parse_property_value(property: &str, value: &str) -> Vec<(String, Value)> {
+    let longhands = match cssshorthands::get_longhands(property) {
+        Some(_) => cssshorthands::expand_shorthand(property, value).unwrap_or_default(),
+        None => vec![(property.to_string(), value.to_string())],
+    };
+    longhands
+        .into_iter()
+        .filter_map(|(name, value)| {
+            let value = translate_value(&name, value)?;
+            Some((name, value))
+        })
+        .collect()
+}
+
+//None when the property is unknown or the value does not match its grammar,the declaration is then dropped
+fn translate_value(property: &str, value: String) -> Option<Value> {
+    let definition = cssproperties::get_property(property)?;
+    if let Some(keyword) = CSS_WIDE_KEYWORDS.iter().find(|k| **k == value) {
+        return Some(Value::Keyword(keyword));
+    }
+
+    match definition.grammar {
+        Grammar::Color if value == "currentcolor" => Some(Value::Keyword("currentcolor")),
+        Grammar::Color => csscolor::parse_color(&value).map(Value::Color),
+        Grammar::Length {
+            percentage,
+            negative,
+            auto,
+            keywords,
+        } => {
+            if auto && value == "auto" {
+                return Some(Value::Keyword("auto"));
+            }
+            if let Some(&(_, px)) = keywords.iter().find(|k| k.0 == value) {
+                return Some(Value::Length(px, Unit::Px));
+            }
+            if cssmath::is_math_function(&value) {
+                return translate_math(&value, percentage);
+            }
+            match translate_length(&value)? {
+                Value::Length(_, Unit::Percent) if !percentage => None,
+                Value::Length(n, _) if n < 0.0 && !negative => None,
+                length => Some(length),
+            }
+        }
+        Grammar::Keyword(keywords) => keywords
+            .iter()
+            .find(|k| **k == value)
+            .map(|k| Value::Keyword(k)),
+        Grammar::Display => Display::from_keyword(&value).map(Value::Display),
+        Grammar::Number => {
+            let number = match value.strip_suffix('%') {
+                Some(percentage) => percentage.parse::<f32>().ok()? / 100.0,
+                None => value.parse::<f32>().ok()?,
+            };
+            if number.is_finite() {
+                Some(Value::Number(number))
+            } else {
+                None
+            }
+        }
+        Grammar::Components(check) => cssvalues::parse_component_list(&value).filter(|v| check(v)),
+    }
+}
+
+fn is_known_property(name: &str) -> bool {
+    cssproperties::get_property(name).is_some() || cssshorthands::get_longhands(name).is_some()
+}
+
+//A math function has to produce a length,or a percentage where the property accepts one.
+//Expressions that only use px are folded to a plain length.
+fn translate_math(value: &str, percentage: bool) -> Option<Value> {
+    let expression = cssmath::parse_math_function(value)?;
+    match expression.math_type()? {
+        MathType::Length => {}
+        MathType::Percentage | MathType::LengthPercentage if percentage => {}
+        _ => return None,
+    }
+    if expression.is_absolute() {
+        let px = expression.evaluate(&|n, _| n);
+        return if px.is_finite() {
+            Some(Value::Length(px, Unit::Px))
+        } else {
+            None
+        };
+    }
+    Some(Value::Math(expression))
+}
 
-    for c in value.chars() {
-        if c.is_numeric() && parsing_num {
-            num_str.push(c);
+//<number><unit> with an optional sign,decimals and exponent.A unitless zero is accepted.
+fn translate_length(value: &str) -> Option<Value> {
+    let (number, unit) = split_number(value)?;
+    let parsed_number = number.parse::<f32>().ok().filter(|n| n.is_finite())?;
+    if unit.is_empty() {
+        return if parsed_number == 0.0 {
+            Some(Value::Length(0.0, Unit::Px))
         } else {
-            unit.push(c);
-            parsing_num = false;
+            None
+        };
+    }
+    Some(Value::Length(parsed_number, match_unit(unit)?))
+}
+
+//Splits the longest prefix that follows the CSS number grammar from the rest of value,
+//None when value does not start with a number
+fn split_number(value: &str) -> Option<(&str, &str)> {
+    let bytes = value.as_bytes();
+    let digits = |mut i: usize| {
+        while i < bytes.len() && bytes[i].is_ascii_digit() {
+            i += 1;
+        }
+        i
+    };
+
+    let mut end = 0;
+    if end < bytes.len() && (bytes[end] == b'+' || bytes[end] == b'-') {
+        end += 1;
+    }
+    let integer_end = digits(end);
+    let mut number_end = integer_end;
+    if number_end < bytes.len() && bytes[number_end] == b'.' {
+        let fraction_end = digits(number_end + 1);
+        if fraction_end > number_end + 1 {
+            number_end = fraction_end;
+        }
+    }
+    if number_end == end {
+        return None;
+    }
+    end = number_end;
+    if end < bytes.len() && (bytes[end] == b'e' || bytes[end] == b'E') {
+        let mut exponent = end + 1;
+        if exponent < bytes.len() && (bytes[exponent] == b'+' || bytes[exponent] == b'-') {
+            exponent += 1;
+        }
+        let exponent_end = digits(exponent);
+        if exponent_end > exponent {
+            end = exponent_end;
         }
     }
-    let parsed_number = num_str.parse().unwrap_or(0.0);
-    Value::Length(parsed_number, match_unit(unit.as_ref()))
+    Some((&value[..end], &value[end..]))
 }
 
-fn translate_color(color: &str) -> Color {
-    if color.starts_with("#") {
-        if color.len() == 7 {
-            let red = match u8::from_str_radix(&color[1..3], 16) {
-                Ok(n) => n as f32 / 255.0,
-                Err(_) => 0.0,
-            };
-            let green = match u8::from_str_radix(&color[3..5], 16) {
-                Ok(n) => n as f32 / 255.0,
-                Err(_) => 0.0,
-            };
-            let blue = match u8::from_str_radix(&color[5..7], 16) {
-                Ok(n) => n as f32 / 255.0,
-                Err(_) => 0.0,
-            };
-            return Color::new(red, green, blue, 1.0);
-        } else if color.len() == 4 {
-            let red = match u8::from_str_radix(&color[1..2], 16) {
-                Ok(n) => n as f32 / 15.0,
-                Err(_) => 0.0,
-            };
-            let green = match u8::from_str_radix(&color[2..3], 16) {
-                Ok(n) => n as f32 / 15.0,
-                Err(_) => 0.0,
+//Splices a single parent selector in place of the :is() that & became,when that keeps the
+//meaning.Specificity is the same either way,:is() takes that of its only argument.
+fn desugar_nesting(mut selector: Selector, parent: &[Selector]) -> Selector {
+    let parent_selector = match parent {
+        [p] if !p.simple.is_empty() => p,
+        _ => return selector,
+    };
+    let nesting = PseudoClass::Is(parent.to_vec());
+    for i in (0..selector.simple.len()).rev() {
+        let compound = &selector.simple[i];
+        let position = match compound.pseudo_classes.iter().position(|p| *p == nesting) {
+            Some(position) => position,
+            None => continue,
+        };
+        //A complex parent can only lead,.a & is not the same as .a .b .c for & = .b .c
+        let last = &parent_selector.simple[parent_selector.simple.len() - 1];
+        if (i > 0 && parent_selector.simple.len() > 1)
+            || compound.tag_name.is_some()
+            || compound.universal
+            || (compound.id.is_some() && last.id.is_some())
+        {
+            continue;
+        }
+
+        let mut compound = selector.simple.remove(i);
+        compound.pseudo_classes.remove(position);
+        let mut merged = last.clone();
+        merged.id = merged.id.or(compound.id);
+        merged.classes.append(&mut compound.classes);
+        merged.pseudo_classes.append(&mut compound.pseudo_classes);
+
+        let mut simple = parent_selector.simple.clone();
+        simple.pop();
+        simple.push(merged);
+        selector.simple.splice(i..i, simple);
+        let combinators = parent_selector.combinators.iter().cloned();
+        selector.combinators.splice(i..i, combinators);
+    }
+    selector
+}
+
+//User-action,input and location pseudo-classes,accepted in selectors but never matched
+const UNTRACKED_STATES: &[&str] = &[
+    "hover",
+    "active",
+    "focus",
+    "focus-visible",
+    "focus-within",
+    "target",
+    "link",
+    "any-link",
+    "visited",
+    "checked",
+    "disabled",
+    "enabled",
+    "indeterminate",
+    "placeholder-shown",
+    "default",
+    "valid",
+    "invalid",
+    "required",
+    "optional",
+    "read-only",
+    "read-write",
+];
+
+//An+B of the :nth-*() pseudo-classes,odd and even included
+fn parse_an_plus_b(argument: &str) -> Option<(i32, i32)> {
+    let argument = argument
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_lowercase();
+    match argument.as_ref() {
+        "odd" => return Some((2, 1)),
+        "even" => return Some((2, 0)),
+        _ => {}
+    }
+    let (a, b) = match argument.find('n') {
+        Some(n) => {
+            let a = match &argument[..n] {
+                "" | "+" => 1,
+                "-" => -1,
+                a => a.parse().ok()?,
             };
-            let blue = match u8::from_str_radix(&color[3..4], 16) {
-                Ok(n) => n as f32 / 15.0,
-                Err(_) => 0.0,
+            let b = match &argument[n + 1..] {
+                "" => 0,
+                b if b.starts_with('+') || b.starts_with('-') => b.parse().ok()?,
+                _ => return None,
             };
-            return Color::new(red, green, blue, 1.0);
-        } else {
-            return Color::default();
+            (a, b)
         }
-    } else if color.starts_with("rgb") {
-        return Color::default();
-    } else if color.starts_with("hsl") {
-        return Color::default();
-    } else {
-        match_color_by_name(color)
-    }
+        None => (0, argument.parse().ok()?),
+    };
+    Some((a, b))
+}
+
+fn is_selector_separator(c: char) -> bool {
+    c == ',' || c == '{' || c == ')' || c == '>' || c == '+' || c == '~'
 }
 
 fn is_valid_indent(c: char) -> bool {
@@ -267,3 +1193,61 @@ fn is_lower_letter(c: char) -> bool {
 fn is_non_ascii(c: char) -> bool {
     c >= '\u{0080}'
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cssserializer::minify_stylesheet;
+
+    fn nested(css: &str) -> String {
+        minify_stylesheet(&cssparser::new(css).parse_stylesheet())
+    }
+
+    #[test]
+    fn nested_rules_are_flattened_after_their_parent() {
+        assert_eq!(
+            nested("a { color: red; b { width: 1px } }"),
+            "a{color:red}a b{width:1px}"
+        );
+        assert_eq!(
+            nested("a { > b { width: 1px } + c { width: 2px } }"),
+            "a>b{width:1px}a+c{width:2px}"
+        );
+        assert_eq!(nested("a { .x { b { width: 1px } } }"), "a .x b{width:1px}");
+    }
+
+    #[test]
+    fn nesting_selector_takes_the_parent_place() {
+        assert_eq!(nested("a { &.x { width: 1px } }"), "a.x{width:1px}");
+        assert_eq!(nested("a { .x & { width: 1px } }"), ".x a{width:1px}");
+        assert_eq!(
+            nested("a, b { & c { width: 1px } }"),
+            ":is(a,b) c{width:1px}"
+        );
+        assert_eq!(nested("a { && { width: 1px } }"), "a:is(a){width:1px}");
+    }
+
+    #[test]
+    fn declarations_after_nested_rules_stay_in_order() {
+        assert_eq!(
+            nested("a { width: 1px; b { width: 2px } height: 3px }"),
+            "a{width:1px}a b{width:2px}a{height:3px}"
+        );
+    }
+
+    #[test]
+    fn conditional_rules_nest_inside_style_rules() {
+        assert_eq!(
+            nested("a { width: 1px; @media print { width: 2px; b { width: 3px } } }"),
+            "a{width:1px}@media print{a{width:2px}a b{width:3px}}"
+        );
+    }
+
+    #[test]
+    fn invalid_nested_selectors_drop_only_their_rule() {
+        assert_eq!(
+            nested("a { :bogus { width: 1px } b { width: 2px } }"),
+            "a b{width:2px}"
+        );
+    }
+}
@@ -0,0 +1,406 @@
+//Registry of the supported longhand properties,their grammar,initial value and whether they inherit.
+//A declaration of a property that is not listed here is kept unparsed,as Value::Other,
+//and ignored when styles are computed.
+use crate::cssanimations;
+use crate::csselements::Value;
+use crate::cssfonts;
+use crate::cssparser::parse_property_value;
+use crate::cssvalues::{each_item, is_ident_in, is_length_percentage, space_items};
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+pub const CSS_WIDE_KEYWORDS: [&str; 4] = ["inherit", "initial", "unset", "revert"];
+
+pub const BORDER_STYLES: &[&str] = &[
+    "none", "hidden", "dotted", "dashed", "solid", "double", "groove", "ridge", "inset", "outset",
+];
+
+const BORDER_WIDTHS: &[(&str, f32)] = &[("thin", 1.0), ("medium", 3.0), ("thick", 5.0)];
+
+const FONT_SIZES: &[(&str, f32)] = &[
+    ("xx-small", 9.0),
+    ("x-small", 10.0),
+    ("small", 13.0),
+    ("medium", 16.0),
+    ("large", 18.0),
+    ("x-large", 24.0),
+    ("xx-large", 32.0),
+    ("xxx-large", 48.0),
+];
+
+pub enum Grammar {
+    //<color> or currentcolor
+    Color,
+    //<length>,plus the keywords that name a length in px
+    Length {
+        percentage: bool,
+        negative: bool,
+        auto: bool,
+        keywords: &'static [(&'static str, f32)],
+    },
+    //One of a fixed set of identifiers,parsed into Value::Keyword
+    Keyword(&'static [&'static str]),
+    //A display keyword such as block,flex or table-cell,parsed into Value::Display
+    Display,
+    //<number> or <percentage>,parsed into Value::Number with percentages divided by 100
+    Number,
+    //Component values accepted by the check,such as the comma lists of animation-*
+    Components(fn(&Value) -> bool),
+}
+
+pub struct PropertyDefinition {
+    pub name: &'static str,
+    pub grammar: Grammar,
+    pub initial: &'static str,
+    pub inherited: bool,
+}
+
+const fn property(
+    name: &'static str,
+    grammar: Grammar,
+    initial: &'static str,
+    inherited: bool,
+) -> PropertyDefinition {
+    PropertyDefinition {
+        name,
+        grammar,
+        initial,
+        inherited,
+    }
+}
+
+const fn length(percentage: bool, negative: bool, auto: bool) -> Grammar {
+    Grammar::Length {
+        percentage,
+        negative,
+        auto,
+        keywords: &[],
+    }
+}
+
+const BORDER_WIDTH: Grammar = Grammar::Length {
+    percentage: false,
+    negative: false,
+    auto: false,
+    keywords: BORDER_WIDTHS,
+};
+
+pub static PROPERTIES: &[PropertyDefinition] = &[
+    property("display", Grammar::Display, "inline", false),
+    property(
+        "box-sizing",
+        Grammar::Keyword(&["content-box", "border-box"]),
+        "content-box",
+        false,
+    ),
+    property("width", length(true, false, true), "auto", false),
+    property("height", length(true, false, true), "auto", false),
+    property("top", length(true, true, true), "auto", false),
+    property("right", length(true, true, true), "auto", false),
+    property("bottom", length(true, true, true), "auto", false),
+    property("left", length(true, true, true), "auto", false),
+    property("margin-top", length(true, true, true), "0", false),
+    property("margin-right", length(true, true, true), "0", false),
+    property("margin-bottom", length(true, true, true), "0", false),
+    property("margin-left", length(true, true, true), "0", false),
+    property("padding-top", length(true, false, false), "0", false),
+    property("padding-right", length(true, false, false), "0", false),
+    property("padding-bottom", length(true, false, false), "0", false),
+    property("padding-left", length(true, false, false), "0", false),
+    property("border-top-width", BORDER_WIDTH, "medium", false),
+    property("border-right-width", BORDER_WIDTH, "medium", false),
+    property("border-bottom-width", BORDER_WIDTH, "medium", false),
+    property("border-left-width", BORDER_WIDTH, "medium", false),
+    property(
+        "border-top-style",
+        Grammar::Keyword(BORDER_STYLES),
+        "none",
+        false,
+    ),
+    property(
+        "border-right-style",
+        Grammar::Keyword(BORDER_STYLES),
+        "none",
+        false,
+    ),
+    property(
+        "border-bottom-style",
+        Grammar::Keyword(BORDER_STYLES),
+        "none",
+        false,
+    ),
+    property(
+        "border-left-style",
+        Grammar::Keyword(BORDER_STYLES),
+        "none",
+        false,
+    ),
+    property("border-top-color", Grammar::Color, "currentcolor", false),
+    property("border-right-color", Grammar::Color, "currentcolor", false),
+    property("border-bottom-color", Grammar::Color, "currentcolor", false),
+    property("border-left-color", Grammar::Color, "currentcolor", false),
+    property("color", Grammar::Color, "black", true),
+    property("background-color", Grammar::Color, "transparent", false),
+    property(
+        "background-image",
+        Grammar::Components(is_image_list),
+        "none",
+        false,
+    ),
+    property(
+        "background-repeat",
+        Grammar::Components(is_repeat_list),
+        "repeat",
+        false,
+    ),
+    property(
+        "background-attachment",
+        Grammar::Keyword(&["scroll", "fixed", "local"]),
+        "scroll",
+        false,
+    ),
+    property(
+        "background-position",
+        Grammar::Components(is_position_list),
+        "0% 0%",
+        false,
+    ),
+    property(
+        "background-size",
+        Grammar::Components(is_size_list),
+        "auto",
+        false,
+    ),
+    property(
+        "font-style",
+        Grammar::Keyword(&["normal", "italic", "oblique"]),
+        "normal",
+        true,
+    ),
+    property(
+        "font-variant",
+        Grammar::Keyword(&["normal", "small-caps"]),
+        "normal",
+        true,
+    ),
+    property(
+        "font-weight",
+        Grammar::Components(cssfonts::is_font_weight),
+        "normal",
+        true,
+    ),
+    property(
+        "font-stretch",
+        Grammar::Components(cssfonts::is_font_stretch),
+        "normal",
+        true,
+    ),
+    property(
+        "font-size",
+        Grammar::Length {
+            percentage: true,
+            negative: false,
+            auto: false,
+            keywords: FONT_SIZES,
+        },
+        "medium",
+        true,
+    ),
+    property(
+        "line-height",
+        Grammar::Components(cssfonts::is_line_height),
+        "normal",
+        true,
+    ),
+    property(
+        "font-family",
+        Grammar::Components(cssfonts::is_family_list),
+        "serif",
+        true,
+    ),
+    property(
+        "list-style-type",
+        Grammar::Keyword(&[
+            "disc",
+            "circle",
+            "square",
+            "decimal",
+            "lower-alpha",
+            "upper-alpha",
+            "lower-roman",
+            "upper-roman",
+            "none",
+        ]),
+        "disc",
+        true,
+    ),
+    property("opacity", Grammar::Number, "1", false),
+    property(
+        "transform",
+        Grammar::Components(cssanimations::is_transform_list),
+        "none",
+        false,
+    ),
+    property(
+        "animation-name",
+        Grammar::Components(cssanimations::is_name_list),
+        "none",
+        false,
+    ),
+    property(
+        "animation-duration",
+        Grammar::Components(cssanimations::is_time_list),
+        "0s",
+        false,
+    ),
+    property(
+        "animation-timing-function",
+        Grammar::Components(cssanimations::is_timing_function_list),
+        "ease",
+        false,
+    ),
+    property(
+        "animation-delay",
+        Grammar::Components(cssanimations::is_delay_list),
+        "0s",
+        false,
+    ),
+    property(
+        "animation-iteration-count",
+        Grammar::Components(cssanimations::is_iteration_count_list),
+        "1",
+        false,
+    ),
+    property(
+        "animation-direction",
+        Grammar::Components(cssanimations::is_direction_list),
+        "normal",
+        false,
+    ),
+    property(
+        "animation-fill-mode",
+        Grammar::Components(cssanimations::is_fill_mode_list),
+        "none",
+        false,
+    ),
+    property(
+        "animation-play-state",
+        Grammar::Components(cssanimations::is_play_state_list),
+        "running",
+        false,
+    ),
+    property(
+        "transition-property",
+        Grammar::Components(cssanimations::is_property_list),
+        "all",
+        false,
+    ),
+    property(
+        "transition-duration",
+        Grammar::Components(cssanimations::is_time_list),
+        "0s",
+        false,
+    ),
+    property(
+        "transition-timing-function",
+        Grammar::Components(cssanimations::is_timing_function_list),
+        "ease",
+        false,
+    ),
+    property(
+        "transition-delay",
+        Grammar::Components(cssanimations::is_delay_list),
+        "0s",
+        false,
+    ),
+];
+
+const REPEATS: &[&str] = &["repeat", "space", "round", "no-repeat"];
+
+//Grammars of the background properties,one item per comma separated layer
+fn is_image_list(value: &Value) -> bool {
+    each_item(value, &|v| match *v {
+        Value::Url(_) => true,
+        Value::Function(ref name, _) => name.ends_with("gradient"),
+        _ => is_ident_in(v, &["none"]),
+    })
+}
+
+fn is_repeat_list(value: &Value) -> bool {
+    each_item(value, &|v| match space_items(v).as_slice() {
+        [repeat] => is_ident_in(repeat, REPEATS) || is_ident_in(repeat, &["repeat-x", "repeat-y"]),
+        [x, y] => is_ident_in(x, REPEATS) && is_ident_in(y, REPEATS),
+        _ => false,
+    })
+}
+
+fn is_position_list(value: &Value) -> bool {
+    each_item(value, &|v| {
+        let parts = space_items(v);
+        parts.len() <= 4
+            && parts.iter().all(|p| {
+                is_length_percentage(p, true)
+                    || is_ident_in(p, &["left", "right", "top", "bottom", "center"])
+            })
+    })
+}
+
+fn is_size_list(value: &Value) -> bool {
+    each_item(value, &|v| match space_items(v).as_slice() {
+        [size] if is_ident_in(size, &["cover", "contain"]) => true,
+        parts => {
+            parts.len() <= 2
+                && parts
+                    .iter()
+                    .all(|p| is_ident_in(p, &["auto"]) || is_length_percentage(p, false))
+        }
+    })
+}
+
+pub fn get_property(name: &str) -> Option<&'static PropertyDefinition> {
+    PROPERTIES.iter().find(|p| p.name == name)
+}
+
+//Position of a property in PROPERTIES,computed styles keep their values in that order
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct PropertyId(usize);
+
+impl PropertyId {
+    pub fn index(self) -> usize {
+        self.0
+    }
+
+    pub fn definition(self) -> &'static PropertyDefinition {
+        &PROPERTIES[self.0]
+    }
+}
+
+pub fn property_id(name: &str) -> Option<PropertyId> {
+    static IDS: OnceLock<HashMap<&'static str, PropertyId>> = OnceLock::new();
+    IDS.get_or_init(|| {
+        PROPERTIES
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (p.name, PropertyId(i)))
+            .collect()
+    })
+    .get(name)
+    .cloned()
+}
+
+//Initial values of the registry parsed into values,once for the whole program
+pub fn initial_value(name: &str) -> Option<&'static Value> {
+    static INITIAL_VALUES: OnceLock<HashMap<&'static str, Value>> = OnceLock::new();
+    INITIAL_VALUES
+        .get_or_init(|| {
+            PROPERTIES
+                .iter()
+                .filter_map(|p| {
+                    let value = parse_property_value(p.name, p.initial).pop()?;
+                    Some((p.name, value.1))
+                })
+                .collect()
+        })
+        .get(name)
+}
@@ -0,0 +1,1191 @@
+//Turns a parsed Stylesheet back into CSS text,either pretty printed or minified
+use crate::csselements::{
+    match_color_by_name, Color, ColorSpace, Comparison, CssRule, Declaration, FontFace, FontSource,
+    FontStyle, KeyframesRule, ListSeparator, MathExpression, MediaCondition, MediaFeature,
+    MediaQuery, MediaType, PseudoClass, RelativeSelector, Rule, Selector, SimpleSelector,
+    Stylesheet, SupportsCondition, Unit, Value,
+};
+use crate::cssparser::parse_property_value;
+use crate::cssproperties;
+use crate::cssshorthands::get_longhands;
+use crate::cssvalues::{fold_case, is_identifier, split_top_level};
+
+//Names that are shorter than the hex form of their color,checked when minifying
+const SHORT_COLOR_NAMES: [&str; 30] = [
+    "red", "tan", "aqua", "blue", "gold", "gray", "grey", "lime", "navy", "peru", "pink", "plum",
+    "snow", "teal", "azure", "beige", "brown", "coral", "green", "ivory", "khaki", "linen",
+    "olive", "wheat", "white", "bisque", "indigo", "maroon", "orange", "orchid",
+];
+
+//Canonical form,one declaration per line,meant for diffs
+pub fn serialize_stylesheet(stylesheet: &Stylesheet) -> String {
+    Serializer {
+        stylesheet,
+        minify: false,
+    }
+    .stylesheet()
+}
+
+//Shortest form:no optional whitespace,shortest colors,no units on zero lengths and
+//adjacent rules with the same selectors or the same declarations merged
+pub fn minify_stylesheet(stylesheet: &Stylesheet) -> String {
+    Serializer {
+        stylesheet,
+        minify: true,
+    }
+    .stylesheet()
+}
+
+pub fn serialize_value(value: &Value) -> String {
+    let stylesheet = Stylesheet::default();
+    Serializer {
+        stylesheet: &stylesheet,
+        minify: false,
+    }
+    .value(value)
+}
+
+//A style rule in text form,kept apart so that adjacent rules can be merged
+struct SerializedRule {
+    selectors: String,
+    declarations: Vec<SerializedDeclaration>,
+}
+
+#[derive(PartialEq)]
+struct SerializedDeclaration {
+    key: String,
+    value: String,
+    text: String,
+    important: bool,
+}
+
+//Shorthands tried when minifying,a shorthand that takes in the longhands of a later one
+//comes first
+const COLLAPSED_SHORTHANDS: &[&str] = &[
+    "border",
+    "border-width",
+    "border-style",
+    "border-color",
+    "border-top",
+    "border-right",
+    "border-bottom",
+    "border-left",
+    "margin",
+    "padding",
+    "inset",
+    "font",
+    "background",
+    "animation",
+    "transition",
+];
+
+struct Serializer<'a> {
+    stylesheet: &'a Stylesheet,
+    minify: bool,
+}
+
+impl<'a> Serializer<'a> {
+    fn stylesheet(&self) -> String {
+        let css = &self.stylesheet.css;
+        let imports = css
+            .iter()
+            .take_while(|r| match **r {
+                CssRule::Import(_) => true,
+                _ => false,
+            })
+            .count();
+
+        let mut blocks = Vec::new();
+        blocks.extend(self.rules(&css[..imports]));
+        blocks.extend(self.namespaces());
+        blocks.extend(self.rules(&css[imports..]));
+        self.join_blocks(blocks, 0)
+    }
+
+    fn namespaces(&self) -> Vec<String> {
+        let mut result = Vec::new();
+        if let Some(ref url) = self.stylesheet.default_namespace {
+            result.push(format!("@namespace {};", self.url(url)));
+        }
+        let mut prefixes = self.stylesheet.namespaces.iter().collect::<Vec<_>>();
+        prefixes.sort();
+        for (prefix, url) in prefixes {
+            result.push(format!("@namespace {} {};", prefix, self.url(url)));
+        }
+        result
+    }
+
+    fn join_blocks(&self, blocks: Vec<String>, depth: usize) -> String {
+        if self.minify {
+            return blocks.concat();
+        }
+        let indent = "    ".repeat(depth);
+        let blocks = blocks
+            .iter()
+            .map(|b| {
+                b.lines()
+                    .map(|l| format!("{}{}", indent, l))
+                    .collect::<Vec<String>>()
+                    .join("\n")
+            })
+            .collect::<Vec<String>>();
+        match depth {
+            0 => blocks.join("\n\n") + "\n",
+            _ => blocks.join("\n"),
+        }
+    }
+
+    //Every rule becomes one block of text,runs of style rules are merged when minifying
+    fn rules(&self, rules: &[CssRule]) -> Vec<String> {
+        let mut blocks = Vec::new();
+        let mut pending: Vec<SerializedRule> = Vec::new();
+
+        for rule in rules {
+            match *rule {
+                //Would be written as a bare block,which does not parse back
+                CssRule::Style(ref r) if r.selectors.is_empty() => {}
+                CssRule::Style(ref r) => {
+                    let serialized = self.style_rule(r);
+                    match pending.last_mut() {
+                        Some(last) if self.minify => {
+                            if last.selectors == serialized.selectors {
+                                last.declarations.extend(serialized.declarations);
+                                remove_overridden(&mut last.declarations);
+                                continue;
+                            }
+                            if last.declarations == serialized.declarations {
+                                last.selectors =
+                                    format!("{},{}", last.selectors, serialized.selectors);
+                                continue;
+                            }
+                        }
+                        _ => {}
+                    }
+                    pending.push(serialized);
+                }
+                CssRule::Media(ref m) => {
+                    blocks.extend(pending.drain(..).map(|r| self.rule_text(r)));
+                    let inner = self.join_blocks(self.rules(&m.rules), 1);
+                    let queries = self.media_queries(&m.queries);
+                    blocks.push(match self.minify {
+                        true => format!("@media {}{{{}}}", queries, inner),
+                        false => format!("@media {} {{\n{}\n}}", queries, inner),
+                    });
+                }
+                CssRule::Import(ref i) => {
+                    blocks.extend(pending.drain(..).map(|r| self.rule_text(r)));
+                    let queries = match i.queries.is_empty() {
+                        true => String::new(),
+                        false => format!(" {}", self.media_queries(&i.queries)),
+                    };
+                    blocks.push(format!("@import {}{};", self.url(&i.url), queries));
+                }
+                CssRule::FontFace(ref face) => {
+                    blocks.extend(pending.drain(..).map(|r| self.rule_text(r)));
+                    blocks.push(self.font_face(face));
+                }
+                CssRule::Supports(ref supports) => {
+                    blocks.extend(pending.drain(..).map(|r| self.rule_text(r)));
+                    let inner = self.join_blocks(self.rules(&supports.rules), 1);
+                    let condition = self.supports_condition(&supports.condition);
+                    blocks.push(match self.minify {
+                        true => format!("@supports {}{{{}}}", condition, inner),
+                        false => format!("@supports {} {{\n{}\n}}", condition, inner),
+                    });
+                }
+                CssRule::Keyframes(ref k) => {
+                    blocks.extend(pending.drain(..).map(|r| self.rule_text(r)));
+                    blocks.push(self.keyframes(k));
+                }
+            }
+        }
+        blocks.extend(pending.drain(..).map(|r| self.rule_text(r)));
+        blocks
+    }
+
+    fn style_rule(&self, rule: &Rule) -> SerializedRule {
+        let separator = if self.minify { "," } else { ", " };
+        let selectors = rule
+            .selectors
+            .iter()
+            .map(|s| self.selector(s))
+            .collect::<Vec<String>>()
+            .join(separator);
+        let mut declarations = self.declarations(&rule.declarations);
+        if self.minify {
+            remove_overridden(&mut declarations);
+            self.collapse_shorthands(&mut declarations);
+        }
+        SerializedRule {
+            selectors,
+            declarations,
+        }
+    }
+
+    fn rule_text(&self, rule: SerializedRule) -> String {
+        let declarations = rule
+            .declarations
+            .into_iter()
+            .map(|d| d.text)
+            .collect::<Vec<String>>();
+        if self.minify {
+            return format!("{}{{{}}}", rule.selectors, declarations.join(";"));
+        }
+        if declarations.is_empty() {
+            return format!("{} {{\n}}", rule.selectors);
+        }
+        format!(
+            "{} {{\n    {};\n}}",
+            rule.selectors,
+            declarations.join(";\n    ")
+        )
+    }
+
+    fn supports_condition(&self, condition: &SupportsCondition) -> String {
+        let in_parens = |c: &SupportsCondition| match *c {
+            SupportsCondition::Not(_) | SupportsCondition::And(_) | SupportsCondition::Or(_) => {
+                format!("({})", self.supports_condition(c))
+            }
+            _ => self.supports_condition(c),
+        };
+        let colon = if self.minify { ":" } else { ": " };
+        match *condition {
+            SupportsCondition::Declaration(ref name, ref value) => {
+                format!("({}{}{})", name, colon, value)
+            }
+            SupportsCondition::Selector(ref selector) => format!("selector({})", selector),
+            SupportsCondition::Not(ref c) => format!("not {}", in_parens(c)),
+            SupportsCondition::And(ref list) => list
+                .iter()
+                .map(in_parens)
+                .collect::<Vec<String>>()
+                .join(" and "),
+            SupportsCondition::Or(ref list) => list
+                .iter()
+                .map(in_parens)
+                .collect::<Vec<String>>()
+                .join(" or "),
+            SupportsCondition::Unknown(ref text) => text.clone(),
+        }
+    }
+
+    fn keyframes(&self, rule: &KeyframesRule) -> String {
+        let separator = if self.minify { "," } else { ", " };
+        let keyframes = rule
+            .keyframes
+            .iter()
+            .map(|k| {
+                let offsets = k
+                    .offsets
+                    .iter()
+                    .map(|o| format!("{}%", self.number(o * 100.0)))
+                    .collect::<Vec<String>>()
+                    .join(separator);
+                self.rule_text(SerializedRule {
+                    selectors: offsets,
+                    declarations: self.declarations(&k.declarations),
+                })
+            })
+            .collect::<Vec<String>>();
+        let name = match is_identifier(&rule.name) {
+            true => rule.name.clone(),
+            false => quote(&rule.name),
+        };
+        let inner = self.join_blocks(keyframes, 1);
+        match self.minify {
+            true => format!("@keyframes {}{{{}}}", name, inner),
+            false => format!("@keyframes {} {{\n{}\n}}", name, inner),
+        }
+    }
+
+    //Descriptors that hold their initial value are left out
+    fn font_face(&self, face: &FontFace) -> String {
+        let (colon, comma) = if self.minify {
+            (":", ",")
+        } else {
+            (": ", ", ")
+        };
+        let mut descriptors = vec![
+            ("font-family", quote(&face.family)),
+            (
+                "src",
+                face.sources
+                    .iter()
+                    .map(|s| match *s {
+                        FontSource::Local(ref name) => format!("local({})", quote(name)),
+                        FontSource::Url {
+                            ref url,
+                            format: Some(ref f),
+                        } => format!("{} format({})", self.url(url), quote(f)),
+                        FontSource::Url { ref url, .. } => self.url(url),
+                    })
+                    .collect::<Vec<String>>()
+                    .join(comma),
+            ),
+        ];
+        match face.weight {
+            (low, high) if low == 400.0 && high == 400.0 => {}
+            (low, high) if low == high => descriptors.push(("font-weight", self.number(low))),
+            (low, high) => descriptors.push((
+                "font-weight",
+                format!("{} {}", self.number(low), self.number(high)),
+            )),
+        }
+        match face.style {
+            FontStyle::Normal => {}
+            FontStyle::Italic => descriptors.push(("font-style", String::from("italic"))),
+            FontStyle::Oblique(low, high) if low == 14.0 && high == 14.0 => {
+                descriptors.push(("font-style", String::from("oblique")))
+            }
+            FontStyle::Oblique(low, high) if low == high => {
+                descriptors.push(("font-style", format!("oblique {}deg", self.number(low))))
+            }
+            FontStyle::Oblique(low, high) => descriptors.push((
+                "font-style",
+                format!("oblique {}deg {}deg", self.number(low), self.number(high)),
+            )),
+        }
+        if face.unicode_range != [(0, 0x10FFFF)] {
+            let ranges = face
+                .unicode_range
+                .iter()
+                .map(|&(low, high)| match low == high {
+                    true => format!("U+{:X}", low),
+                    false => format!("U+{:X}-{:X}", low, high),
+                })
+                .collect::<Vec<String>>()
+                .join(comma);
+            descriptors.push(("unicode-range", ranges));
+        }
+
+        let declarations = descriptors
+            .into_iter()
+            .map(|(key, value)| SerializedDeclaration {
+                key: key.to_string(),
+                text: format!("{}{}{}", key, colon, value),
+                value,
+                important: false,
+            })
+            .collect();
+        self.rule_text(SerializedRule {
+            selectors: String::from("@font-face"),
+            declarations,
+        })
+    }
+
+    //A shorthand whose value holds var() is expanded into longhands that all keep the
+    //shorthand's text,the run of them is written back as the shorthand declaration
+    fn declarations(&self, declarations: &[Declaration]) -> Vec<SerializedDeclaration> {
+        let mut result = Vec::new();
+        let mut previous: Option<&Declaration> = None;
+        for declaration in declarations {
+            let shorthand = match declaration.value {
+                Value::Var(ref var) => var.shorthand.as_ref(),
+                _ => None,
+            };
+            match shorthand {
+                Some(shorthand) => {
+                    let same_run = previous.map_or(false, |p| {
+                        p.value == declaration.value && p.important == declaration.important
+                    });
+                    if !same_run {
+                        result.push(self.declaration(shorthand, declaration));
+                    }
+                    previous = Some(declaration);
+                }
+                None => {
+                    result.push(self.declaration(&declaration.key, declaration));
+                    previous = None;
+                }
+            }
+        }
+        result
+    }
+
+    fn declaration(&self, key: &str, declaration: &Declaration) -> SerializedDeclaration {
+        let important = match (declaration.important, self.minify) {
+            (false, _) => "",
+            (true, true) => "!important",
+            (true, false) => " !important",
+        };
+        let colon = if self.minify { ":" } else { ": " };
+        let value = self.value(&declaration.value);
+        SerializedDeclaration {
+            key: key.to_string(),
+            text: format!("{}{}{}{}", key, colon, value, important),
+            value,
+            important: declaration.important,
+        }
+    }
+
+    //Replaces every complete set of longhands with the same importance by their shorthand,
+    //when the shorthand is shorter and parses back into the same values
+    fn collapse_shorthands(&self, declarations: &mut Vec<SerializedDeclaration>) {
+        for &shorthand in COLLAPSED_SHORTHANDS {
+            let longhands = match get_longhands(shorthand) {
+                Some(l) => l,
+                None => continue,
+            };
+            //A shorthand kept as written,one with var(),overlaps the set
+            let overlapping = declarations.iter().any(|d| {
+                get_longhands(&d.key).map_or(false, |l| l.iter().any(|k| longhands.contains(k)))
+            });
+            let positions = longhands
+                .iter()
+                .map(|l| declarations.iter().position(|d| d.key == *l))
+                .collect::<Option<Vec<usize>>>();
+            let positions = match positions {
+                Some(p) if !overlapping => p,
+                _ => continue,
+            };
+            let important = declarations[positions[0]].important;
+            if positions
+                .iter()
+                .any(|&i| declarations[i].important != important)
+            {
+                continue;
+            }
+
+            let values = positions
+                .iter()
+                .map(|&i| declarations[i].value.as_str())
+                .collect::<Vec<&str>>();
+            let value = match self.shorthand_value(shorthand, &longhands, &values) {
+                Some(v) => v,
+                None => continue,
+            };
+            let collapsed = self.declaration(
+                shorthand,
+                &Declaration {
+                    key: shorthand.to_string(),
+                    value: Value::Other(value),
+                    important,
+                },
+            );
+            let length = positions
+                .iter()
+                .map(|&i| declarations[i].text.len() + 1)
+                .sum::<usize>();
+            if collapsed.text.len() + 1 >= length {
+                continue;
+            }
+
+            //The others come after the first one,so removing them keeps its index
+            let first = positions.iter().cloned().min().unwrap_or(0);
+            let mut rest = positions
+                .into_iter()
+                .filter(|&i| i != first)
+                .collect::<Vec<usize>>();
+            rest.sort_unstable();
+            declarations[first] = collapsed;
+            for i in rest.into_iter().rev() {
+                declarations.remove(i);
+            }
+        }
+    }
+
+    //Text of shorthand that sets longhands to values.Values equal to the initial one are left
+    //out where the grammar allows,None when no text parses back into the same values.
+    fn shorthand_value(
+        &self,
+        shorthand: &str,
+        longhands: &[String],
+        values: &[&str],
+    ) -> Option<String> {
+        let initial = |longhand: &str| {
+            cssproperties::initial_value(longhand)
+                .map(|v| self.value(v))
+                .unwrap_or_default()
+        };
+        let set = |i: usize| values[i] != initial(&longhands[i]);
+        let mut candidates = Vec::new();
+        match shorthand {
+            "margin" | "padding" | "inset" | "border-width" | "border-style" | "border-color" => {
+                candidates.push(box_value(values));
+            }
+            "border" => {
+                let side = |start: usize| &values[start..start + 4];
+                if [0, 4, 8]
+                    .iter()
+                    .any(|&s| side(s).iter().any(|v| *v != values[s]))
+                {
+                    return None;
+                }
+                let parts = [0, 4, 8]
+                    .iter()
+                    .filter(|&&i| set(i))
+                    .map(|&i| values[i])
+                    .collect::<Vec<&str>>();
+                candidates.push(parts.join(" "));
+                candidates.push(format!("{} {} {}", values[0], values[4], values[8]));
+            }
+            "border-top" | "border-right" | "border-bottom" | "border-left" => {
+                let parts = (0..3)
+                    .filter(|&i| set(i))
+                    .map(|i| values[i])
+                    .collect::<Vec<&str>>();
+                candidates.push(parts.join(" "));
+                candidates.push(values.join(" "));
+            }
+            "font" => {
+                //style variant weight stretch size/line-height family
+                let mut parts = (0..4)
+                    .filter(|&i| set(i))
+                    .map(|i| values[i])
+                    .collect::<Vec<&str>>();
+                let size = match set(5) {
+                    true => format!("{}/{}", values[4], values[5]),
+                    false => values[4].to_string(),
+                };
+                parts.push(&size);
+                parts.push(values[6]);
+                candidates.push(parts.join(" "));
+            }
+            "background" => {
+                //color image repeat attachment position/size
+                let mut parts = (0..4)
+                    .filter(|&i| set(i))
+                    .map(|i| values[i])
+                    .collect::<Vec<&str>>();
+                let position = match (set(4), set(5)) {
+                    (_, true) => format!("{}/{}", values[4], values[5]),
+                    (true, false) => values[4].to_string(),
+                    (false, false) => String::new(),
+                };
+                if !position.is_empty() {
+                    parts.push(&position);
+                }
+                if parts.is_empty() {
+                    parts.push(values[0]);
+                }
+                candidates.push(parts.join(" "));
+            }
+            "animation" | "transition" => {
+                let lists = values
+                    .iter()
+                    .map(|v| split_top_level(v, ','))
+                    .collect::<Vec<Vec<&str>>>();
+                let layer_count = lists[0].len();
+                if lists.iter().any(|l| l.len() != layer_count) {
+                    return None;
+                }
+                let layers = (0..layer_count)
+                    .map(|layer| {
+                        //The first time is the duration,it has to be kept for a delay to follow
+                        let delay = longhands.iter().position(|l| l.ends_with("-delay"));
+                        let duration = longhands.iter().position(|l| l.ends_with("-duration"));
+                        let layer_set = |i: usize| lists[i][layer].trim() != initial(&longhands[i]);
+                        let parts = (0..longhands.len())
+                            .filter(|&i| {
+                                layer_set(i)
+                                    || (Some(i) == duration && delay.map_or(false, layer_set))
+                            })
+                            .map(|i| lists[i][layer].trim())
+                            .collect::<Vec<&str>>();
+                        match parts.is_empty() {
+                            true => lists[0][layer].trim().to_string(),
+                            false => parts.join(" "),
+                        }
+                    })
+                    .collect::<Vec<String>>();
+                candidates.push(layers.join(","));
+            }
+            _ => return None,
+        }
+        candidates
+            .into_iter()
+            .find(|c| expands_to(shorthand, c, longhands, values))
+    }
+
+    fn selector(&self, selector: &Selector) -> String {
+        let mut result = String::new();
+        for (i, simple) in selector.simple.iter().enumerate() {
+            if i > 0 {
+                let combinator = selector.combinators.get(i - 1).cloned().unwrap_or(' ');
+                result.push_str(&self.combinator(combinator));
+            }
+            result.push_str(&self.compound(simple));
+        }
+        result
+    }
+
+    fn combinator(&self, combinator: char) -> String {
+        match (combinator, self.minify) {
+            (' ', _) => String::from(" "),
+            (c, true) => c.to_string(),
+            (c, false) => format!(" {} ", c),
+        }
+    }
+
+    fn compound(&self, simple: &SimpleSelector) -> String {
+        let default = &self.stylesheet.default_namespace;
+        let prefix = match simple.namespace {
+            ref n if n == default => None,
+            None => Some(String::from("*")),
+            Some(ref n) if n.is_empty() => Some(String::new()),
+            Some(ref n) => self
+                .stylesheet
+                .namespaces
+                .iter()
+                .find(|&(_, url)| url == n)
+                .map(|(p, _)| p.clone()),
+        };
+
+        let mut result = String::new();
+        let type_selector = match simple.tag_name {
+            Some(ref t) => Some(t.as_str()),
+            None if simple.universal || prefix.is_some() => Some("*"),
+            None => None,
+        };
+        if let Some(t) = type_selector {
+            if let Some(p) = prefix {
+                result.push_str(&p);
+                result.push('|');
+            }
+            result.push_str(t);
+        }
+        if let Some(ref id) = simple.id {
+            result.push('#');
+            result.push_str(id);
+        }
+        for class in &simple.classes {
+            result.push('.');
+            result.push_str(class);
+        }
+        for pseudo in &simple.pseudo_classes {
+            result.push_str(&self.pseudo_class(pseudo));
+        }
+        if result.is_empty() {
+            result.push('*');
+        }
+        result
+    }
+
+    fn pseudo_class(&self, pseudo: &PseudoClass) -> String {
+        let separator = if self.minify { "," } else { ", " };
+        let list = |selectors: &[Selector]| {
+            selectors
+                .iter()
+                .map(|s| self.selector(s))
+                .collect::<Vec<String>>()
+                .join(separator)
+        };
+        match *pseudo {
+            PseudoClass::Not(ref s) => format!(":not({})", list(s)),
+            PseudoClass::Is(ref s) => format!(":is({})", list(s)),
+            PseudoClass::Where(ref s) => format!(":where({})", list(s)),
+            PseudoClass::Has(ref relative) => {
+                let relative = relative
+                    .iter()
+                    .map(|r| self.relative_selector(r))
+                    .collect::<Vec<String>>()
+                    .join(separator);
+                format!(":has({})", relative)
+            }
+            ref simple => format!(":{}", simple.name().unwrap_or_default()),
+        }
+    }
+
+    fn relative_selector(&self, relative: &RelativeSelector) -> String {
+        match relative.combinator {
+            ' ' => self.selector(&relative.selector),
+            c => format!(
+                "{}{}",
+                self.combinator(c).trim_start(),
+                self.selector(&relative.selector)
+            ),
+        }
+    }
+
+    fn media_queries(&self, queries: &[MediaQuery]) -> String {
+        if queries.is_empty() {
+            return String::from("all");
+        }
+        let separator = if self.minify { "," } else { ", " };
+        queries
+            .iter()
+            .map(|q| self.media_query(q))
+            .collect::<Vec<String>>()
+            .join(separator)
+    }
+
+    fn media_query(&self, query: &MediaQuery) -> String {
+        let media_type = match query.media_type {
+            MediaType::All => "all",
+            MediaType::Screen => "screen",
+            MediaType::Print => "print",
+            MediaType::Other(ref t) => t.as_str(),
+        };
+        let not = if query.negated { "not " } else { "" };
+        match query.condition {
+            None => format!("{}{}", not, media_type),
+            Some(ref c) if !query.negated && query.media_type == MediaType::All => {
+                self.media_condition(c)
+            }
+            Some(ref c) => format!(
+                "{}{} and {}",
+                not,
+                media_type,
+                self.media_condition_in_parens(c)
+            ),
+        }
+    }
+
+    fn media_condition(&self, condition: &MediaCondition) -> String {
+        match *condition {
+            MediaCondition::Feature(ref f) => self.media_feature(f),
+            MediaCondition::Not(ref c) => format!("not {}", self.media_condition_in_parens(c)),
+            MediaCondition::And(ref list) | MediaCondition::Or(ref list) => {
+                let joiner = match *condition {
+                    MediaCondition::And(_) => " and ",
+                    _ => " or ",
+                };
+                list.iter()
+                    .map(|c| self.media_condition_in_parens(c))
+                    .collect::<Vec<String>>()
+                    .join(joiner)
+            }
+        }
+    }
+
+    fn media_condition_in_parens(&self, condition: &MediaCondition) -> String {
+        match *condition {
+            MediaCondition::Feature(_) => self.media_condition(condition),
+            _ => format!("({})", self.media_condition(condition)),
+        }
+    }
+
+    fn media_feature(&self, feature: &MediaFeature) -> String {
+        let space = if self.minify { "" } else { " " };
+        match *feature {
+            MediaFeature::Boolean(ref name) => format!("({})", name),
+            MediaFeature::Discrete {
+                ref name,
+                ref value,
+            } => format!("({}:{}{})", name, space, value),
+            MediaFeature::Range {
+                ref name,
+                ref comparisons,
+            } => {
+                //Values were normalized to px and dppx by the parser
+                let unit = match name.as_ref() {
+                    "width" | "height" | "device-width" | "device-height" => "px",
+                    "resolution" => "dppx",
+                    _ => "",
+                };
+                let value = |v: f32| format!("{}{}", self.number(v), unit);
+                match comparisons.as_slice() {
+                    [(op, v)] => format!(
+                        "({}{}{}{}{})",
+                        name,
+                        space,
+                        comparison(*op),
+                        space,
+                        value(*v)
+                    ),
+                    [(low_op, low), (high_op, high)] => format!(
+                        "({}{s}{}{s}{}{s}{}{s}{})",
+                        value(*low),
+                        comparison(flip(*low_op)),
+                        name,
+                        comparison(*high_op),
+                        value(*high),
+                        s = space
+                    ),
+                    _ => String::from("(not all)"),
+                }
+            }
+        }
+    }
+
+    fn value(&self, value: &Value) -> String {
+        let separator = if self.minify { "," } else { ", " };
+        match *value {
+            Value::Color(ref c) => self.color(c),
+            Value::Length(n, unit) => self.length(n, unit),
+            Value::Other(ref s) | Value::Custom(ref s) | Value::Ident(ref s) => s.clone(),
+            Value::Var(ref v) => v.css.clone(),
+            Value::Math(ref m) => self.math(m),
+            Value::Keyword(k) => k.to_string(),
+            Value::Display(d) => String::from(d.keyword()),
+            Value::Number(n) => self.number(n),
+            Value::String(ref s) => quote(s),
+            Value::Url(ref u) => self.url(u),
+            Value::Function(ref name, ref arguments) => {
+                let arguments = arguments
+                    .iter()
+                    .map(|a| self.value(a))
+                    .collect::<Vec<String>>();
+                format!("{}({})", name, arguments.join(separator))
+            }
+            Value::List(ref values, list_separator) => {
+                let values = values
+                    .iter()
+                    .map(|v| self.value(v))
+                    .collect::<Vec<String>>();
+                match list_separator {
+                    ListSeparator::Space => values.join(" "),
+                    ListSeparator::Comma => values.join(separator),
+                }
+            }
+            Value::Time(t) => format!("{}s", self.number(t)),
+            Value::Angle(a) => format!("{}deg", self.number(a)),
+        }
+    }
+
+    fn url(&self, url: &str) -> String {
+        format!("url({})", quote(url))
+    }
+
+    fn number(&self, n: f32) -> String {
+        let rounded = (n * 10000.0).round() / 10000.0;
+        let text = format!("{}", if rounded == 0.0 { 0.0 } else { rounded });
+        if !self.minify {
+            return text;
+        }
+        if text.starts_with("0.") {
+            text[1..].to_string()
+        } else if text.starts_with("-0.") {
+            format!("-{}", &text[2..])
+        } else {
+            text
+        }
+    }
+
+    //Shortest alpha with the same 8-bit value,so that the hex form of a minified color
+    //reads back as the same rgba()
+    fn alpha(&self, a: f32) -> String {
+        let byte = (a.max(0.0).min(1.0) * 255.0).round();
+        let hundredths = (a * 100.0).round() / 100.0;
+        if (hundredths * 255.0).round() == byte {
+            self.number(hundredths)
+        } else {
+            self.number((a * 1000.0).round() / 1000.0)
+        }
+    }
+
+    fn length(&self, n: f32, unit: Unit) -> String {
+        if self.minify && n == 0.0 {
+            return String::from("0");
+        }
+        format!("{}{}", self.number(n), unit_name(unit))
+    }
+
+    fn math(&self, expression: &MathExpression) -> String {
+        match *expression {
+            MathExpression::Min(_) | MathExpression::Max(_) | MathExpression::Clamp(..) => {
+                self.math_term(expression)
+            }
+            _ => format!("calc({})", self.math_sum(expression)),
+        }
+    }
+
+    //Top level of a calc() or of a function argument,where sums need no parentheses
+    fn math_sum(&self, expression: &MathExpression) -> String {
+        match *expression {
+            MathExpression::Sum(ref terms) => {
+                let mut result = String::new();
+                for (i, term) in terms.iter().enumerate() {
+                    match (i, term) {
+                        (0, t) => result.push_str(&self.math_term(t)),
+                        (_, &MathExpression::Negate(ref t)) => {
+                            result.push_str(" - ");
+                            result.push_str(&self.math_term(t));
+                        }
+                        (_, t) => {
+                            result.push_str(" + ");
+                            result.push_str(&self.math_term(t));
+                        }
+                    }
+                }
+                result
+            }
+            MathExpression::Product(ref factors) => {
+                let (times, divide) = if self.minify {
+                    ("*", "/")
+                } else {
+                    (" * ", " / ")
+                };
+                let mut result = String::new();
+                for (i, factor) in factors.iter().enumerate() {
+                    match (i, factor) {
+                        (0, f) => result.push_str(&self.math_term(f)),
+                        (_, &MathExpression::Invert(ref f)) => {
+                            result.push_str(divide);
+                            result.push_str(&self.math_term(f));
+                        }
+                        (_, f) => {
+                            result.push_str(times);
+                            result.push_str(&self.math_term(f));
+                        }
+                    }
+                }
+                result
+            }
+            _ => self.math_term(expression),
+        }
+    }
+
+    fn math_term(&self, expression: &MathExpression) -> String {
+        let separator = if self.minify { "," } else { ", " };
+        let list = |arguments: &[&MathExpression]| {
+            arguments
+                .iter()
+                .map(|a| self.math_sum(a))
+                .collect::<Vec<String>>()
+                .join(separator)
+        };
+        match *expression {
+            MathExpression::Number(n) => self.number(n),
+            MathExpression::Dimension(n, unit) => format!("{}{}", self.number(n), unit_name(unit)),
+            MathExpression::Sum(_) | MathExpression::Product(_) => {
+                format!("({})", self.math_sum(expression))
+            }
+            MathExpression::Negate(ref e) => format!("(-1 * {})", self.math_term(e)),
+            MathExpression::Invert(ref e) => format!("(1 / {})", self.math_term(e)),
+            MathExpression::Min(ref a) => format!("min({})", list(&a.iter().collect::<Vec<_>>())),
+            MathExpression::Max(ref a) => format!("max({})", list(&a.iter().collect::<Vec<_>>())),
+            MathExpression::Clamp(ref low, ref value, ref high) => {
+                format!("clamp({})", list(&[&**low, &**value, &**high]))
+            }
+        }
+    }
+
+    fn color(&self, color: &Color) -> String {
+        if let Some(ref source) = color.source {
+            let name = match source.space {
+                ColorSpace::Lab => Some("lab"),
+                ColorSpace::Lch => Some("lch"),
+                ColorSpace::Oklab => Some("oklab"),
+                ColorSpace::Oklch => Some("oklch"),
+                _ => None,
+            };
+            let space = match source.space {
+                ColorSpace::Srgb => Some("srgb"),
+                ColorSpace::SrgbLinear => Some("srgb-linear"),
+                ColorSpace::DisplayP3 => Some("display-p3"),
+                ColorSpace::A98Rgb => Some("a98-rgb"),
+                ColorSpace::ProphotoRgb => Some("prophoto-rgb"),
+                ColorSpace::Rec2020 => Some("rec2020"),
+                ColorSpace::XyzD50 => Some("xyz-d50"),
+                ColorSpace::XyzD65 => Some("xyz-d65"),
+                _ => None,
+            };
+            let components = source
+                .components
+                .iter()
+                .map(|c| self.number(*c))
+                .collect::<Vec<String>>()
+                .join(" ");
+            let alpha = match source.alpha {
+                a if a >= 1.0 => String::new(),
+                a => format!(" / {}", self.number(a)),
+            };
+            match (name, space) {
+                (Some(n), _) => return format!("{}({}{})", n, components, alpha),
+                (_, Some(s)) => return format!("color({} {}{})", s, components, alpha),
+                _ => {}
+            }
+        }
+
+        let channel = |c: f32| (c.max(0.0).min(1.0) * 255.0).round() as u8;
+        let (r, g, b, a) = (
+            channel(color.r),
+            channel(color.g),
+            channel(color.b),
+            channel(color.a),
+        );
+        if !self.minify {
+            return match a {
+                255 => format!("#{:02x}{:02x}{:02x}", r, g, b),
+                _ => format!("rgba({}, {}, {}, {})", r, g, b, self.alpha(color.a)),
+            };
+        }
+
+        let short = [r, g, b, a].iter().all(|c| c % 17 == 0);
+        let hex = match (short, a) {
+            (true, 255) => format!("#{:x}{:x}{:x}", r / 17, g / 17, b / 17),
+            (true, _) => format!("#{:x}{:x}{:x}{:x}", r / 17, g / 17, b / 17, a / 17),
+            (false, 255) => format!("#{:02x}{:02x}{:02x}", r, g, b),
+            (false, _) => format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a),
+        };
+        SHORT_COLOR_NAMES
+            .iter()
+            .filter(|name| name.len() < hex.len())
+            .find(|name| {
+                let named = match_color_by_name(name);
+                (
+                    channel(named.r),
+                    channel(named.g),
+                    channel(named.b),
+                    channel(named.a),
+                ) == (r, g, b, a)
+            })
+            .map_or(hex, |name| name.to_string())
+    }
+}
+
+//Keeps only the declaration that wins the cascade for each property
+//Shortest 1 to 4 value form of the top,right,bottom and left values
+fn box_value(values: &[&str]) -> String {
+    let (top, right, bottom, left) = (values[0], values[1], values[2], values[3]);
+    let parts = if left != right {
+        vec![top, right, bottom, left]
+    } else if bottom != top {
+        vec![top, right, bottom]
+    } else if right != top {
+        vec![top, right]
+    } else {
+        vec![top]
+    };
+    parts.join(" ")
+}
+
+//Whether text as the value of shorthand sets exactly longhands to the values they were written with
+fn expands_to(shorthand: &str, text: &str, longhands: &[String], values: &[&str]) -> bool {
+    let expanded = parse_property_value(shorthand, &fold_case(text));
+    expanded.len() == longhands.len()
+        && expanded
+            .iter()
+            .zip(longhands.iter().zip(values.iter()))
+            .all(|((name, value), (longhand, written))| {
+                name == longhand
+                    && parse_property_value(longhand, &fold_case(written))
+                        .pop()
+                        .map_or(false, |(_, v)| v == *value)
+            })
+}
+
+fn remove_overridden(declarations: &mut Vec<SerializedDeclaration>) {
+    let mut kept: Vec<SerializedDeclaration> = Vec::new();
+    for declaration in declarations.drain(..) {
+        match kept.iter().position(|d| d.key == declaration.key) {
+            Some(i) if kept[i].important && !declaration.important => continue,
+            Some(i) => {
+                kept.remove(i);
+            }
+            None => {}
+        }
+        kept.push(declaration);
+    }
+    *declarations = kept;
+}
+
+fn quote(s: &str) -> String {
+    let mut result = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' | '\\' => {
+                result.push('\\');
+                result.push(c);
+            }
+            '\n' => result.push_str("\\a "),
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
+
+fn comparison(op: Comparison) -> &'static str {
+    match op {
+        Comparison::Less => "<",
+        Comparison::LessEqual => "<=",
+        Comparison::Equal => "=",
+        Comparison::GreaterEqual => ">=",
+        Comparison::Greater => ">",
+    }
+}
+
+fn flip(op: Comparison) -> Comparison {
+    match op {
+        Comparison::Less => Comparison::Greater,
+        Comparison::LessEqual => Comparison::GreaterEqual,
+        Comparison::Greater => Comparison::Less,
+        Comparison::GreaterEqual => Comparison::LessEqual,
+        Comparison::Equal => Comparison::Equal,
+    }
+}
+
+fn unit_name(unit: Unit) -> &'static str {
+    match unit {
+        Unit::Em => "em",
+        Unit::Ex => "ex",
+        Unit::Ch => "ch",
+        Unit::Rem => "rem",
+        Unit::Vh => "vh",
+        Unit::Vw => "vw",
+        Unit::Vmin => "vmin",
+        Unit::Vmax => "vmax",
+        Unit::Px => "px",
+        Unit::Mm => "mm",
+        Unit::Q => "q",
+        Unit::Cm => "cm",
+        Unit::In => "in",
+        Unit::Pt => "pt",
+        Unit::Pc => "pc",
+        Unit::Percent => "%",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cssparser::cssparser;
+
+    const SHEET: &str = r#"
+        @namespace svg url(http://www.w3.org/2000/svg);
+        @font-face { font-family: "Open Sans"; src: url(open.woff) format("woff"), local(Arial); font-weight: bold }
+        html, body > p.note:not(.x) { margin: 0 auto; color: #ff0000; width: calc(100% - 2em) }
+        svg|rect:first-child, *|* { background: url("a b.png") no-repeat; opacity: 50% }
+        a:is(#main, .nav) ~ span { border: 1px solid rgba(0, 0, 0, 0.5) !important; --gap: 4px }
+        @media screen and (min-width: 600px) { div { font: italic 12px/1.5 serif } }
+        @supports (display: grid) and (not (color: nonsense)) { ul { display: flex } }
+        @keyframes slide { from { left: 0 } 50% { left: 10px } to { left: 20px } }
+        p { animation: slide 2s ease-in-out infinite; unknown-prop: Keep  Me }
+    "#;
+
+    fn parse(css: &str) -> Stylesheet {
+        cssparser::new(css).parse_stylesheet()
+    }
+
+    #[test]
+    fn serialized_sheet_parses_back_to_itself() {
+        let serialized = serialize_stylesheet(&parse(SHEET));
+        assert_eq!(serialize_stylesheet(&parse(&serialized)), serialized);
+    }
+
+    #[test]
+    fn minified_sheet_parses_back_to_the_same_sheet() {
+        let stylesheet = parse(SHEET);
+        let minified = minify_stylesheet(&stylesheet);
+        assert!(minified.len() < serialize_stylesheet(&stylesheet).len());
+        assert!(!minified.contains('\n'));
+        assert_eq!(minify_stylesheet(&parse(&minified)), minified);
+        //Zero percentages lose their unit
+        assert_eq!(
+            serialize_stylesheet(&parse(&minified)),
+            serialize_stylesheet(&stylesheet).replace("0% 0%", "0 0")
+        );
+    }
+
+    #[test]
+    fn minifying_shortens_values() {
+        let minified = minify_stylesheet(&parse(
+            "p { color: #ff0000; margin-top: 0px; background-color: #ffffff }",
+        ));
+        assert_eq!(minified, "p{color:red;margin-top:0;background-color:#fff}");
+    }
+
+    #[test]
+    fn minifying_merges_adjacent_rules() {
+        assert_eq!(
+            minify_stylesheet(&parse("p { color: red } p { width: 1px } a { width: 1px }")),
+            "p{color:red;width:1px}a{width:1px}"
+        );
+        assert_eq!(
+            minify_stylesheet(&parse("p { width: 1px } a { width: 1px } b { width: 2px }")),
+            "p,a{width:1px}b{width:2px}"
+        );
+    }
+}
@@ -0,0 +1,506 @@
+//Expansion of shorthand properties into their longhands,done while parsing declarations
+use crate::cssmath;
+use crate::cssproperties::{BORDER_STYLES, CSS_WIDE_KEYWORDS};
+use crate::cssvalues::{split_components, split_top_level};
+
+const SIDES: [&str; 4] = ["top", "right", "bottom", "left"];
+
+pub type Longhands = Vec<(String, String)>;
+
+//Longhands set by a shorthand property,None if name is not a shorthand
+pub fn get_longhands(name: &str) -> Option<Vec<String>> {
+    let sides = |prefix: &str, suffix: &str| -> Vec<String> {
+        SIDES
+            .iter()
+            .map(|side| format!("{}{}{}", prefix, side, suffix))
+            .collect()
+    };
+    let longhands = match name {
+        "margin" => sides("margin-", ""),
+        "padding" => sides("padding-", ""),
+        "inset" => sides("", ""),
+        "border-width" => sides("border-", "-width"),
+        "border-style" => sides("border-", "-style"),
+        "border-color" => sides("border-", "-color"),
+        "border" => {
+            let mut all = sides("border-", "-width");
+            all.append(&mut sides("border-", "-style"));
+            all.append(&mut sides("border-", "-color"));
+            all
+        }
+        "border-top" | "border-right" | "border-bottom" | "border-left" => vec![
+            format!("{}-width", name),
+            format!("{}-style", name),
+            format!("{}-color", name),
+        ],
+        "background" => vec![
+            "background-color",
+            "background-image",
+            "background-repeat",
+            "background-attachment",
+            "background-position",
+            "background-size",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect(),
+        "font" => vec![
+            "font-style",
+            "font-variant",
+            "font-weight",
+            "font-stretch",
+            "font-size",
+            "line-height",
+            "font-family",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect(),
+        "animation" => vec![
+            "animation-name",
+            "animation-duration",
+            "animation-timing-function",
+            "animation-delay",
+            "animation-iteration-count",
+            "animation-direction",
+            "animation-fill-mode",
+            "animation-play-state",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect(),
+        "transition" => vec![
+            "transition-property",
+            "transition-duration",
+            "transition-timing-function",
+            "transition-delay",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect(),
+        _ => return None,
+    };
+    Some(longhands)
+}
+
+//Expands a shorthand declaration,None if the value does not fit the shorthand grammar
+pub fn expand_shorthand(name: &str, value: &str) -> Option<Longhands> {
+    let longhands = get_longhands(name)?;
+    let value = value.trim();
+
+    if CSS_WIDE_KEYWORDS.contains(&value) {
+        return Some(
+            longhands
+                .into_iter()
+                .map(|l| (l, value.to_string()))
+                .collect(),
+        );
+    }
+
+    let components = split_components(value);
+    if components.is_empty() {
+        return None;
+    }
+    match name {
+        "margin" | "padding" | "inset" | "border-width" | "border-style" | "border-color" => {
+            let values = expand_box_values(&components)?;
+            Some(longhands.into_iter().zip(values.into_iter()).collect())
+        }
+        "border" => {
+            let (width, style, color) = split_border(&components)?;
+            let mut result = Vec::new();
+            for (i, longhand) in longhands.into_iter().enumerate() {
+                let v = match i / 4 {
+                    0 => width.clone(),
+                    1 => style.clone(),
+                    _ => color.clone(),
+                };
+                result.push((longhand, v));
+            }
+            Some(result)
+        }
+        "border-top" | "border-right" | "border-bottom" | "border-left" => {
+            let (width, style, color) = split_border(&components)?;
+            Some(
+                longhands
+                    .into_iter()
+                    .zip(vec![width, style, color].into_iter())
+                    .collect(),
+            )
+        }
+        "background" => expand_background(&components, longhands),
+        "font" => expand_font(&components, longhands),
+        "animation" | "transition" => expand_layers(name, value, longhands),
+        _ => None,
+    }
+}
+
+//Applies the 1-to-4 value rule: top,right,bottom,left
+fn expand_box_values(components: &[String]) -> Option<Vec<String>> {
+    let c = components;
+    let values = match c.len() {
+        1 => vec![&c[0], &c[0], &c[0], &c[0]],
+        2 => vec![&c[0], &c[1], &c[0], &c[1]],
+        3 => vec![&c[0], &c[1], &c[2], &c[1]],
+        4 => vec![&c[0], &c[1], &c[2], &c[3]],
+        _ => return None,
+    };
+    Some(values.into_iter().cloned().collect())
+}
+
+//Splits <width> || <style> || <color>,filling the missing parts with initial values
+fn split_border(components: &[String]) -> Option<(String, String, String)> {
+    let mut width = None;
+    let mut style = None;
+    let mut color = None;
+
+    for component in components {
+        if is_border_width(component) && width.is_none() {
+            width = Some(component.clone());
+        } else if BORDER_STYLES.contains(&component.as_str()) && style.is_none() {
+            style = Some(component.clone());
+        } else if color.is_none() {
+            color = Some(component.clone());
+        } else {
+            return None;
+        }
+    }
+    Some((
+        width.unwrap_or_else(|| String::from("medium")),
+        style.unwrap_or_else(|| String::from("none")),
+        color.unwrap_or_else(|| String::from("currentcolor")),
+    ))
+}
+
+fn is_border_width(component: &str) -> bool {
+    match component {
+        "thin" | "medium" | "thick" => true,
+        _ => starts_like_number(component) || cssmath::is_math_function(component),
+    }
+}
+
+fn starts_like_number(component: &str) -> bool {
+    component
+        .chars()
+        .next()
+        .map_or(false, |c| c.is_digit(10) || c == '.' || c == '-' || c == '+')
+}
+
+//Only the final background layer may carry a color,earlier layers are not supported
+fn expand_background(components: &[String], longhands: Vec<String>) -> Option<Longhands> {
+    let mut color = None;
+    let mut image = None;
+    let mut repeat = Vec::new();
+    let mut attachment = None;
+    let mut position = Vec::new();
+    let mut size = Vec::new();
+    let mut after_slash = false;
+
+    for component in components {
+        let c = component.as_str();
+        if c == "/" {
+            after_slash = true;
+        } else if after_slash
+            && (starts_like_number(c) || c == "auto" || c == "cover" || c == "contain")
+        {
+            size.push(component.clone());
+        } else if c == "none" || c.starts_with("url(") || c.contains("gradient(") {
+            image = Some(component.clone());
+        } else if match c {
+            "repeat" | "repeat-x" | "repeat-y" | "no-repeat" | "space" | "round" => true,
+            _ => false,
+        } {
+            repeat.push(component.clone());
+        } else if c == "scroll" || c == "fixed" || c == "local" {
+            attachment = Some(component.clone());
+        } else if starts_like_number(c)
+            || match c {
+                "left" | "right" | "top" | "bottom" | "center" => true,
+                _ => false,
+            }
+        {
+            position.push(component.clone());
+        } else if color.is_none() {
+            color = Some(component.clone());
+        } else {
+            return None;
+        }
+    }
+
+    let or_initial = |parts: Vec<String>, initial: &str| {
+        if parts.is_empty() {
+            initial.to_string()
+        } else {
+            parts.join(" ")
+        }
+    };
+    let values = vec![
+        color.unwrap_or_else(|| String::from("transparent")),
+        image.unwrap_or_else(|| String::from("none")),
+        or_initial(repeat, "repeat"),
+        attachment.unwrap_or_else(|| String::from("scroll")),
+        or_initial(position, "0% 0%"),
+        or_initial(size, "auto"),
+    ];
+    Some(longhands.into_iter().zip(values.into_iter()).collect())
+}
+
+//[style || variant || weight || stretch]? size[/line-height]? family
+fn expand_font(components: &[String], longhands: Vec<String>) -> Option<Longhands> {
+    let mut style = None;
+    let mut variant = None;
+    let mut weight = None;
+    let mut stretch = None;
+    let mut index = 0;
+
+    while index < components.len() {
+        let c = components[index].as_str();
+        if c == "normal" {
+        } else if c == "italic" || c == "oblique" {
+            style = Some(c.to_string());
+        } else if c == "small-caps" {
+            variant = Some(c.to_string());
+        } else if c == "bold" || c == "bolder" || c == "lighter" || c.parse::<u32>().is_ok() {
+            weight = Some(c.to_string());
+        } else if c.ends_with("condensed") || c.ends_with("expanded") {
+            stretch = Some(c.to_string());
+        } else {
+            break;
+        }
+        index += 1;
+    }
+
+    let size = components.get(index)?;
+    index += 1;
+    let (size, mut line_height) = match size.find('/') {
+        Some(i) => (size[..i].to_string(), Some(size[i + 1..].to_string())),
+        None => (size.clone(), None),
+    };
+    if components.get(index).map_or(false, |c| c == "/") {
+        line_height = Some(components.get(index + 1)?.clone());
+        index += 2;
+    } else if components.get(index).map_or(false, |c| c.starts_with('/')) {
+        line_height = Some(components[index][1..].to_string());
+        index += 1;
+    }
+
+    if index >= components.len() {
+        return None;
+    }
+    let family = components[index..].join(" ");
+
+    let values = vec![
+        style.unwrap_or_else(|| String::from("normal")),
+        variant.unwrap_or_else(|| String::from("normal")),
+        weight.unwrap_or_else(|| String::from("normal")),
+        stretch.unwrap_or_else(|| String::from("normal")),
+        size,
+        line_height.unwrap_or_else(|| String::from("normal")),
+        family,
+    ];
+    Some(longhands.into_iter().zip(values.into_iter()).collect())
+}
+
+//Comma separated layers of animation or transition,each longhand gets one entry per layer.
+//The first time in a layer is the duration and the second the delay.
+fn expand_layers(name: &str, value: &str, longhands: Vec<String>) -> Option<Longhands> {
+    let mut lists = vec![Vec::new(); longhands.len()];
+
+    for layer in split_top_level(value, ',') {
+        let mut entries: Vec<Option<String>> = vec![None; longhands.len()];
+        let mut times = 0;
+        for component in split_components(layer) {
+            let c = component.as_str();
+            let slot = if is_time(c) {
+                times += 1;
+                match times {
+                    1 => 1,
+                    2 => 3,
+                    _ => return None,
+                }
+            } else if is_timing_function(c) {
+                2
+            } else if name == "transition" {
+                0
+            } else if c == "infinite" || c.parse::<f32>().is_ok() {
+                4
+            } else if match c {
+                "normal" | "reverse" | "alternate" | "alternate-reverse" => true,
+                _ => false,
+            } && entries[5].is_none()
+            {
+                5
+            } else if match c {
+                "forwards" | "backwards" | "both" => true,
+                _ => false,
+            } || (c == "none" && entries[6].is_none() && entries[0].is_some())
+            {
+                6
+            } else if c == "running" || c == "paused" {
+                7
+            } else {
+                0
+            };
+            if entries[slot].is_some() {
+                return None;
+            }
+            entries[slot] = Some(component);
+        }
+
+        let initial = match name {
+            "transition" => ["all", "0s", "ease", "0s"].to_vec(),
+            _ => ["none", "0s", "ease", "0s", "1", "normal", "none", "running"].to_vec(),
+        };
+        for (i, entry) in entries.into_iter().enumerate() {
+            lists[i].push(entry.unwrap_or_else(|| initial[i].to_string()));
+        }
+    }
+    Some(
+        longhands
+            .into_iter()
+            .zip(lists.into_iter().map(|l| l.join(", ")))
+            .collect(),
+    )
+}
+
+fn is_time(component: &str) -> bool {
+    let number = match component.strip_suffix("ms") {
+        Some(n) => n,
+        None => match component.strip_suffix('s') {
+            Some(n) => n,
+            None => return false,
+        },
+    };
+    number.parse::<f32>().is_ok()
+}
+
+fn is_timing_function(component: &str) -> bool {
+    match component {
+        "linear" | "ease" | "ease-in" | "ease-out" | "ease-in-out" | "step-start" | "step-end" => {
+            true
+        }
+        _ => component.starts_with("cubic-bezier(") || component.starts_with("steps("),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //Values of the expanded longhands,in the order of get_longhands
+    fn values(name: &str, value: &str) -> Option<Vec<String>> {
+        let longhands = expand_shorthand(name, value)?;
+        assert_eq!(
+            longhands.iter().map(|l| l.0.clone()).collect::<Vec<_>>(),
+            get_longhands(name).unwrap()
+        );
+        Some(longhands.into_iter().map(|l| l.1).collect())
+    }
+
+    #[test]
+    fn box_shorthands_take_one_to_four_values() {
+        assert_eq!(
+            values("margin", "1px").unwrap(),
+            ["1px", "1px", "1px", "1px"]
+        );
+        assert_eq!(
+            values("padding", "1px 2px").unwrap(),
+            ["1px", "2px", "1px", "2px"]
+        );
+        assert_eq!(
+            values("inset", "1px 2px 3px").unwrap(),
+            ["1px", "2px", "3px", "2px"]
+        );
+        assert_eq!(
+            values("margin", "1px auto calc(1px + 2%) 4px").unwrap(),
+            ["1px", "auto", "calc(1px + 2%)", "4px"]
+        );
+        assert_eq!(values("border-style", "solid none").unwrap()[3], "none");
+        assert!(values("margin", "1px 2px 3px 4px 5px").is_none());
+        assert!(values("margin", "").is_none());
+    }
+
+    #[test]
+    fn border_parts_in_any_order() {
+        let all = values("border", "red 2px dashed").unwrap();
+        assert_eq!(&all[0..4], ["2px", "2px", "2px", "2px"]);
+        assert_eq!(&all[4..8], ["dashed", "dashed", "dashed", "dashed"]);
+        assert_eq!(&all[8..12], ["red", "red", "red", "red"]);
+        assert_eq!(
+            values("border-left", "thick").unwrap(),
+            ["thick", "none", "currentcolor"]
+        );
+        assert_eq!(
+            values("border-top", "solid rgb(0, 0, 0)").unwrap(),
+            ["medium", "solid", "rgb(0, 0, 0)"]
+        );
+        assert!(values("border", "red blue").is_none());
+    }
+
+    #[test]
+    fn background_fills_missing_parts_with_initial_values() {
+        assert_eq!(
+            values(
+                "background",
+                "url(a.png) no-repeat fixed center / cover #fff"
+            )
+            .unwrap(),
+            [
+                "#fff",
+                "url(a.png)",
+                "no-repeat",
+                "fixed",
+                "center",
+                "cover"
+            ]
+        );
+        assert_eq!(
+            values("background", "blue").unwrap(),
+            ["blue", "none", "repeat", "scroll", "0% 0%", "auto"]
+        );
+        assert!(values("background", "red blue").is_none());
+    }
+
+    #[test]
+    fn font_needs_a_size_and_a_family() {
+        assert_eq!(
+            values("font", "italic bold 12px/1.5 \"Open Sans\", serif").unwrap(),
+            [
+                "italic",
+                "normal",
+                "bold",
+                "normal",
+                "12px",
+                "1.5",
+                "\"Open Sans\", serif"
+            ]
+        );
+        assert_eq!(
+            values("font", "condensed 1em / 2 monospace").unwrap(),
+            [
+                "normal",
+                "normal",
+                "normal",
+                "condensed",
+                "1em",
+                "2",
+                "monospace"
+            ]
+        );
+        assert!(values("font", "12px").is_none());
+        assert!(values("font", "bold serif").is_none());
+    }
+
+    #[test]
+    fn wide_keywords_set_every_longhand() {
+        assert_eq!(
+            values("border-color", "inherit").unwrap(),
+            ["inherit", "inherit", "inherit", "inherit"]
+        );
+        assert!(values("font", "initial")
+            .unwrap()
+            .iter()
+            .all(|v| v == "initial"));
+        assert!(expand_shorthand("color", "red").is_none());
+    }
+}
@@ -0,0 +1,165 @@
+//Parser for @supports conditions such as `(display: grid) and (not selector(a > b))`.
+//Whether a condition holds is decided by the stylesheet parser,see cssparser::supports.
+use crate::csselements::SupportsCondition;
+
+//None when the prelude is not a valid condition,the whole @supports rule is then dropped
+pub fn parse_supports_condition(prelude: &str) -> Option<SupportsCondition> {
+    let mut parser = SupportsParser {
+        text: prelude.trim(),
+        position: 0,
+    };
+    let condition = parser.parse_condition()?;
+    parser.skip_whitespace();
+    if parser.position != parser.text.len() {
+        return None;
+    }
+    Some(condition)
+}
+
+struct SupportsParser<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl<'a> SupportsParser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.text[self.position..]
+    }
+
+    fn skip_whitespace(&mut self) -> bool {
+        let rest = self.rest();
+        let trimmed = rest.trim_start();
+        self.position += rest.len() - trimmed.len();
+        rest.len() != trimmed.len()
+    }
+
+    //Consumes keyword when it is followed by whitespace or a parenthesis
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let rest = self.rest();
+        if rest.len() <= keyword.len() || !rest[..keyword.len()].eq_ignore_ascii_case(keyword) {
+            return false;
+        }
+        match rest[keyword.len()..].chars().next() {
+            Some(c) if c.is_whitespace() || c == '(' => {
+                self.position += keyword.len();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    //not <in-parens> | <in-parens> [and <in-parens>]* | <in-parens> [or <in-parens>]*
+    fn parse_condition(&mut self) -> Option<SupportsCondition> {
+        self.skip_whitespace();
+        if self.eat_keyword("not") {
+            self.skip_whitespace();
+            return Some(SupportsCondition::Not(Box::new(self.parse_in_parens()?)));
+        }
+
+        let first = self.parse_in_parens()?;
+        let mut conditions = vec![first];
+        let mut joiner = None;
+        loop {
+            let before = self.position;
+            self.skip_whitespace();
+            let next = if self.eat_keyword("and") {
+                "and"
+            } else if self.eat_keyword("or") {
+                "or"
+            } else {
+                self.position = before;
+                break;
+            };
+            //and and or can not be mixed without parentheses
+            if joiner.map_or(false, |j| j != next) {
+                return None;
+            }
+            joiner = Some(next);
+            self.skip_whitespace();
+            conditions.push(self.parse_in_parens()?);
+        }
+
+        match joiner {
+            None => conditions.pop(),
+            Some("and") => Some(SupportsCondition::And(conditions)),
+            Some(_) => Some(SupportsCondition::Or(conditions)),
+        }
+    }
+
+    //( <condition> ) | ( <declaration> ) | selector( <selector> ) | <general-enclosed>
+    fn parse_in_parens(&mut self) -> Option<SupportsCondition> {
+        let rest = self.rest();
+        let open = rest.find('(')?;
+        let name = &rest[..open];
+        if !name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+        {
+            return None;
+        }
+        let length = open + balanced_length(&rest[open..])?;
+        let whole = &rest[..length];
+        let inner = rest[open + 1..length - 1].trim();
+        self.position += length;
+
+        if name.eq_ignore_ascii_case("selector") {
+            return Some(SupportsCondition::Selector(inner.to_string()));
+        }
+        if !name.is_empty() {
+            return Some(SupportsCondition::Unknown(whole.to_string()));
+        }
+        if let Some(condition) = parse_supports_condition(inner) {
+            return Some(condition);
+        }
+        match inner.find(':') {
+            Some(colon) if is_property_name(inner[..colon].trim()) => {
+                Some(SupportsCondition::Declaration(
+                    inner[..colon].trim().to_string(),
+                    inner[colon + 1..].trim().to_string(),
+                ))
+            }
+            _ => Some(SupportsCondition::Unknown(whole.to_string())),
+        }
+    }
+}
+
+fn is_property_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_' || !c.is_ascii())
+}
+
+//Length of the parenthesized block text starts with,strings are skipped over
+fn balanced_length(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match quote {
+            Some(q) => {
+                if c == '\\' {
+                    escaped = true;
+                } else if c == q {
+                    quote = None;
+                }
+            }
+            None => match c {
+                '"' | '\'' => quote = Some(c),
+                '(' => depth += 1,
+                ')' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(i + 1);
+                    }
+                }
+                _ => {}
+            },
+        }
+    }
+    None
+}
@@ -0,0 +1,12 @@
+//The built-in user-agent stylesheet,the lowest cascade origin under user and author sheets.
+//useragent.css holds the defaults of the rendering section of the HTML standard.
+use crate::csselements::{Origin, Stylesheet};
+use crate::cssparser::cssparser;
+
+const USER_AGENT_CSS: &str = include_str!("useragent.css");
+
+pub fn user_agent_stylesheet() -> Stylesheet {
+    let mut stylesheet = cssparser::new(USER_AGENT_CSS).parse_stylesheet();
+    stylesheet.origin = Origin::UserAgent;
+    stylesheet
+}
@@ -0,0 +1,302 @@
+//Component values: space and comma separated lists,strings,urls,identifiers and functions
+use crate::csscolor;
+use crate::csselements::{match_unit, ListSeparator, Value};
+
+//Lowercases value except for quoted strings and unquoted url() contents,which are case sensitive
+pub fn fold_case(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars().peekable();
+    let mut quote = None;
+    let mut in_url = false;
+
+    while let Some(c) = chars.next() {
+        if let Some(q) = quote {
+            result.push(c);
+            if c == '\\' {
+                if let Some(escaped) = chars.next() {
+                    result.push(escaped);
+                }
+            } else if c == q {
+                quote = None;
+            }
+            continue;
+        }
+        if in_url {
+            result.push(c);
+            in_url = c != ')';
+            continue;
+        }
+        match c {
+            '"' | '\'' => {
+                quote = Some(c);
+                result.push(c);
+            }
+            '(' if result.ends_with("url") => {
+                result.push(c);
+                while chars.peek().map_or(false, |c| c.is_whitespace()) {
+                    result.push(chars.next().unwrap());
+                }
+                in_url = chars.peek().map_or(false, |c| *c != '"' && *c != '\'');
+            }
+            _ => result.extend(c.to_lowercase()),
+        }
+    }
+    result
+}
+
+//Parses a case folded value into a component value,a list when it has more than one component
+pub fn parse_component_list(value: &str) -> Option<Value> {
+    let groups = split_top_level(value, ',');
+    if groups.len() == 1 {
+        return parse_space_list(groups[0]);
+    }
+    let values = groups
+        .into_iter()
+        .map(parse_space_list)
+        .collect::<Option<Vec<Value>>>()?;
+    Some(Value::List(values, ListSeparator::Comma))
+}
+
+fn parse_space_list(value: &str) -> Option<Value> {
+    let mut components = split_components(value)
+        .iter()
+        .map(|c| parse_component(c))
+        .collect::<Option<Vec<Value>>>()?;
+    match components.len() {
+        0 => None,
+        1 => components.pop(),
+        _ => Some(Value::List(components, ListSeparator::Space)),
+    }
+}
+
+fn parse_component(component: &str) -> Option<Value> {
+    if component.starts_with('"') || component.starts_with('\'') {
+        return parse_string(component).map(Value::String);
+    }
+    if component.starts_with('#') {
+        return csscolor::parse_color(component).map(Value::Color);
+    }
+    if let Some(open) = component.find('(') {
+        if !component.ends_with(')') {
+            return None;
+        }
+        let name = &component[..open];
+        let arguments = component[open + 1..component.len() - 1].trim();
+        if name == "url" {
+            let url = match parse_string(arguments) {
+                Some(s) => s,
+                None => arguments.to_string(),
+            };
+            return Some(Value::Url(url));
+        }
+        if let Some(color) = csscolor::parse_color(component) {
+            return Some(Value::Color(color));
+        }
+        let arguments = match arguments.is_empty() {
+            true => Vec::new(),
+            false => split_top_level(arguments, ',')
+                .into_iter()
+                .map(parse_space_list)
+                .collect::<Option<Vec<Value>>>()?,
+        };
+        return Some(Value::Function(name.to_string(), arguments));
+    }
+    if let Some(value) = parse_numeric(component) {
+        return Some(value);
+    }
+    if is_identifier(component) {
+        return Some(Value::Ident(component.to_string()));
+    }
+    Some(Value::Other(component.to_string()))
+}
+
+pub fn is_identifier(component: &str) -> bool {
+    let mut chars = component.chars();
+    let first = match chars.next() {
+        Some('-') => chars.next(),
+        c => c,
+    };
+    first.map_or(false, |c| {
+        c.is_alphabetic() || c == '_' || c == '-' || !c.is_ascii()
+    }) && chars.all(|c| c.is_alphanumeric() || c == '-' || c == '_' || !c.is_ascii())
+}
+
+//A plain number,a length,a time or an angle
+fn parse_numeric(component: &str) -> Option<Value> {
+    let unit_start = component
+        .char_indices()
+        .find(|&(i, c)| c.is_alphabetic() || c == '%' || (i > 0 && (c == '+' || c == '-')))
+        .map_or(component.len(), |(i, _)| i);
+    let number = component[..unit_start].parse::<f32>().ok()?;
+    match &component[unit_start..] {
+        "" => Some(Value::Number(number)),
+        "s" => Some(Value::Time(number)),
+        "ms" => Some(Value::Time(number / 1000.0)),
+        "deg" => Some(Value::Angle(number)),
+        "grad" => Some(Value::Angle(number * 0.9)),
+        "rad" => Some(Value::Angle(number.to_degrees())),
+        "turn" => Some(Value::Angle(number * 360.0)),
+        unit => Some(Value::Length(number, match_unit(unit)?)),
+    }
+}
+
+//Contents of a quoted string with its escapes resolved,None if value is not exactly one string
+fn parse_string(value: &str) -> Option<String> {
+    let mut chars = value.chars();
+    let quote = chars.next().filter(|q| *q == '"' || *q == '\'')?;
+    let mut result = String::new();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                let mut hex = String::new();
+                let mut rest = chars.clone();
+                while hex.len() < 6 {
+                    match rest.next() {
+                        Some(h) if h.is_digit(16) => {
+                            hex.push(h);
+                            chars.next();
+                        }
+                        _ => break,
+                    }
+                }
+                if hex.is_empty() {
+                    result.push(chars.next()?);
+                } else {
+                    result.push(
+                        std::char::from_u32(u32::from_str_radix(&hex, 16).ok()?)
+                            .unwrap_or('\u{fffd}'),
+                    );
+                    if chars.clone().next().map_or(false, |c| c.is_whitespace()) {
+                        chars.next();
+                    }
+                }
+            }
+            c if c == quote => {
+                return match chars.next() {
+                    None => Some(result),
+                    Some(_) => None,
+                };
+            }
+            c => result.push(c),
+        }
+    }
+    None
+}
+
+//Splits on separator outside of strings and parentheses
+pub fn split_top_level(value: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut quote = None;
+    let mut escaped = false;
+    let mut start = 0;
+
+    for (i, c) in value.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match quote {
+            Some(q) => {
+                if c == '\\' {
+                    escaped = true;
+                } else if c == q {
+                    quote = None;
+                }
+            }
+            None => match c {
+                '"' | '\'' => quote = Some(c),
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                c if c == separator && depth == 0 => {
+                    parts.push(value[start..i].trim());
+                    start = i + c.len_utf8();
+                }
+                _ => {}
+            },
+        }
+    }
+    parts.push(value[start..].trim());
+    parts
+}
+
+//Splits a value on top level whitespace,keeping functions and strings whole
+pub fn split_components(value: &str) -> Vec<String> {
+    let mut components = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0;
+    let mut quote = None;
+
+    for c in value.chars() {
+        match quote {
+            Some(q) => {
+                current.push(c);
+                if c == q {
+                    quote = None;
+                }
+                continue;
+            }
+            None => {}
+        }
+        match c {
+            '"' | '\'' => {
+                quote = Some(c);
+                current.push(c);
+            }
+            '(' => {
+                depth += 1;
+                current.push(c);
+            }
+            ')' => {
+                depth -= 1;
+                current.push(c);
+            }
+            '/' if depth == 0 && current.is_empty() => components.push(String::from("/")),
+            c if c.is_whitespace() && depth == 0 => {
+                if !current.is_empty() {
+                    components.push(current.clone());
+                    current.clear();
+                }
+            }
+            _ => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        components.push(current);
+    }
+    components
+}
+
+//Whether check accepts every item of a comma separated list,or value itself when it is not one
+pub fn each_item(value: &Value, check: &dyn Fn(&Value) -> bool) -> bool {
+    match *value {
+        Value::List(ref items, ListSeparator::Comma) => items.iter().all(|v| check(v)),
+        _ => check(value),
+    }
+}
+
+//The components of a space separated list,or value itself when it is not one
+pub fn space_items(value: &Value) -> Vec<&Value> {
+    match *value {
+        Value::List(ref items, ListSeparator::Space) => items.iter().collect(),
+        _ => vec![value],
+    }
+}
+
+pub fn is_ident_in(value: &Value, idents: &[&str]) -> bool {
+    match *value {
+        Value::Ident(ref i) => idents.contains(&i.as_str()),
+        _ => false,
+    }
+}
+
+//A length,a percentage,a unitless zero or a math function,which is checked when it is computed
+pub fn is_length_percentage(value: &Value, negative: bool) -> bool {
+    match *value {
+        Value::Length(n, _) => negative || n >= 0.0,
+        Value::Number(n) => n == 0.0,
+        Value::Function(ref name, _) => ["calc", "min", "max", "clamp"].contains(&name.as_str()),
+        _ => false,
+    }
+}
@@ -0,0 +1,301 @@
+//Custom properties (--name) and var() substitution,done when the style tree is built
+use std::collections::{HashMap, HashSet};
+
+//Computed custom properties of an element,the values are the unparsed text with var() already substituted
+pub type CustomProperties = HashMap<String, String>;
+
+pub fn is_custom_property(name: &str) -> bool {
+    name.starts_with("--")
+}
+
+pub fn contains_var(value: &str) -> bool {
+    find_var(value, 0).is_some()
+}
+
+//Computes the custom properties of an element from its parent's and the cascaded `--name: value` pairs.
+//Properties that are part of a reference cycle,or that reference an invalid one without a fallback,
+//are invalid at computed-value time and are left out.
+pub fn compute_custom_properties(
+    inherited: &CustomProperties,
+    specified: &[(&str, &str)],
+) -> CustomProperties {
+    let mut resolver = CustomResolver {
+        computed: inherited.clone(),
+        pending: HashMap::new(),
+        stack: Vec::new(),
+        cyclic: HashSet::new(),
+    };
+    for &(name, value) in specified {
+        match value.trim() {
+            "initial" => {
+                resolver.computed.remove(name);
+                resolver.pending.remove(name);
+            }
+            "inherit" | "unset" | "revert" => {
+                resolver.pending.remove(name);
+            }
+            _ => {
+                resolver
+                    .pending
+                    .insert(name.to_string(), value.trim().to_string());
+            }
+        }
+    }
+
+    let names = resolver.pending.keys().cloned().collect::<Vec<String>>();
+    for name in names {
+        resolver.get(&name);
+    }
+    resolver.computed
+}
+
+struct CustomResolver {
+    computed: CustomProperties,
+    //Specified on this element but not yet substituted
+    pending: HashMap<String, String>,
+    stack: Vec<String>,
+    cyclic: HashSet<String>,
+}
+
+impl CustomResolver {
+    fn get(&mut self, name: &str) -> Option<String> {
+        if let Some(position) = self.stack.iter().position(|n| n == name) {
+            for n in &self.stack[position..] {
+                self.cyclic.insert(n.clone());
+            }
+            return None;
+        }
+        let value = match self.pending.remove(name) {
+            Some(v) => v,
+            None => return self.computed.get(name).cloned(),
+        };
+
+        self.stack.push(name.to_string());
+        let substituted = substitute_vars(&value, &mut |n: &str| self.get(n));
+        self.stack.pop();
+
+        match substituted {
+            Some(v) if !self.cyclic.contains(name) => {
+                self.computed.insert(name.to_string(), v.clone());
+                Some(v)
+            }
+            _ => {
+                self.computed.remove(name);
+                None
+            }
+        }
+    }
+}
+
+//Replaces every var(--name[,fallback]) in value,None if a reference can not be resolved
+pub fn substitute_vars(
+    value: &str,
+    lookup: &mut dyn FnMut(&str) -> Option<String>,
+) -> Option<String> {
+    let mut result = String::new();
+    let mut position = 0;
+
+    while let Some(start) = find_var(value, position) {
+        result.push_str(&value[position..start]);
+        let arguments_start = start + "var(".len();
+        let arguments_end = find_closing_paren(value, arguments_start)?;
+        let arguments = &value[arguments_start..arguments_end];
+
+        let (name, fallback) = match find_top_level_comma(arguments) {
+            Some(i) => (arguments[..i].trim(), Some(&arguments[i + 1..])),
+            None => (arguments.trim(), None),
+        };
+        if !is_custom_property(name) {
+            return None;
+        }
+        let replacement = match lookup(name) {
+            Some(v) => v,
+            None => substitute_vars(fallback?.trim(), lookup)?,
+        };
+        result.push_str(&replacement);
+        position = arguments_end + 1;
+    }
+    result.push_str(&value[position..]);
+    Some(result)
+}
+
+//Byte offset of the next var( that is not part of a longer identifier or a string
+fn find_var(value: &str, from: usize) -> Option<usize> {
+    let bytes = value.as_bytes();
+    let mut quote = None;
+    let mut i = from;
+
+    while i < bytes.len() {
+        let b = bytes[i];
+        match quote {
+            Some(q) => {
+                if b == q {
+                    quote = None;
+                }
+            }
+            None if b == b'"' || b == b'\'' => quote = Some(b),
+            None => {
+                let starts_identifier = i == 0 || {
+                    let p = bytes[i - 1];
+                    !(p.is_ascii_alphanumeric() || p == b'-' || p == b'_')
+                };
+                if starts_identifier
+                    && i + 4 <= bytes.len()
+                    && bytes[i..i + 4].eq_ignore_ascii_case(b"var(")
+                {
+                    return Some(i);
+                }
+            }
+        }
+        i += 1;
+    }
+    None
+}
+
+fn find_closing_paren(value: &str, from: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut quote = None;
+
+    for (i, c) in value[from..].char_indices() {
+        match quote {
+            Some(q) => {
+                if c == q {
+                    quote = None;
+                }
+            }
+            None => match c {
+                '"' | '\'' => quote = Some(c),
+                '(' => depth += 1,
+                ')' if depth == 0 => return Some(from + i),
+                ')' => depth -= 1,
+                _ => {}
+            },
+        }
+    }
+    None
+}
+
+fn find_top_level_comma(arguments: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut quote = None;
+
+    for (i, c) in arguments.char_indices() {
+        match quote {
+            Some(q) => {
+                if c == q {
+                    quote = None;
+                }
+            }
+            None => match c {
+                '"' | '\'' => quote = Some(c),
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                ',' if depth == 0 => return Some(i),
+                _ => {}
+            },
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compute(inherited: &[(&str, &str)], specified: &[(&str, &str)]) -> Vec<(String, String)> {
+        let inherited = inherited
+            .iter()
+            .map(|&(n, v)| (n.to_string(), v.to_string()))
+            .collect();
+        let mut computed = compute_custom_properties(&inherited, specified)
+            .into_iter()
+            .collect::<Vec<_>>();
+        computed.sort();
+        computed
+    }
+
+    fn pairs(list: &[(&str, &str)]) -> Vec<(String, String)> {
+        list.iter()
+            .map(|&(n, v)| (n.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn references_are_substituted_in_any_order() {
+        assert_eq!(
+            compute(&[], &[("--b", "var(--a) 2px"), ("--a", "1px")]),
+            pairs(&[("--a", "1px"), ("--b", "1px 2px")])
+        );
+        assert_eq!(
+            compute(&[("--a", "red")], &[("--b", "var(--a)")]),
+            pairs(&[("--a", "red"), ("--b", "red")])
+        );
+    }
+
+    #[test]
+    fn reference_cycles_are_invalid() {
+        assert_eq!(
+            compute(
+                &[],
+                &[("--a", "var(--b)"), ("--b", "var(--a)"), ("--c", "1px")]
+            ),
+            pairs(&[("--c", "1px")])
+        );
+        assert_eq!(compute(&[], &[("--a", "var(--a, 1px)")]), pairs(&[]));
+        //A property that only references a cycle is not part of it,its fallback applies
+        assert_eq!(
+            compute(
+                &[],
+                &[
+                    ("--a", "var(--b)"),
+                    ("--b", "var(--a)"),
+                    ("--c", "var(--a, 2px)")
+                ]
+            ),
+            pairs(&[("--c", "2px")])
+        );
+        assert_eq!(
+            compute(&[("--a", "1px")], &[("--a", "var(--a)")]),
+            pairs(&[])
+        );
+    }
+
+    #[test]
+    fn wide_keywords() {
+        assert_eq!(
+            compute(&[("--a", "1px")], &[("--a", "initial")]),
+            pairs(&[])
+        );
+        assert_eq!(
+            compute(&[("--a", "1px")], &[("--a", "inherit")]),
+            pairs(&[("--a", "1px")])
+        );
+    }
+
+    #[test]
+    fn fallbacks_and_invalid_references() {
+        let mut lookup = |name: &str| match name {
+            "--x" => Some(String::from("1px")),
+            _ => None,
+        };
+        assert_eq!(
+            substitute_vars("calc(var(--x) + var(--y, 2px))", &mut lookup),
+            Some(String::from("calc(1px + 2px)"))
+        );
+        assert_eq!(
+            substitute_vars("var(--y, var(--z, 3px))", &mut lookup),
+            Some(String::from("3px"))
+        );
+        assert_eq!(
+            substitute_vars("var(--y, a, b)", &mut lookup),
+            Some(String::from("a, b"))
+        );
+        assert_eq!(substitute_vars("var(--y)", &mut lookup), None);
+        assert_eq!(substitute_vars("var(x)", &mut lookup), None);
+        assert_eq!(substitute_vars("var(--x", &mut lookup), None);
+        assert_eq!(
+            substitute_vars("\"var(--x)\"", &mut lookup),
+            Some(String::from("\"var(--x)\""))
+        );
+    }
+}
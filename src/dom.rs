@@ -3,6 +3,10 @@ use std::collections::{HashMap,HashSet};
 use std::fmt;
 
 pub type AttrMap=HashMap<String,String>;
+
+pub const HTML_NAMESPACE:&str="http://www.w3.org/1999/xhtml";
+pub const SVG_NAMESPACE:&str="http://www.w3.org/2000/svg";
+pub const MATHML_NAMESPACE:&str="http://www.w3.org/1998/Math/MathML";
 //Defines a basic Node
 #[derive(PartialEq,Eq)]
 pub struct Node{
@@ -21,6 +25,7 @@ pub enum NodeType{
 pub struct ElementData{
    pub tag_name:String,
     pub attributes:AttrMap,
+    pub namespace:String,
 }
 impl Node{
     pub fn new(node_type:NodeType,children:Vec<Node>)->Node{
@@ -28,17 +33,28 @@ impl Node{
             node_type,children
         }
     }
-} 
+    //Moves every element in this subtree that is still in the HTML namespace into namespace
+    pub fn set_namespace(&mut self,namespace:&str){
+        if let NodeType::Element(ref mut e)=self.node_type{
+            if e.namespace==HTML_NAMESPACE{
+                e.namespace=namespace.to_string();
+            }
+        }
+        for child in self.children.iter_mut(){
+            child.set_namespace(namespace);
+        }
+    }
+}
  impl ElementData{
     pub fn new(tag_name:String,attributes:AttrMap)-> ElementData{
-        ElementData{tag_name,attributes}
+        ElementData{tag_name,attributes,namespace:String::from(HTML_NAMESPACE)}
     }
     pub fn getId(&self)->Option<&String>{
         self.attributes.get("id")
     }
     pub fn getClasses(&self)->HashSet<&str>{
-        match self.attributes.get("classes"){
-            Some(s)=>s.split(' ').collect(),
+        match self.attributes.get("class"){
+            Some(s)=>s.split_whitespace().collect(),
             None=>HashSet::new(),
         }
 
@@ -1,4 +1,4 @@
-use crate::dom::{AttrMap, ElementData, Node, NodeType};
+use crate::dom::{AttrMap, ElementData, Node, NodeType, MATHML_NAMESPACE, SVG_NAMESPACE};
 
 use std::iter::Peekable;
 use std::str::Chars;
@@ -32,6 +32,10 @@ impl<'a> HtmlParser<'a> {
                     self.consume_while(|x| x != '>');
                     self.chars.next();
 
+                    //Void elements have no end tag,a stray one is ignored
+                    if is_void_element(&close_tag_name) {
+                        continue;
+                    }
                     self.node_q.push(close_tag_name);
                     break;
                 } else if self.chars.peek().map_or(false, |c| *c == '!') {
@@ -68,9 +72,24 @@ impl<'a> HtmlParser<'a> {
         let tagname = self.consume_while(is_valid_tag_name);
         let attributes = self.parse_attributes();
 
+        let namespace = match tagname.to_lowercase().as_ref() {
+            "svg" => Some(SVG_NAMESPACE),
+            "math" => Some(MATHML_NAMESPACE),
+            _ => None,
+        };
+
+        let children = match tagname.to_lowercase().as_ref() {
+            "style" | "script" => self.parse_raw_text(&tagname),
+            _ if is_void_element(&tagname) => Vec::new(),
+            _ => self.parse_nodes(),
+        };
         let elem = ElementData::new(tagname, attributes);
-        let children = self.parse_nodes();
-        Node::new(NodeType::Element(elem), children)
+        let mut node = Node::new(NodeType::Element(elem), children);
+
+        if let Some(namespace) = namespace {
+            node.set_namespace(namespace);
+        }
+        node
     }
 
     fn parse_text_node(&mut self) -> Node {
@@ -87,6 +106,31 @@ impl<'a> HtmlParser<'a> {
         Node::new(NodeType::Text(text_content), Vec::new())
     }
 
+    //The content of <style> and <script> is not markup,it runs up to the matching end tag
+    fn parse_raw_text(&mut self, tag_name: &str) -> Vec<Node> {
+        let end_tag = format!("</{}", tag_name);
+        let mut text_content = String::new();
+
+        loop {
+            text_content.push_str(&self.consume_while(|c| c != '<'));
+            let ahead = self.chars.clone().take(end_tag.len()).collect::<String>();
+            if ahead.eq_ignore_ascii_case(&end_tag) {
+                self.consume_while(|c| c != '>');
+                self.chars.next();
+                break;
+            }
+            match self.chars.next() {
+                Some(c) => text_content.push(c),
+                None => break,
+            }
+        }
+
+        if text_content.is_empty() {
+            return Vec::new();
+        }
+        vec![Node::new(NodeType::Text(text_content), Vec::new())]
+    }
+
     fn parse_comment_node(&mut self) -> Node {
         let mut comment_content = String::new();
 
@@ -187,6 +231,11 @@ impl<'a> HtmlParser<'a> {
 
         while self.chars.peek().map_or(false, |c| *c != '>') {
             self.consume_while(char::is_whitespace);
+            //The slash of a self-closing tag such as <link ... /> carries no meaning
+            if self.chars.peek().map_or(false, |c| *c == '/') {
+                self.chars.next();
+                continue;
+            }
             let name = self.consume_while(|c| is_valid_attr_name(c)).to_lowercase();
             self.consume_while(char::is_whitespace);
 
@@ -240,6 +289,14 @@ fn is_valid_tag_name(ch: char) -> bool {
     ch.is_digit(36)
 }
 
+fn is_void_element(tag_name: &str) -> bool {
+    match tag_name.to_lowercase().as_ref() {
+        "area" | "base" | "br" | "col" | "embed" | "hr" | "img" | "input" | "link" | "meta"
+        | "source" | "track" | "wbr" => true,
+        _ => false,
+    }
+}
+
 fn is_valid_attr_name(c: char) -> bool {
     !is_excluded_name(c) && !is_control(c)
 }
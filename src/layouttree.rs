@@ -1,13 +1,13 @@
 use std::fmt;
 
-use crate::csselements::{Unit, Value};
-use crate::styletree::{Display, StyleNode};
+use crate::csselements::{Display, MathType, Unit};
+use crate::styletree::{ComputedLength, StyleNode};
 
 #[derive(Clone)]
 pub struct LayoutContainer<'a> {
     pub dims: Dimensions,
     boxtype: BoxType,
-    pub style_node: &'a StyleNode<'a>,
+    pub style_node: &'a StyleNode,
     pub children_nodes: Vec<LayoutContainer<'a>>,
 }
 #[derive(Clone, Copy, Default)]
@@ -17,6 +17,9 @@ pub struct Dimensions {
     pub border: EdgeValues,
     margin: EdgeValues,
     current: Rectangle,
+    //Height of the content box when it does not depend on the content,percentage heights
+    //of the children resolve against it
+    definite_height: Option<f32>,
 }
 #[derive(Clone, Copy, Default)]
 pub struct Rectangle {
@@ -59,37 +62,39 @@ impl<'a> LayoutContainer<'a> {
     fn layout_inline_block(&mut self, dims: Dimensions) {
         self.calculate_inline_horizontal(dims);
         self.calculate_inline_vertical(dims);
+        self.dims.definite_height = get_definite_height(&self.style_node.style.height, dims);
         self.layout_children();
         self.calculate_height();
     }
     fn layout_block(&mut self, dims: Dimensions) {
         self.calculate_horizontal(dims);
         self.calculate_vertical(dims);
+        self.dims.definite_height = get_definite_height(&self.style_node.style.height, dims);
         self.layout_children();
         self.calculate_height();
     }
     fn calculate_inline_horizontal(&mut self, dims: Dimensions) {
-        let s = self.style_node;
+        let s = &self.style_node.style;
         let d = &mut self.dims;
 
-        d.coordinates.width = get_absolute_num(s, dims, "width").unwrap_or(0.0);
-        d.margin.left = s.num_or("margin-left", 0.0);
-        d.margin.right = s.num_or("margin-right", 0.0);
-        d.padding.left = s.num_or("padding-left", 0.0);
-        d.padding.right = s.num_or("padding-right", 0.0);
-        d.border.left = s.num_or("border-left-width", 0.0);
-        d.border.right = s.num_or("border-right-width", 0.0);
+        d.coordinates.width = get_absolute_num(&s.width, dims).unwrap_or(0.0);
+        d.margin.left = get_absolute_num(&s.margin.left, dims).unwrap_or(0.0);
+        d.margin.right = get_absolute_num(&s.margin.right, dims).unwrap_or(0.0);
+        d.padding.left = get_absolute_num(&s.padding.left, dims).unwrap_or(0.0);
+        d.padding.right = get_absolute_num(&s.padding.right, dims).unwrap_or(0.0);
+        d.border.left = s.border_width.left;
+        d.border.right = s.border_width.right;
     }
     fn calculate_inline_vertical(&mut self, dims: Dimensions) {
-        let style = self.style_node;
+        let style = &self.style_node.style;
         let d = &mut self.dims;
 
-        d.margin.top = style.num_or("margin-top", 0.0);
-        d.margin.bottom = style.num_or("margin-bottom", 0.0);
-        d.border.top = style.num_or("border-top-width", 0.0);
-        d.border.bottom = style.num_or("border-bottom-width", 0.0);
-        d.padding.top = style.num_or("padding-top", 0.0);
-        d.padding.bottom = style.num_or("padding-bottom", 0.0);
+        d.margin.top = get_absolute_num(&style.margin.top, dims).unwrap_or(0.0);
+        d.margin.bottom = get_absolute_num(&style.margin.bottom, dims).unwrap_or(0.0);
+        d.border.top = style.border_width.top;
+        d.border.bottom = style.border_width.bottom;
+        d.padding.top = get_absolute_num(&style.padding.top, dims).unwrap_or(0.0);
+        d.padding.bottom = get_absolute_num(&style.padding.bottom, dims).unwrap_or(0.0);
 
         d.coordinates.x =
             dims.coordinates.x + dims.current.x + d.margin.left + d.border.left + d.padding.left;
@@ -101,29 +106,17 @@ impl<'a> LayoutContainer<'a> {
     }
 
     fn calculate_horizontal(&mut self, dims: Dimensions) {
-        let s = self.style_node;
+        let s = &self.style_node.style;
         let d = &mut self.dims;
-        let width = get_absolute_num(s, dims, "width").unwrap_or(0.0);
-        let is_left_margin = s.get_value("margin-left");
-        let is_right_margin = s.get_value("margin-right");
-        let left_margin = match is_left_margin {
-            Some(m) => match **m {
-                Value::Other(ref s) => s.parse().unwrap_or(0.0),
-                _ => 0.0,
-            },
-            None => 0.0,
-        };
-        let right_margin = match is_right_margin {
-            Some(m) => match **m {
-                Value::Other(ref s) => s.parse().unwrap_or(0.0),
-                _ => 0.0,
-            },
-            None => 0.0,
-        };
-        d.padding.left = s.num_or("padding-left", 0.0);
-        d.padding.right = s.num_or("padding-right", 0.0);
-        d.border.left = s.num_or("border-left-width", 0.0);
-        d.border.right = s.num_or("border-right-width", 0.0);
+        let width = get_absolute_num(&s.width, dims).unwrap_or(0.0);
+        let is_left_margin = s.margin.left != ComputedLength::Auto;
+        let is_right_margin = s.margin.right != ComputedLength::Auto;
+        let left_margin = get_absolute_num(&s.margin.left, dims).unwrap_or(0.0);
+        let right_margin = get_absolute_num(&s.margin.right, dims).unwrap_or(0.0);
+        d.padding.left = get_absolute_num(&s.padding.left, dims).unwrap_or(0.0);
+        d.padding.right = get_absolute_num(&s.padding.right, dims).unwrap_or(0.0);
+        d.border.left = s.border_width.left;
+        d.border.right = s.border_width.right;
 
         let total = width
             + left_margin
@@ -144,17 +137,17 @@ impl<'a> LayoutContainer<'a> {
                 }
                 d.margin.left = left_margin;
             }
-            (w, None, Some(_)) if w != 0.0 => {
+            (w, false, true) if w != 0.0 => {
                 d.margin.left = underflow;
                 d.margin.right = right_margin;
                 d.coordinates.width = w;
             }
-            (w, Some(_), None) if w != 0.0 => {
+            (w, true, false) if w != 0.0 => {
                 d.margin.right = underflow;
                 d.margin.left = left_margin;
                 d.coordinates.width = w;
             }
-            (w, None, None) if w != 0.0 => {
+            (w, false, false) if w != 0.0 => {
                 d.margin.left = underflow / 2.0;
                 d.margin.right = underflow / 2.0;
                 d.coordinates.width = w;
@@ -167,15 +160,15 @@ impl<'a> LayoutContainer<'a> {
         }
     }
     fn calculate_vertical(&mut self, dims: Dimensions) {
-        let style = self.style_node;
+        let style = &self.style_node.style;
         let d = &mut self.dims;
 
-        d.margin.top = style.num_or("margin-top", 0.0);
-        d.margin.bottom = style.num_or("margin-bottom", 0.0);
-        d.padding.top = style.num_or("padding-top", 0.0);
-        d.padding.bottom = style.num_or("padding-bottom", 0.0);
-        d.border.top = style.num_or("border-top-width", 0.0);
-        d.border.bottom = style.num_or("border-bottom-width", 0.0);
+        d.margin.top = get_absolute_num(&style.margin.top, dims).unwrap_or(0.0);
+        d.margin.bottom = get_absolute_num(&style.margin.bottom, dims).unwrap_or(0.0);
+        d.padding.top = get_absolute_num(&style.padding.top, dims).unwrap_or(0.0);
+        d.padding.bottom = get_absolute_num(&style.padding.bottom, dims).unwrap_or(0.0);
+        d.border.top = style.border_width.top;
+        d.border.bottom = style.border_width.bottom;
 
         d.coordinates.x = dims.coordinates.x + d.margin.left + d.padding.left + d.border.left;
         d.coordinates.y =
@@ -224,13 +217,11 @@ impl<'a> LayoutContainer<'a> {
             previous_box_type = single_child.boxtype.clone();
         }
     }
+    //A height that cannot be resolved is auto,the box keeps the height of its children
     fn calculate_height(&mut self) {
-        self.style_node
-            .get_value("height")
-            .map_or((), |h| match **h {
-                Value::Length(n, _) => self.dims.coordinates.height = n,
-                _ => {}
-            })
+        if let Some(height) = self.dims.definite_height {
+            self.dims.coordinates.height = height;
+        }
     }
 }
 impl<'a> fmt::Debug for LayoutContainer<'a> {
@@ -305,25 +296,44 @@ impl fmt::Debug for BoxType {
         write!(f, "Box Type:{:?}\n", box_type_string)
     }
 }
-//Get value of a property number
-fn get_absolute_num(style_node: &StyleNode, parent_dims: Dimensions, prop: &str) -> Option<f32> {
-    match style_node.get_value(prop) {
-        Some(ref v) => match ***v {
-            Value::Length(l, ref u) => match *u {
-                Unit::Px => Some(l),
-                Unit::Percent => Some(l * parent_dims.coordinates.width / 100.0),
-                _ => Some(0.0),
-            },
-            _ => None,
-        },
-        None => None,
+//Get value of a length,percentages and math functions resolve against the containing block
+fn get_absolute_num(length: &ComputedLength, parent_dims: Dimensions) -> Option<f32> {
+    //Every other unit has been resolved to px when the style was computed
+    let to_px = |l: f32, u: Unit| match u {
+        Unit::Percent => l * parent_dims.coordinates.width / 100.0,
+        _ => l,
+    };
+    match *length {
+        ComputedLength::Px(l) => Some(l),
+        ComputedLength::Percent(p) => Some(to_px(p, Unit::Percent)),
+        ComputedLength::Math(ref m) => Some(m.evaluate(&to_px)),
+        ComputedLength::Auto => None,
+    }
+}
+
+//Get value of a height.Percentages resolve against the height of the containing block,
+//when that height depends on the content they behave as auto
+fn get_definite_height(length: &ComputedLength, parent_dims: Dimensions) -> Option<f32> {
+    match (length, parent_dims.definite_height) {
+        (ComputedLength::Px(l), _) => Some(*l),
+        (ComputedLength::Percent(p), Some(height)) => Some(p * height / 100.0),
+        (ComputedLength::Math(m), Some(height)) => Some(m.evaluate(&|l, u| match u {
+            Unit::Percent => l * height / 100.0,
+            _ => l,
+        })),
+        (ComputedLength::Math(m), None) if m.math_type() == Some(MathType::Length) => {
+            Some(m.evaluate(&|l, _| l))
+        }
+        _ => None,
     }
 }
 
 pub fn get_layout_tree<'a>(
-    root: &'a StyleNode<'a>,
+    root: &'a StyleNode,
     mut viewport_dims: Dimensions,
 ) -> LayoutContainer<'a> {
+    //The viewport is the initial containing block,its height is always definite
+    viewport_dims.definite_height = Some(viewport_dims.coordinates.height);
     viewport_dims.coordinates.height = 0.0;
     let mut root_container = generate_layout_tree(root);
     root_container.layout(viewport_dims);
@@ -333,18 +343,93 @@ pub fn get_layout_tree<'a>(
 fn generate_layout_tree<'a>(node: &'a StyleNode) -> LayoutContainer<'a> {
     let mut layout_node = LayoutContainer::new(
         match node.get_display_value() {
-            Display::Block => BoxType::Block,
             Display::Inline => BoxType::Inline,
-            Display::InlineBlock => BoxType::InlineBlock,
-            Display::None => BoxType::Anonymous,
+            Display::None | Display::Contents => BoxType::Anonymous,
+            display if display.is_inline_level() => BoxType::InlineBlock,
+            _ => BoxType::Block,
         },
         node,
     );
+    push_children(&mut layout_node, node);
+    layout_node
+}
+
+//The children of a display: contents element take its place in the parent
+fn push_children<'a>(layout_node: &mut LayoutContainer<'a>, node: &'a StyleNode) {
     for child in &node.children {
         match child.get_display_value() {
             Display::None => {}
+            Display::Contents => push_children(layout_node, child),
             _ => layout_node.children_nodes.push(generate_layout_tree(child)),
         }
     }
-    layout_node
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cssparser::cssparser;
+    use crate::htmlparser::HtmlParser;
+
+    //Lays out html on an 800x600 viewport and returns the content box of the first child of
+    //the root element
+    fn first_child(html: &str, css: &str) -> Rectangle {
+        let document = HtmlParser::new(html).parse_nodes().remove(0);
+        let stylesheet = cssparser::new(&format!("*{{display:block}} {}", css)).parse_stylesheet();
+        let mut viewport = Dimensions::default();
+        viewport.coordinates.width = 800.0;
+        viewport.coordinates.height = 600.0;
+        let root = StyleNode::new(&document, &[&stylesheet], viewport);
+        let layout = get_layout_tree(&root, viewport);
+        layout.children_nodes[0].dims.coordinates
+    }
+
+    #[test]
+    fn calc_width_resolves_against_the_containing_block() {
+        let html = "<html><div></div></html>";
+        assert_eq!(
+            first_child(html, "div{width:calc(50% - 10px)}").width,
+            390.0
+        );
+        assert_eq!(first_child(html, "div{width:calc(2 * 100px)}").width, 200.0);
+        assert_eq!(first_child(html, "div{width:min(50%, 300px)}").width, 300.0);
+        assert_eq!(
+            first_child(html, "div{width:clamp(100px, 10%, 200px)}").width,
+            100.0
+        );
+    }
+
+    #[test]
+    fn percentage_height_of_a_definite_containing_block() {
+        let html = "<html><div></div></html>";
+        assert_eq!(
+            first_child(html, "html{height:200px} div{height:50%}").height,
+            100.0
+        );
+        assert_eq!(
+            first_child(html, "html{height:200px} div{height:calc(100% - 20px)}").height,
+            180.0
+        );
+        assert_eq!(
+            first_child(html, "html{height:50%} div{height:50%}").height,
+            150.0
+        );
+    }
+
+    #[test]
+    fn percentage_height_of_an_auto_containing_block_is_auto() {
+        let html = "<html><div><p></p></div></html>";
+        assert_eq!(
+            first_child(html, "div{height:50%} p{height:30px}").height,
+            30.0
+        );
+        assert_eq!(
+            first_child(html, "div{height:calc(50% + 10px)} p{height:30px}").height,
+            30.0
+        );
+        assert_eq!(
+            first_child(html, "div{height:calc(10px + 2 * 10px)} p{height:5px}").height,
+            30.0
+        );
+    }
 }
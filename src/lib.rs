@@ -9,7 +9,20 @@ pub mod screencommanddisplay;
 pub mod dom;
 pub mod htmlparser;
 pub mod csselements;
+pub mod cssanimations;
+pub mod csscolor;
+pub mod cssfonts;
+pub mod cssmath;
+pub mod cssmedia;
 pub mod cssparser;
+pub mod cssproperties;
+pub mod cssserializer;
+pub mod cssshorthands;
+pub mod csssupports;
+pub mod cssuseragent;
+pub mod cssvalues;
+pub mod cssvariables;
+pub mod resourceloader;
 pub mod styletree;
 pub mod layouttree;
 pub mod screenrender;
\ No newline at end of file
@@ -0,0 +1,419 @@
+//Loading of stylesheets from disk,including the sheets they pull in with @import
+//and the ones a document embeds or links
+use crate::csselements::{CssRule, FontSource, MediaRule, Stylesheet};
+use crate::cssmedia;
+use crate::cssparser::cssparser;
+use crate::dom::{ElementData, Node, NodeType};
+
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+//Imports nested deeper than this are dropped
+pub const MAX_IMPORT_DEPTH: usize = 16;
+
+const DEFAULT_NAMESPACE_PREFIX: &str = "ns";
+
+pub trait ResourceLoader {
+    //Resolves url against the location of the resource that references it
+    fn resolve(&self, base: &str, url: &str) -> String;
+    fn load(&self, location: &str) -> Option<String>;
+    //Binary resources such as fonts
+    fn load_bytes(&self, location: &str) -> Option<Vec<u8>>;
+}
+
+//Loads resources from the local file system,relative urls are taken from the importing file's directory
+pub struct FileLoader;
+
+impl ResourceLoader for FileLoader {
+    fn resolve(&self, base: &str, url: &str) -> String {
+        let url = url.trim_start_matches("file://");
+        let path = match Path::new(base).parent() {
+            Some(directory) if !Path::new(url).is_absolute() => directory.join(url),
+            _ => PathBuf::from(url),
+        };
+
+        //`..` is folded away so that the same file always resolves to the same location
+        let mut normalized = PathBuf::new();
+        for component in path.components() {
+            match component {
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    if !normalized.pop() {
+                        normalized.push("..");
+                    }
+                }
+                other => normalized.push(other.as_os_str()),
+            }
+        }
+        normalized.to_string_lossy().into_owned()
+    }
+
+    fn load(&self, location: &str) -> Option<String> {
+        fs::read_to_string(location).ok()
+    }
+
+    fn load_bytes(&self, location: &str) -> Option<Vec<u8>> {
+        fs::read(location).ok()
+    }
+}
+
+//Parses the stylesheet at location,None if it could not be loaded
+pub fn load_stylesheet(loader: &dyn ResourceLoader, location: &str) -> Option<Stylesheet> {
+    let source = loader.load(location)?;
+    let mut stylesheet = cssparser::new(&source).parse_stylesheet();
+    resolve_imports(loader, location, &mut stylesheet);
+    Some(stylesheet)
+}
+
+//The author stylesheets of a document loaded from location,in tree order:
+//<style> elements and <link rel="stylesheet" href> resolved against the document.
+//Sheets that can not be loaded are left out.
+pub fn document_stylesheets(
+    loader: &dyn ResourceLoader,
+    location: &str,
+    document: &Node,
+) -> Vec<Stylesheet> {
+    let mut stylesheets = Vec::new();
+    collect_stylesheets(loader, location, document, &mut stylesheets);
+    stylesheets
+}
+
+fn collect_stylesheets(
+    loader: &dyn ResourceLoader,
+    location: &str,
+    node: &Node,
+    stylesheets: &mut Vec<Stylesheet>,
+) {
+    if let NodeType::Element(ref element) = node.node_type {
+        let stylesheet = if element.tag_name.eq_ignore_ascii_case("style") {
+            style_element_stylesheet(loader, location, node, element)
+        } else if element.tag_name.eq_ignore_ascii_case("link") {
+            link_element_stylesheet(loader, location, element)
+        } else {
+            None
+        };
+        if let Some(mut stylesheet) = stylesheet {
+            apply_media_attribute(element, &mut stylesheet);
+            stylesheets.push(stylesheet);
+        }
+    }
+    for child in &node.children {
+        collect_stylesheets(loader, location, child, stylesheets);
+    }
+}
+
+fn style_element_stylesheet(
+    loader: &dyn ResourceLoader,
+    location: &str,
+    node: &Node,
+    element: &ElementData,
+) -> Option<Stylesheet> {
+    if !is_css_type(element) {
+        return None;
+    }
+    let source = node
+        .children
+        .iter()
+        .filter_map(|child| match child.node_type {
+            NodeType::Text(ref t) => Some(t.as_str()),
+            _ => None,
+        })
+        .collect::<String>();
+    let mut stylesheet = cssparser::new(&source).parse_stylesheet();
+    resolve_imports(loader, location, &mut stylesheet);
+    Some(stylesheet)
+}
+
+//rel is a list of keywords,alternate stylesheets are only used when picked by the user
+fn link_element_stylesheet(
+    loader: &dyn ResourceLoader,
+    location: &str,
+    element: &ElementData,
+) -> Option<Stylesheet> {
+    let rel = element.attributes.get("rel")?.to_lowercase();
+    let keywords = rel.split_whitespace().collect::<Vec<&str>>();
+    if !keywords.contains(&"stylesheet") || keywords.contains(&"alternate") {
+        return None;
+    }
+    if !is_css_type(element) {
+        return None;
+    }
+    let href = element.attributes.get("href")?.trim();
+    if href.is_empty() {
+        return None;
+    }
+    load_stylesheet(loader, &loader.resolve(location, href))
+}
+
+fn is_css_type(element: &ElementData) -> bool {
+    element.attributes.get("type").map_or(true, |t| {
+        let t = t.trim();
+        t.is_empty() || t.eq_ignore_ascii_case("text/css")
+    })
+}
+
+//The sheet of an element with a media attribute only applies when the queries match
+fn apply_media_attribute(element: &ElementData, stylesheet: &mut Stylesheet) {
+    let media = match element.attributes.get("media") {
+        Some(m) if !m.trim().is_empty() => m,
+        _ => return,
+    };
+    let rules = std::mem::replace(&mut stylesheet.css, Vec::new());
+    stylesheet.css = vec![CssRule::Media(MediaRule {
+        queries: cssmedia::parse_media_query_list(media),
+        rules,
+    })];
+}
+
+//Replaces the @import rules of a sheet loaded from base with the imported rules,in order
+pub fn resolve_imports(loader: &dyn ResourceLoader, base: &str, stylesheet: &mut Stylesheet) {
+    let rules = std::mem::replace(&mut stylesheet.css, Vec::new());
+    stylesheet.css = inline_imports(loader, base, rules, &mut vec![base.to_string()], stylesheet);
+}
+
+//loading holds the chain of sheets being imported,a url already on it is a cycle and is skipped.
+//The namespaces of the imported sheets are added to those of target.
+fn inline_imports(
+    loader: &dyn ResourceLoader,
+    base: &str,
+    rules: Vec<CssRule>,
+    loading: &mut Vec<String>,
+    target: &mut Stylesheet,
+) -> Vec<CssRule> {
+    let mut result = Vec::new();
+
+    for rule in rules {
+        let import = match rule {
+            CssRule::Import(i) => i,
+            mut other => {
+                resolve_font_sources(loader, base, &mut other);
+                result.push(other);
+                continue;
+            }
+        };
+        let location = loader.resolve(base, &import.url);
+        if loading.len() > MAX_IMPORT_DEPTH || loading.contains(&location) {
+            continue;
+        }
+        let source = match loader.load(&location) {
+            Some(s) => s,
+            None => continue,
+        };
+
+        let imported = cssparser::new(&source).parse_stylesheet();
+        adopt_namespaces(target, &imported);
+        loading.push(location.clone());
+        let mut imported = inline_imports(loader, &location, imported.css, loading, target);
+        loading.pop();
+
+        if import.queries.is_empty() {
+            result.append(&mut imported);
+        } else {
+            result.push(CssRule::Media(MediaRule {
+                queries: import.queries,
+                rules: imported,
+            }));
+        }
+    }
+    result
+}
+
+//Selectors already hold the namespace urls they were parsed with,the prefixes of an imported
+//sheet are kept so that the combined sheet can still name those urls when it is serialized.
+//A prefix that target uses for another url gets a numeric suffix,the default namespace of
+//the imported sheet is given the prefix ns.
+fn adopt_namespaces(target: &mut Stylesheet, imported: &Stylesheet) {
+    let mut namespaces = imported
+        .namespaces
+        .iter()
+        .map(|(prefix, url)| (prefix.as_str(), url))
+        .collect::<Vec<_>>();
+    namespaces.sort();
+    let default = imported
+        .default_namespace
+        .as_ref()
+        .filter(|url| Some(*url) != target.default_namespace.as_ref())
+        .map(|url| (DEFAULT_NAMESPACE_PREFIX, url));
+
+    for (prefix, url) in default.into_iter().chain(namespaces) {
+        if target.namespaces.values().any(|u| u == url) {
+            continue;
+        }
+        let mut name = prefix.to_string();
+        let mut suffix = 1;
+        while target.namespaces.contains_key(&name) {
+            name = format!("{}{}", prefix, suffix);
+            suffix += 1;
+        }
+        target.namespaces.insert(name, url.clone());
+    }
+}
+
+//@font-face urls are relative to the sheet they are written in,which is lost once imports are inlined
+fn resolve_font_sources(loader: &dyn ResourceLoader, base: &str, rule: &mut CssRule) {
+    match *rule {
+        CssRule::FontFace(ref mut face) => {
+            for source in face.sources.iter_mut() {
+                if let FontSource::Url { ref mut url, .. } = *source {
+                    *url = loader.resolve(base, url);
+                }
+            }
+        }
+        CssRule::Media(ref mut media) => {
+            for rule in media.rules.iter_mut() {
+                resolve_font_sources(loader, base, rule);
+            }
+        }
+        CssRule::Supports(ref mut supports) => {
+            for rule in supports.rules.iter_mut() {
+                resolve_font_sources(loader, base, rule);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::csselements::{Device, Rule, Unit, Value};
+    use crate::cssserializer::serialize_stylesheet;
+
+    use std::collections::HashMap;
+
+    //Sheets kept in memory under their name,urls are not resolved against a directory
+    struct MemoryLoader(HashMap<String, String>);
+
+    impl ResourceLoader for MemoryLoader {
+        fn resolve(&self, _base: &str, url: &str) -> String {
+            url.to_string()
+        }
+
+        fn load(&self, location: &str) -> Option<String> {
+            self.0.get(location).map(|s| s.to_string())
+        }
+
+        fn load_bytes(&self, _location: &str) -> Option<Vec<u8>> {
+            None
+        }
+    }
+
+    fn load(sheets: &[(&str, &str)]) -> Stylesheet {
+        let loader = MemoryLoader(
+            sheets
+                .iter()
+                .map(|&(name, css)| (name.to_string(), css.to_string()))
+                .collect(),
+        );
+        load_stylesheet(&loader, sheets[0].0).unwrap()
+    }
+
+    fn rules(stylesheet: &Stylesheet) -> Vec<&Rule> {
+        stylesheet.rules_for_device(&Device::new(800.0, 600.0))
+    }
+
+    #[test]
+    fn imported_selectors_keep_their_namespace() {
+        let stylesheet = load(&[
+            (
+                "main.css",
+                "@import url(svg.css); @namespace svg url(main); svg|a { width: 1px }",
+            ),
+            (
+                "svg.css",
+                "@namespace url(default); @namespace svg url(svg); svg|rect, circle { width: 2px }",
+            ),
+        ]);
+        let namespaces = rules(&stylesheet)
+            .iter()
+            .flat_map(|rule| &rule.selectors)
+            .map(|selector| selector.simple[0].namespace.clone().unwrap())
+            .collect::<Vec<String>>();
+        assert_eq!(namespaces, vec!["svg", "default", "main"]);
+
+        assert_eq!(stylesheet.namespaces["svg"], "main");
+        assert_eq!(stylesheet.namespaces["svg1"], "svg");
+        assert_eq!(stylesheet.namespaces["ns"], "default");
+        let css = serialize_stylesheet(&stylesheet);
+        assert!(css.contains("svg1|rect, ns|circle"), "{}", css);
+    }
+
+    //The width each rule for an 800x600 screen sets,in order
+    fn widths(stylesheet: &Stylesheet) -> Vec<f32> {
+        rules(stylesheet)
+            .iter()
+            .map(|rule| match rule.declarations[0].value {
+                Value::Length(n, Unit::Px) => n,
+                _ => panic!("not a width"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn imported_rules_come_before_the_importing_sheet() {
+        let stylesheet = load(&[
+            (
+                "main.css",
+                "@import url(a.css); @import 'b.css'; p { width: 3px }",
+            ),
+            ("a.css", "p { width: 1px }"),
+            ("b.css", "p { width: 2px }"),
+        ]);
+        assert_eq!(widths(&stylesheet), [1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn import_media_queries_scope_the_imported_rules() {
+        let stylesheet = load(&[
+            (
+                "main.css",
+                "@import url(print.css) print; @import url(wide.css) (min-width: 500px); p { width: 3px }",
+            ),
+            ("print.css", "p { width: 1px }"),
+            ("wide.css", "p { width: 2px }"),
+        ]);
+        assert_eq!(widths(&stylesheet), [2.0, 3.0]);
+    }
+
+    #[test]
+    fn import_cycles_are_skipped() {
+        let stylesheet = load(&[
+            ("a.css", "@import url(b.css); p { width: 1px }"),
+            (
+                "b.css",
+                "@import url(a.css); @import url(b.css); p { width: 2px }",
+            ),
+        ]);
+        assert_eq!(widths(&stylesheet), [2.0, 1.0]);
+
+        //The same sheet may still be imported twice when that is not a cycle
+        let stylesheet = load(&[
+            ("main.css", "@import url(c.css); @import url(c.css);"),
+            ("c.css", "p { width: 1px }"),
+        ]);
+        assert_eq!(widths(&stylesheet), [1.0, 1.0]);
+    }
+
+    #[test]
+    fn imports_past_the_depth_limit_are_dropped() {
+        let sheets = (0..MAX_IMPORT_DEPTH + 4)
+            .map(|i| {
+                (
+                    format!("{}.css", i),
+                    format!("@import url({}.css); p {{ width: {}px }}", i + 1, i),
+                )
+            })
+            .collect::<Vec<_>>();
+        let sheets = sheets
+            .iter()
+            .map(|(name, css)| (name.as_str(), css.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(widths(&load(&sheets)).len(), MAX_IMPORT_DEPTH + 1);
+    }
+
+    #[test]
+    fn missing_imports_are_ignored() {
+        let stylesheet = load(&[("main.css", "@import url(missing.css); p { width: 1px }")]);
+        assert_eq!(widths(&stylesheet), [1.0]);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::csselements::{Color, Value};
+use crate::csselements::Color;
 use crate::layouttree::{LayoutContainer, Rectangle};
 use std::fmt;
 
@@ -26,7 +26,7 @@ fn render_layout_container(commands: &mut DisplayCommandList, root: &LayoutConta
 }
 
 fn render_background(commands: &mut DisplayCommandList, root: &LayoutContainer) {
-    get_background_color(root, "background-color").map(|color| {
+    visible(&root.style_node.style.background_color).map(|color| {
         commands.push(DisplayCommand::SolidRect(
             color,
             root.dims.get_border_dimensions(),
@@ -34,65 +34,63 @@ fn render_background(commands: &mut DisplayCommandList, root: &LayoutContainer)
     });
 }
 
-fn get_background_color(layout_container: &LayoutContainer, name: &str) -> Option<Color> {
-    println!(
-        "Name in func : {} \n and value : {:?}",
-        name,
-        layout_container.style_node.get_value(name)
-    );
-    match layout_container.style_node.get_value(name) {
-        Some(v) => match **v {
-            Value::Color(ref c) => return Some(c.clone()),
-            _ => return None,
-        },
-        None => return None,
+//Fully transparent colors are skipped as they would draw nothing
+fn visible(color: &Color) -> Option<Color> {
+    if color.a > 0.0 {
+        Some(color.clone())
+    } else {
+        None
     }
 }
 
 fn render_borders(commands: &mut DisplayCommandList, root: &LayoutContainer) {
-    let color = match get_background_color(root, "border-color") {
-        Some(color) => color,
-        _ => return,
-    };
     let root_dims = &root.dims;
     let root_border_dims = root_dims.get_border_dimensions();
 
-    commands.push(DisplayCommand::SolidRect(
-        color.clone(),
-        Rectangle {
-            x: root_border_dims.x,
-            y: root_border_dims.y,
-            width: root_dims.border.left,
-            height: root_border_dims.height,
-        },
-    ));
-    commands.push(DisplayCommand::SolidRect(
-        color.clone(),
-        Rectangle {
-            x: root_border_dims.x + root_border_dims.width - root_dims.border.right,
-            y: root_border_dims.y,
-            width: root_dims.border.left,
-            height: root_border_dims.height,
-        },
-    ));
-    commands.push(DisplayCommand::SolidRect(
-        color.clone(),
-        Rectangle {
-            x: root_border_dims.x,
-            y: root_border_dims.y,
-            width: root_border_dims.width,
-            height: root_dims.border.top,
-        },
-    ));
-    commands.push(DisplayCommand::SolidRect(
-        color.clone(),
-        Rectangle {
-            x: root_border_dims.x,
-            y: root_border_dims.y + root_border_dims.height - root_dims.border.bottom,
-            width: root_border_dims.width,
-            height: root_dims.border.bottom,
-        },
-    ));
+    if let Some(color) = visible(&root.style_node.style.border_color.left) {
+        commands.push(DisplayCommand::SolidRect(
+            color,
+            Rectangle {
+                x: root_border_dims.x,
+                y: root_border_dims.y,
+                width: root_dims.border.left,
+                height: root_border_dims.height,
+            },
+        ));
+    }
+    if let Some(color) = visible(&root.style_node.style.border_color.right) {
+        commands.push(DisplayCommand::SolidRect(
+            color,
+            Rectangle {
+                x: root_border_dims.x + root_border_dims.width - root_dims.border.right,
+                y: root_border_dims.y,
+                width: root_dims.border.right,
+                height: root_border_dims.height,
+            },
+        ));
+    }
+    if let Some(color) = visible(&root.style_node.style.border_color.top) {
+        commands.push(DisplayCommand::SolidRect(
+            color,
+            Rectangle {
+                x: root_border_dims.x,
+                y: root_border_dims.y,
+                width: root_border_dims.width,
+                height: root_dims.border.top,
+            },
+        ));
+    }
+    if let Some(color) = visible(&root.style_node.style.border_color.bottom) {
+        commands.push(DisplayCommand::SolidRect(
+            color,
+            Rectangle {
+                x: root_border_dims.x,
+                y: root_border_dims.y + root_border_dims.height - root_dims.border.bottom,
+                width: root_border_dims.width,
+                height: root_dims.border.bottom,
+            },
+        ));
+    }
 }
 impl fmt::Debug for DisplayCommand {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
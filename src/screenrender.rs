@@ -5,9 +5,11 @@ use glutin;
 
 use gfx::traits::FactoryExt;
 use gfx::Device;
+use std::rc::Rc;
 
+use crate::cssanimations::Timeline;
 use crate::layouttree;
-use crate::screencommanddisplay::DisplayCommand;
+use crate::screencommanddisplay::{DisplayCommand, DisplayCommandList};
 
 pub type DepthFormat = gfx::format::DepthStencil;
 pub type ColorFormat = gfx::format::Rgba8;
@@ -39,7 +41,7 @@ fn render_texts(command_list: &[DisplayCommand]) -> Vec<RenderText> {
     Vec::new()
 }
 
-fn render_commands(command_list: &[DisplayCommand]) -> (Vec<Vertex>, Vec<u16>) {
+fn render_commands(command_list: &[DisplayCommand], screen: (f32, f32)) -> (Vec<Vertex>, Vec<u16>) {
     let mut vertices = Vec::new();
     let mut index_data = Vec::new();
     let mut rect_num: u16 = 0;
@@ -49,7 +51,7 @@ fn render_commands(command_list: &[DisplayCommand]) -> (Vec<Vertex>, Vec<u16>) {
             DisplayCommand::SolidRect(ref color, ref rect) => {
                 let c = [color.r, color.g, color.b];
 
-                let mut v = render_rectangle(&c, rect);
+                let mut v = render_rectangle(&c, rect, screen);
                 vertices.append(&mut v);
 
                 let index_base: u16 = rect_num * 4;
@@ -68,8 +70,8 @@ fn render_commands(command_list: &[DisplayCommand]) -> (Vec<Vertex>, Vec<u16>) {
     return (vertices, index_data);
 }
 
-fn render_rectangle(c: &[f32; 3], rect: &layouttree::Rectangle) -> Vec<Vertex> {
-    let (x, y, h, w) = transform_rectangle(rect);
+fn render_rectangle(c: &[f32; 3], rect: &layouttree::Rectangle, screen: (f32, f32)) -> Vec<Vertex> {
+    let (x, y, h, w) = transform_rectangle(rect, screen);
     let vertices = vec![
         Vertex {
             pos: [x + w, y],
@@ -92,22 +94,34 @@ fn render_rectangle(c: &[f32; 3], rect: &layouttree::Rectangle) -> Vec<Vertex> {
     vertices
 }
 
-fn transform_rectangle(rect: &layouttree::Rectangle) -> (f32, f32, f32, f32) {
-    let w = rect.width / SCREEN_WIDTH as f32 * 2.0;
-    let h = rect.height / SCREEN_HEIGHT as f32 * 2.0;
-    let x = rect.x / SCREEN_WIDTH as f32 * 2.0 - 1.0;
-    let y = -(rect.y / SCREEN_HEIGHT as f32 * 2.0 - 1.0 + h);
+fn transform_rectangle(rect: &layouttree::Rectangle, screen: (f32, f32)) -> (f32, f32, f32, f32) {
+    let (screen_width, screen_height) = screen;
+    let w = rect.width / screen_width * 2.0;
+    let h = rect.height / screen_height * 2.0;
+    let x = rect.x / screen_width * 2.0 - 1.0;
+    let y = -(rect.y / screen_height * 2.0 - 1.0 + h);
 
     (x, y, h, w)
 }
 
-pub fn render_loop(command_list: &[DisplayCommand]) {
+//relayout is called with the new window size whenever it changes,so the page can be restyled,
+//and on every frame while the timeline has animations running.It also returns the font of the
+//restyled page,the text renderer is rebuilt when that is a different one.
+//font is the data of a TrueType/OpenType font for the text,gfx_text's own font is used without one.
+pub fn render_loop<F>(
+    command_list: &[DisplayCommand],
+    font: Option<Rc<Vec<u8>>>,
+    timeline: &mut Timeline,
+    mut relayout: F,
+) where
+    F: FnMut(f32, f32, &mut Timeline) -> (DisplayCommandList, Option<Rc<Vec<u8>>>),
+{
     let builder = glutin::WindowBuilder::new()
         .with_title(String::from("Browser"))
         .with_dimensions(SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32)
         .with_vsync();
 
-    let (window, mut device, mut factory, main_color, _main_depth) =
+    let (window, mut device, mut factory, main_color, mut main_depth) =
         gfx_window_glutin::init::<ColorFormat, DepthFormat>(builder);
 
     let mut encoder: gfx::Encoder<_, _> = factory.create_command_buffer().into();
@@ -120,29 +134,71 @@ pub fn render_loop(command_list: &[DisplayCommand]) {
         )
         .unwrap();
 
-    let (vertices, index_data) = render_commands(command_list);
+    let mut screen = (SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32);
+    let (vertices, index_data) = render_commands(command_list, screen);
     println!("Command List: {:?}", command_list);
     let texts = render_texts(command_list);
 
-    let (vertex_buffer, slice) =
+    let (vertex_buffer, mut slice) =
         factory.create_vertex_buffer_with_slice(&vertices, &index_data[..]);
 
-    let data = pipe::Data {
+    let mut data = pipe::Data {
         vbuf: vertex_buffer,
         out: main_color,
     };
 
-    let mut test_renderer = gfx_text::new(factory).build().unwrap();
+    let mut font = font;
+    let mut text_renderer = None;
 
+    //One more frame is laid out after the animations end,so their final values are drawn
+    let mut animating = true;
     'main: loop {
+        timeline.tick();
+        let running = timeline.is_running();
+        let mut needs_layout = running || animating;
+        animating = running;
+
         for event in window.poll_events() {
             match event {
                 glutin::Event::KeyboardInput(_, _, Some(glutin::VirtualKeyCode::Escape))
                 | glutin::Event::Closed => break 'main,
+                glutin::Event::Resized(width, height) => {
+                    gfx_window_glutin::update_views(&window, &mut data.out, &mut main_depth);
+                    screen = (width as f32, height as f32);
+                    needs_layout = true;
+                }
                 _ => {}
             }
         }
 
+        if needs_layout {
+            let (commands, new_font) = relayout(screen.0, screen.1, timeline);
+            let same_font = match (&font, &new_font) {
+                (Some(a), Some(b)) => Rc::ptr_eq(a, b),
+                (None, None) => true,
+                _ => false,
+            };
+            if !same_font {
+                font = new_font;
+                text_renderer = None;
+            }
+            let (vertices, index_data) = render_commands(&commands, screen);
+            let (vertex_buffer, new_slice) =
+                factory.create_vertex_buffer_with_slice(&vertices, &index_data[..]);
+            data.vbuf = vertex_buffer;
+            slice = new_slice;
+        }
+
+        let test_renderer = text_renderer.get_or_insert_with(|| match font {
+            Some(ref data) => match gfx_text::new(factory.clone()).with_font_data(data).build() {
+                Ok(renderer) => renderer,
+                Err(e) => {
+                    println!("Font could not be used,error:{:?}", e);
+                    gfx_text::new(factory.clone()).build().unwrap()
+                }
+            },
+            None => gfx_text::new(factory.clone()).build().unwrap(),
+        });
         for text in &texts {
             test_renderer.add(text.text, text.position, text.color);
         }
@@ -1,86 +1,373 @@
 use std::collections::HashMap;
 use std::fmt;
+use std::mem;
+use std::sync::Arc;
 
-use crate::dom::{ElementData,Node,NodeType};
-use crate::csselements::{Selector,Stylesheet,Value};
+use crate::dom::{Node,NodeType};
+use crate::csselements::{Color,Declaration,Device,Display,MathExpression,Origin,PseudoClass,RelativeSelector,Rule,Selector,SimpleSelector,Specificity,Stylesheet,Unit,Value,VarValue};
+use crate::cssfonts::FontMetrics;
+use crate::cssparser::{parse_declaration_list,parse_property_value};
+use crate::cssproperties::{initial_value,property_id,PropertyId,CSS_WIDE_KEYWORDS,PROPERTIES};
+use crate::cssvalues::fold_case;
+use crate::cssvariables::{compute_custom_properties,substitute_vars,CustomProperties};
+use crate::layouttree::Dimensions;
 
-type StyleMap<'a>=HashMap<& 'a str, & 'a Value>;
-pub struct StyleNode<'a>{
-     node: & 'a Node,
-     styles:StyleMap<'a>,
-     pub children:Vec<StyleNode<'a>>,
+type SpecifiedMap<'a>=HashMap<& 'a str, & 'a Value>;
+//The style tree owns its values,so the document and the stylesheets can change while it exists
+pub struct StyleNode{
+     //Position of the element in a preorder walk of the document,text and comments included
+     pub node_id:usize,
+     pub style:Arc<ComputedStyle>,
+     lengths:LengthContext,
+     pub children:Vec<StyleNode>,
 }
-pub enum Display{
-    Block,InlineBlock,Inline,None
+//Computed value of every registered property,indexed by PropertyId,with typed copies of
+//those layout and painting read.
+//Elements with the same style can share one,set_value copies it before writing.
+#[derive(Clone,PartialEq)]
+pub struct ComputedStyle{
+    pub display:Display,
+    pub width:ComputedLength,
+    pub height:ComputedLength,
+    pub margin:Sides<ComputedLength>,
+    pub padding:Sides<ComputedLength>,
+    //In px,sides without a border style have none
+    pub border_width:Sides<f32>,
+    //currentcolor is resolved to color
+    pub color:Color,
+    pub background_color:Color,
+    pub border_color:Sides<Color>,
+    //In px
+    pub font_size:f32,
+    values:Vec<Value>,
+    custom_properties:Arc<CustomProperties>,
 }
-impl<'a>  StyleNode<'a>{
-     pub fn new(node:& 'a Node, stylesheet:& 'a Stylesheet)->StyleNode<'a>{
+//Computed <length-percentage> or auto,layout resolves the percentages
+#[derive(Clone,PartialEq,Debug)]
+pub enum ComputedLength{
+    Px(f32),
+    Percent(f32),
+    //Math function with a percentage in it
+    Math(MathExpression),
+    Auto,
+}
+#[derive(Clone,PartialEq,Debug)]
+pub struct Sides<T>{
+    pub top:T,
+    pub right:T,
+    pub bottom:T,
+    pub left:T,
+}
+//Metrics of the font a computed style selects,None when it has no font file
+pub type FontMetricsSource<'a>=dyn Fn(&ComputedStyle)->Option<FontMetrics>+'a;
+//What the relative lengths of an element are resolved against,in px.
+//ex and ch in font-size refer to the parent's font,elsewhere to the element's own.
+#[derive(Clone,Copy)]
+struct LengthContext{
+    parent_font_size:f32,
+    parent_font:Option<FontMetrics>,
+    font:Option<FontMetrics>,
+    root_font_size:f32,
+    viewport:(f32,f32),
+}
+//The rules of the stylesheets that apply to the device,in cascade order
+struct Cascade<'a>{
+    rules:Vec<(Origin,&'a Rule)>,
+    //Indices of the rules with a selector that looks at siblings or descendants
+    structural:Vec<usize>,
+    fonts:&'a FontMetricsSource<'a>,
+}
+//Everything the rules matched by an element depend on besides its ancestors.Selectors only
+//look at the namespace,tag name,id and classes of an element,the rules that also look at
+//its siblings or descendants are matched for the key itself.
+#[derive(PartialEq,Eq,Hash)]
+struct SharingKey<'a>{
+    namespace:&'a str,
+    tag_name:&'a str,
+    id:Option<&'a str>,
+    classes:Vec<&'a str>,
+    structural:Vec<usize>,
+}
+//Sort key of a matched declaration,fields are compared in cascade order
+#[derive(PartialEq,Eq,PartialOrd,Ord)]
+struct CascadeKey{
+    precedence:u8,
+    //Declarations of the style attribute win over any selector
+    inline:bool,
+    specificity:Specificity,
+    order:(usize,usize),
+}
+impl ComputedStyle{
+    //values holds a value for every registered property,in registry order
+    fn new(values:Vec<Value>,custom_properties:Arc<CustomProperties>)->ComputedStyle{
+        let value=|name:&str| &values[id(name).index()];
+        let color=match *value("color"){
+            Value::Color(ref c)=>c.clone(),
+            _=>Color::new(0.0,0.0,0.0,1.0),
+        };
+        let to_color=|name:&str| match *value(name){
+            Value::Color(ref c)=>c.clone(),
+            Value::Keyword("currentcolor")=>color.clone(),
+            _=>Color::new(0.0,0.0,0.0,0.0),
+        };
+        let to_px=|name:&str| match *value(name){
+            Value::Length(n,Unit::Px)=>n,
+            _=>0.0,
+        };
+        let to_length=|name:&str| match *value(name){
+            Value::Length(n,Unit::Px)=>ComputedLength::Px(n),
+            Value::Length(n,Unit::Percent)=>ComputedLength::Percent(n),
+            Value::Math(ref m)=>ComputedLength::Math(m.clone()),
+            Value::Keyword("auto")=>ComputedLength::Auto,
+            _=>ComputedLength::Px(0.0),
+        };
+
+        let display=match *value("display"){
+            Value::Display(d)=>d,
+            _=>Display::Inline
+        };
+        let width=to_length("width");
+        let height=to_length("height");
+        let margin=Sides::from_names(MARGINS,to_length);
+        let padding=Sides::from_names(PADDINGS,to_length);
+        let border_width=Sides::from_names(BORDER_WIDTHS,to_px);
+        let background_color=to_color("background-color");
+        let border_color=Sides::from_names(BORDER_COLORS,to_color);
+        let font_size=match *value("font-size"){
+            Value::Length(n,Unit::Px)=>n,
+            _=>DEFAULT_FONT_SIZE,
+        };
+        ComputedStyle{display,width,height,margin,padding,border_width,color,background_color,border_color,font_size,values,custom_properties}
+    }
+    //The typed fields are rebuilt from the values
+    fn set(&mut self,id:PropertyId,value:Value){
+        self.values[id.index()]=value;
+        let values=mem::take(&mut self.values);
+        *self=ComputedStyle::new(values,self.custom_properties.clone());
+    }
+    pub fn get(&self,id:PropertyId)->&Value{
+        &self.values[id.index()]
+    }
+    pub fn get_by_name(&self,name:&str)->Option<&Value>{
+        property_id(name).map(|id| self.get(id))
+    }
+    pub fn custom_property(&self,name:&str)->Option<&String>{
+        self.custom_properties.get(name)
+    }
+}
+impl<T> Sides<T>{
+    //names are in top,right,bottom,left order
+    fn from_names<F>(names:[&str;4],f:F)->Sides<T> where F:Fn(&str)->T{
+        Sides{top:f(names[0]),right:f(names[1]),bottom:f(names[2]),left:f(names[3])}
+    }
+}
+impl StyleNode{
+     //@media rules are evaluated against viewport,so the tree has to be rebuilt when it changes.
+     //Sheets of the same origin cascade in the order they are given.
+     pub fn new(node:&Node, stylesheets:&[&Stylesheet],viewport:Dimensions)->StyleNode{
+        let device=Device::new(viewport.coordinates.width,viewport.coordinates.height);
+        StyleNode::with_fonts(node,stylesheets,&device,&|_| None)
+     }
+     //device is the viewport together with the user's preferences,such as the color scheme.
+     //ex and ch are resolved with the metrics fonts gives for an element's style,
+     //without them they are half an em
+     pub fn with_fonts(node:&Node, stylesheets:&[&Stylesheet],device:&Device,fonts:&FontMetricsSource)->StyleNode{
+        let rules=stylesheets.iter()
+            .flat_map(|sheet| sheet.rules_for_device(device).into_iter().map(move |rule| (sheet.origin,rule)))
+            .collect::<Vec<(Origin,&Rule)>>();
+        //The root element's rem and em refer to the initial font size
+        let lengths=LengthContext{
+            parent_font_size:DEFAULT_FONT_SIZE,
+            parent_font:None,
+            font:None,
+            root_font_size:DEFAULT_FONT_SIZE,
+            viewport:(device.width,device.height),
+        };
+        let structural=rules.iter().enumerate()
+            .filter(|&(_,&(_,rule))| rule.selectors.iter().any(is_structural))
+            .map(|(i,_)| i)
+            .collect();
+        let cascade=Cascade{rules,structural,fonts};
+        StyleNode::build(node,&mut 0,&cascade,&mut Vec::new(),None,lengths,None)
+    }
+    //shared is the style of a sibling with the same sharing key,it is used instead of cascading again.
+    //next_id is the preorder position of node,it is advanced past the subtree.
+    fn build<'a>(node:& 'a Node,next_id:&mut usize, cascade:&Cascade<'a>,ancestors:&mut Vec<&'a Node>,parent:Option<&ComputedStyle>,lengths:LengthContext,shared:Option<Arc<ComputedStyle>>)->StyleNode{
+        let (style,font)=match shared{
+            Some(style)=>{
+                let font=(cascade.fonts)(&style);
+                (style,font)
+            },
+            None=>{
+                let inline=inline_style(node);
+                let specified=match node.node_type{
+                    NodeType::Element(_)=>StyleNode::get_styles(node,ancestors,&cascade.rules,&inline),
+                    _=>SpecifiedMap::new()
+                };
+                let (style,font)=StyleNode::compute_styles(specified,parent,&lengths,cascade.fonts);
+                (Arc::new(style),font)
+            }
+        };
+        let lengths=LengthContext{font,..lengths};
+        let font_size=style.font_size;
+        let child_lengths=LengthContext{
+            parent_font_size:font_size,
+            parent_font:font,
+            root_font_size:if parent.is_none(){font_size}else{lengths.root_font_size},
+            ..lengths
+        };
         let mut style_children=Vec::new();
+        //Siblings have the same parent style,so the same key gives the same computed style
+        let mut sharing=HashMap::<SharingKey,Arc<ComputedStyle>>::new();
 
+        let node_id=*next_id;
+        *next_id+=1;
+        ancestors.push(node);
         for child in &node.children{
             match child.node_type{
-                NodeType::Element(_)=>style_children.push(StyleNode::new(&child, stylesheet)),
-                _=>{}
+                NodeType::Element(_)=>{
+                    let key=sharing_key(child,ancestors,cascade);
+                    let shared=key.as_ref().and_then(|k| sharing.get(k).cloned());
+                    let style_child=StyleNode::build(child,next_id,cascade,ancestors,Some(&style),child_lengths,shared);
+                    if let Some(k)=key{
+                        sharing.entry(k).or_insert_with(|| style_child.style.clone());
+                    }
+                    style_children.push(style_child);
+                },
+                //Text and comments have no children
+                _=>*next_id+=1
             }
-
         }
+        ancestors.pop();
 
-        StyleNode{node,styles:match node.node_type{
-            NodeType::Element(ref e)=>StyleNode::get_styles(e,stylesheet),
-            _=>StyleMap::new()
-        },
+        StyleNode{node_id,style,lengths,
         children:style_children    
     }
 
     }
-    fn get_styles(e:& 'a ElementData,stylesheet:&'a Stylesheet)->StyleMap<'a>{
-        let mut styles=StyleMap::new();
-         for single_rule in &stylesheet.css{
-             for selector in &single_rule.selectors{
-                 if selector_matches(e,&selector){
-                     for declarations in &single_rule.declarations{
-                         styles.insert(&declarations.key,&declarations.value);
-                     }
-                     break;
-                 }
+    //Cascades every matching declaration,later entries in the sorted list win.
+    //revert discards the rest of its origin,so a lower origin's declaration wins instead.
+    fn get_styles<'a>(node:& 'a Node,ancestors:&[&'a Node],rules:&[(Origin,&'a Rule)],inline:&'a [Declaration])->SpecifiedMap<'a>{
+        let mut matched=Vec::new();
+         for (rule_index,&(origin,single_rule)) in rules.iter().enumerate(){
+             let specificity=single_rule.selectors.iter()
+                 .filter(|selector| selector_matches(node,ancestors,selector))
+                 .map(|selector| selector.specificity())
+                 .max();
+             let specificity=match specificity{
+                 Some(s)=>s,
+                 None=>continue,
+             };
+             for (declaration_index,declaration) in single_rule.declarations.iter().enumerate(){
+                 let key=CascadeKey{
+                     precedence:precedence(origin,declaration.important),
+                     inline:false,
+                     specificity,
+                     order:(rule_index,declaration_index),
+                 };
+                 matched.push((key,origin,declaration));
+             }
+         }
+         for (declaration_index,declaration) in inline.iter().enumerate(){
+             let key=CascadeKey{
+                 precedence:precedence(Origin::Author,declaration.important),
+                 inline:true,
+                 specificity:(0,0,0),
+                 order:(rules.len(),declaration_index),
+             };
+             matched.push((key,Origin::Author,declaration));
+         }
+         matched.sort_by(|a,b| a.0.cmp(&b.0));
+
+         let mut styles=SpecifiedMap::new();
+         let mut reverted=HashMap::<&str,Origin>::new();
+         for (_,origin,declaration) in matched.into_iter().rev(){
+             let name=declaration.key.as_str();
+             if styles.contains_key(name)||reverted.get(name).map_or(false,|r| origin>=*r){
+                 continue;
+             }
+             match declaration.value{
+                 Value::Keyword("revert")=>{reverted.insert(name,origin);},
+                 ref value=>{styles.insert(name,value);}
              }
          }
          styles
     }
+    //Custom properties inherit from the parent.A var() that can not be substituted makes
+    //the declaration invalid at computed-value time,the property is then left unset.
+    //Every registered property gets a value,see compute_defaults.Also gives the metrics of the element's font.
+    fn compute_styles(specified:SpecifiedMap,parent:Option<&ComputedStyle>,lengths:&LengthContext,fonts:&FontMetricsSource)->(ComputedStyle,Option<FontMetrics>){
+        let inherited=parent.map_or_else(|| Arc::new(CustomProperties::new()),|p| p.custom_properties.clone());
+        let custom=specified.iter().filter_map(|(name,value)| match **value{
+            Value::Custom(ref c)=>Some((*name,c.as_str())),
+            _=>None
+        }).collect::<Vec<(&str,&str)>>();
+        let custom_properties=match custom.len(){
+            0=>inherited,
+            _=>Arc::new(compute_custom_properties(&inherited,&custom))
+        };
+
+        let mut cascaded=vec![None;PROPERTIES.len()];
+        for (name,value) in specified{
+            let id=match property_id(name){
+                Some(id)=>id,
+                None=>continue,
+            };
+            cascaded[id.index()]=match *value{
+                Value::Var(ref var)=>resolve_var(name,var,&custom_properties),
+                _=>Some(value.clone())
+            };
+        }
+        //The font is chosen by properties that are not lengths,so it is known before they are computed
+        let mut style=ComputedStyle::new(compute_defaults(cascaded,parent),custom_properties);
+        let font=fonts(&style);
+        let mut values=mem::take(&mut style.values);
+        compute_lengths(&mut values,&LengthContext{font,..*lengths});
+        (ComputedStyle::new(values,style.custom_properties),font)
+    }
  
-    pub fn get_value(&self,name:&str)->Option<&&Value>{
-        self.styles.get(name)
+    pub fn get_value(&self,name:&str)->Option<&Value>{
+        self.style.get_by_name(name)
+    }
+    //Overrides a computed value,used for the values of running animations.
+    //Relative lengths in value are resolved like those of the stylesheet.
+    pub fn set_value(&mut self,name:&str,value:Value){
+        let id=match property_id(name){
+            Some(id)=>id,
+            None=>return,
+        };
+        let font_size=self.style.font_size;
+        let computed=match name{
+            "font-size"=>compute_font_size(&value,&self.lengths),
+            _=>resolve_lengths(&value,&|n,unit| length_to_px(n,unit,font_size,self.lengths.font,&self.lengths)),
+        };
+        Arc::make_mut(&mut self.style).set(id,computed.unwrap_or(value));
+    }
+    pub fn get_custom_property(&self,name:&str)->Option<&String>{
+        self.style.custom_property(name)
     }
     pub fn get_display_value(&self)->Display{
-        match self.get_value("display"){
-            Some(s)=>match **s{
-                Value::Other(ref v)=>match v.as_ref(){
-                    "block"=>Display::Block,
-                    "none"=>Display::None,
-                    "inline-block"=>Display::InlineBlock,
-                    _=>Display::Inline
-                },
-                _=>Display::Inline
-            }
-            None=>Display::Inline
-        } 
+        self.style.display
     }
 
+    //Lengths are px once computed,percentages depend on layout and give default
     pub fn num_or(&self,name:&str,default:f32)->f32{
         match self.get_value(name){
-            Some(v)=>match **v{
-                Value::Length(n,_)=>n,
-                _=>default,
-            }
-            None=>default
+            Some(&Value::Length(n,Unit::Px))=>n,
+            _=>default,
         }
     }
   
    
 }
-impl <'a> fmt::Debug for StyleNode<'a>{
+impl fmt::Debug for ComputedStyle{
+fn fmt(&self,f:&mut fmt::Formatter)->fmt::Result{
+    f.debug_map().entries(PROPERTIES.iter().map(|p| p.name).zip(self.values.iter())).finish()
+}
+}
+impl fmt::Debug for StyleNode{
 fn fmt(&self,f:&mut fmt::Formatter)->fmt::Result{
-    write!(f,"{:?}{:?}",self.node,self.styles)
+    write!(f,"#{}{:?}",self.node_id,self.style)
 }
 }
 pub fn pretty_print(node: &StyleNode,indent_size:usize){
@@ -93,34 +380,561 @@ pub fn pretty_print(node: &StyleNode,indent_size:usize){
 
 }
 
-fn selector_matches(element:&ElementData,selector:&Selector)->bool{
-    for simple in &selector.simple{
-        let mut selector_match=true;
+//Declarations of the style attribute.It is parsed on every restyle,so changes made to the
+//attribute through the DOM show up the next time the tree is built.
+fn inline_style(node:&Node)->Vec<Declaration>{
+    match node.node_type{
+        NodeType::Element(ref element)=>element.attributes.get("style").map_or(Vec::new(),|css| parse_declaration_list(css)),
+        _=>Vec::new()
+    }
+}
+
+//None for elements whose style can not be shared,those with a style attribute
+fn sharing_key<'a>(node:&'a Node,ancestors:&[&Node],cascade:&Cascade)->Option<SharingKey<'a>>{
+    let element=match node.node_type{
+        NodeType::Element(ref e)=>e,
+        _=>return None,
+    };
+    if element.attributes.contains_key("style"){
+        return None;
+    }
+    let mut classes=element.getClasses().into_iter().collect::<Vec<&str>>();
+    classes.sort_unstable();
+    let structural=cascade.structural.iter().cloned()
+        .filter(|&i| cascade.rules[i].1.selectors.iter().any(|s| selector_matches(node,ancestors,s)))
+        .collect();
+    Some(SharingKey{
+        namespace:&element.namespace,
+        tag_name:&element.tag_name,
+        id:element.getId().map(|id| id.as_str()),
+        classes,
+        structural,
+    })
+}
+
+//Whether selector uses a sibling combinator,:has() or a tree-structural pseudo-class,so two
+//elements that only differ in their position or children can match it differently
+fn is_structural(selector:&Selector)->bool{
+    selector.combinators.iter().any(|&c| c=='+'||c=='~')||selector.simple.iter().any(|simple|
+        simple.pseudo_classes.iter().any(|pseudo| match *pseudo{
+            PseudoClass::Has(_)|PseudoClass::Empty|PseudoClass::Nth{..}|PseudoClass::Only{..}=>true,
+            PseudoClass::Not(ref list)|PseudoClass::Is(ref list)|PseudoClass::Where(ref list)=>list.iter().any(is_structural),
+            PseudoClass::Root|PseudoClass::State(_)=>false,
+        }))
+}
+
+//Normal user-agent,user and author declarations,followed by the author,user and
+//user-agent !important ones
+fn precedence(origin:Origin,important:bool)->u8{
+    match important{
+        false=>origin as u8,
+        true=>5-origin as u8,
+    }
+}
+
+//Id of a property the style tree reads itself,they are all in the registry
+fn id(name:&str)->PropertyId{
+    property_id(name).expect("property is registered")
+}
 
-        match simple.tag_name{
-            Some(ref t)=>
-            if *t != element.tag_name{
-                continue;
+//Gives a value to the properties without a cascaded value and resolves the CSS-wide keywords:
+//inherited properties take the parent's value,the others their initial value
+fn compute_defaults(cascaded:Vec<Option<Value>>,parent:Option<&ComputedStyle>)->Vec<Value>{
+    let mut values=cascaded.into_iter().zip(PROPERTIES).enumerate().map(|(i,(value,property))|{
+        let keyword=match value{
+            Some(Value::Keyword(k)) if CSS_WIDE_KEYWORDS.contains(&k)=>k,
+            //currentcolor is the parent's color when it is the value of color itself
+            Some(Value::Keyword("currentcolor")) if property.name=="color"=>"inherit",
+            Some(v)=>return v,
+            None=>"unset",
+        };
+        let inherit=match keyword{
+            "inherit"=>true,
+            "initial"=>false,
+            _=>property.inherited,
+        };
+        match parent{
+            Some(p) if inherit=>p.values[i].clone(),
+            _=>initial_value(property.name).cloned().unwrap_or_else(|| Value::Other(property.initial.to_string())),
+        }
+    }).collect::<Vec<Value>>();
+
+    //A border without a style has no width
+    for &(style,width) in BORDER_SIDES{
+        match values[id(style).index()]{
+            Value::Keyword("none")|Value::Keyword("hidden")=>{
+                values[id(width).index()]=Value::Length(0.0,Unit::Px);
             },
-            None=>{}
+            _=>{}
+        }
+    }
+    values
+}
+const MARGINS:[&str;4]=["margin-top","margin-right","margin-bottom","margin-left"];
+const PADDINGS:[&str;4]=["padding-top","padding-right","padding-bottom","padding-left"];
+const BORDER_WIDTHS:[&str;4]=["border-top-width","border-right-width","border-bottom-width","border-left-width"];
+const BORDER_COLORS:[&str;4]=["border-top-color","border-right-color","border-bottom-color","border-left-color"];
+const BORDER_SIDES:&[(&str,&str)]=&[
+    ("border-top-style","border-top-width"),
+    ("border-right-style","border-right-width"),
+    ("border-bottom-style","border-bottom-width"),
+    ("border-left-style","border-left-width"),
+];
+
+//font-size:medium,what the root element's relative lengths refer to
+const DEFAULT_FONT_SIZE:f32=16.0;
+
+//Turns the lengths that do not depend on layout into px.font-size goes first,
+//as it is what em in the other properties refers to.Percentages are left to layout.
+fn compute_lengths(values:&mut [Value],lengths:&LengthContext){
+    let font_size_id=id("font-size").index();
+    if let Some(value)=compute_font_size(&values[font_size_id],lengths){
+        values[font_size_id]=value;
+    }
+
+    let font_size=match values[font_size_id]{
+        Value::Length(n,Unit::Px)=>n,
+        _=>lengths.parent_font_size,
+    };
+    for (i,value) in values.iter_mut().enumerate(){
+        if i==font_size_id{
+            continue;
+        }
+        if let Some(v)=resolve_lengths(value,&|n,unit| length_to_px(n,unit,font_size,lengths.font,lengths)){
+            *value=v;
+        }
+    }
+}
+
+//Percentages in font-size refer to the parent's font size
+fn compute_font_size(value:&Value,lengths:&LengthContext)->Option<Value>{
+    let parent=lengths.parent_font_size;
+    resolve_lengths(value,&|n,unit| match unit{
+        Unit::Percent=>Some(n*parent/100.0),
+        _=>length_to_px(n,unit,parent,lengths.parent_font,lengths),
+    })
+}
+
+//None when value holds nothing to resolve
+fn resolve_lengths(value:&Value,to_px:&dyn Fn(f32,Unit)->Option<f32>)->Option<Value>{
+    match *value{
+        Value::Length(_,Unit::Px)=>None,
+        Value::Length(n,unit)=>to_px(n,unit).map(|px| Value::Length(px,Unit::Px)),
+        Value::Math(ref expression)=>{
+            let expression=expression.resolve_units(to_px);
+            if expression.is_absolute(){
+                Some(Value::Length(expression.evaluate(&|n,_| n),Unit::Px))
+            }else{
+                Some(Value::Math(expression))
+            }
+        },
+        _=>None,
+    }
+}
+
+//None for percentages.ex and ch come from the metrics of font,without them they are taken as
+//half an em,the fallback CSS Values gives for fonts that do not provide them.
+fn length_to_px(n:f32,unit:Unit,font_size:f32,font:Option<FontMetrics>,lengths:&LengthContext)->Option<f32>{
+    let (width,height)=lengths.viewport;
+    let px=match unit{
+        Unit::Px=>n,
+        Unit::Em=>n*font_size,
+        Unit::Ex=>n*font_size*font.and_then(|f| f.x_height).unwrap_or(0.5),
+        Unit::Ch=>n*font_size*font.and_then(|f| f.zero_advance).unwrap_or(0.5),
+        Unit::Rem=>n*lengths.root_font_size,
+        Unit::Vw=>n*width/100.0,
+        Unit::Vh=>n*height/100.0,
+        Unit::Vmin=>n*width.min(height)/100.0,
+        Unit::Vmax=>n*width.max(height)/100.0,
+        Unit::In=>n*96.0,
+        Unit::Cm=>n*96.0/2.54,
+        Unit::Mm=>n*96.0/25.4,
+        Unit::Q=>n*96.0/101.6,
+        Unit::Pt=>n*96.0/72.0,
+        Unit::Pc=>n*16.0,
+        Unit::Percent=>return None,
+    };
+    Some(px)
+}
+
+//Substitutes the references of a var() value and parses the result for property name
+fn resolve_var(name:&str,var:&VarValue,custom_properties:&CustomProperties)->Option<Value>{
+    let css=fold_case(&substitute_vars(&var.css,&mut |n| custom_properties.get(n).cloned())?);
+    let property=var.shorthand.as_ref().map_or(name,|s| s.as_str());
+    parse_property_value(property,&css).into_iter()
+        .find(|&(ref longhand,_)| longhand==name)
+        .map(|(_,value)| value)
+}
+
+//Matches a complex selector right to left,ancestors run from the root down to the parent of node
+fn selector_matches(node:&Node,ancestors:&[&Node],selector:&Selector)->bool{
+    match selector.simple.len(){
+        0=>false,
+        n=>matches_from(node,ancestors,selector,n-1,None)
+    }
+}
+
+fn matches_from(node:&Node,ancestors:&[&Node],selector:&Selector,index:usize,anchor:Option<(&Node,char)>)->bool{
+    if !compound_matches(node,ancestors,&selector.simple[index]){
+        return false;
+    }
+    if index==0{
+        return match anchor{
+            Some((a,combinator))=>is_related(a,combinator,node,ancestors),
+            None=>true,
+        };
+    }
+    match selector.combinators.get(index-1).cloned().unwrap_or(' '){
+        '>'=>match ancestors.split_last(){
+            Some((parent,rest))=>matches_from(parent,rest,selector,index-1,anchor),
+            None=>false,
+        },
+        '+'=>match previous_siblings(node,ancestors).last(){
+            Some(sibling)=>matches_from(sibling,ancestors,selector,index-1,anchor),
+            None=>false,
+        },
+        '~'=>previous_siblings(node,ancestors).iter().any(|sibling| matches_from(sibling,ancestors,selector,index-1,anchor)),
+        _=>(0..ancestors.len()).rev().any(|i| matches_from(ancestors[i],&ancestors[..i],selector,index-1,anchor)),
+    }
+}
+
+fn compound_matches(node:&Node,ancestors:&[&Node],simple:&SimpleSelector)->bool{
+    let element=match node.node_type{
+        NodeType::Element(ref e)=>e,
+        _=>return false,
+    };
+    match simple.namespace{
+        Some(ref n)=>if *n != element.namespace{
+            return false;
+        },
+        None=>{}
+    }
+    match simple.tag_name{
+        Some(ref t)=>
+        if *t != element.tag_name{
+            return false;
+        },
+        None=>{}
+    };
+    match simple.id{
+        Some(ref id)=>if element.getId()!=Some(id){
+            return false;
+        },
+        None=>{}
+    }
+    let element_classes=element.getClasses();
+
+    for class in &simple.classes{
+        if !element_classes.contains::<str>(class){
+            return false;
+        }
+    }
+    simple.pseudo_classes.iter().all(|pseudo| match *pseudo{
+        PseudoClass::Not(ref list)=>!list.iter().any(|s| selector_matches(node,ancestors,s)),
+        PseudoClass::Is(ref list)|PseudoClass::Where(ref list)=>list.iter().any(|s| selector_matches(node,ancestors,s)),
+        PseudoClass::Has(ref list)=>list.iter().any(|r| has_matches(node,ancestors,r)),
+        PseudoClass::Root=>ancestors.is_empty(),
+        PseudoClass::Empty=>node.children.iter().all(|c| matches!(c.node_type,NodeType::Comment(_))),
+        PseudoClass::Nth{a,b,of_type,from_end}=>nth_matches(a,b,sibling_index(node,ancestors,of_type,from_end)),
+        PseudoClass::Only{of_type}=>sibling_index(node,ancestors,of_type,false)==1&&sibling_index(node,ancestors,of_type,true)==1,
+        PseudoClass::State(_)=>false,
+    })
+}
+
+//1-based position of node among the element children of its parent,counted from the last one
+//when from_end.of_type only counts the elements with the same name.
+fn sibling_index(node:&Node,ancestors:&[&Node],of_type:bool,from_end:bool)->usize{
+    let parent=match ancestors.last(){
+        Some(p)=>p,
+        None=>return 1,
+    };
+    let own=match node.node_type{
+        NodeType::Element(ref e)=>e,
+        _=>return 1,
+    };
+    let same_kind=|n:&Node| match n.node_type{
+        NodeType::Element(ref e)=>!of_type||(e.tag_name==own.tag_name&&e.namespace==own.namespace),
+        _=>false,
+    };
+    let mut siblings=parent.children.iter().filter(|c| same_kind(c));
+    let position=match from_end{
+        true=>siblings.rev().position(|c| std::ptr::eq(c,node)),
+        false=>siblings.position(|c| std::ptr::eq(c,node)),
+    };
+    position.map_or(1,|p| p+1)
+}
+
+//Whether index is A*n+B for some n>=0
+fn nth_matches(a:i32,b:i32,index:usize)->bool{
+    let offset=index as i32-b;
+    match a{
+        0=>offset==0,
+        _=>offset%a==0&&offset/a>=0,
+    }
+}
+
+//:has() looks for an element,relative to the anchor,that the argument selector matches
+fn has_matches(anchor:&Node,ancestors:&[&Node],relative:&RelativeSelector)->bool{
+    let last=match relative.selector.simple.len(){
+        0=>return false,
+        n=>n-1,
+    };
+    let mut chain=ancestors.to_vec();
+    let mut found=false;
+    {
+        let mut check=|n:&Node,a:&[&Node]| {
+            found=found||matches_from(n,a,&relative.selector,last,Some((anchor,relative.combinator)));
         };
-        match element.getId(){
-            Some(i)=>match simple.id{
-                Some (ref id)=> if *i != *id {
-                    continue;
+        match relative.combinator{
+            '+'|'~'=>{
+                if let Some(parent)=ancestors.last(){
+                    let mut after=false;
+                    for sibling in &parent.children{
+                        if after{
+                            visit_elements(sibling,&mut chain,&mut check);
+                        }
+                        after=after||std::ptr::eq(sibling,anchor);
+                    }
                 }
-                _=>{}
-            },
-            None=>{},
+            }
+            _=>{
+                chain.push(anchor);
+                for child in &anchor.children{
+                    visit_elements(child,&mut chain,&mut check);
+                }
+            }
         }
-        let element_classes=element.getClasses();
+    }
+    found
+}
 
-        for class in &simple.classes{
-            selector_match&=element_classes.contains::<str>(class);
+fn visit_elements<'n,F>(node:&'n Node,ancestors:&mut Vec<&'n Node>,f:&mut F) where F:FnMut(&Node,&[&Node]){
+    if let NodeType::Element(_)=node.node_type{
+        f(node,ancestors);
+        ancestors.push(node);
+        for child in &node.children{
+            visit_elements(child,ancestors,f);
         }
-        if selector_match{
-            return true;
+        ancestors.pop();
+    }
+}
+
+//Element siblings that come before node,in document order
+fn previous_siblings<'n>(node:&Node,ancestors:&[&'n Node])->Vec<&'n Node>{
+    let mut siblings=Vec::new();
+    if let Some(parent)=ancestors.last(){
+        for child in &parent.children{
+            if std::ptr::eq(child,node){
+                break;
+            }
+            if let NodeType::Element(_)=child.node_type{
+                siblings.push(child);
+            }
         }
     }
-    false
-}
\ No newline at end of file
+    siblings
+}
+
+fn is_related(anchor:&Node,combinator:char,node:&Node,ancestors:&[&Node])->bool{
+    match combinator{
+        '>'=>ancestors.last().map_or(false,|p| std::ptr::eq(*p,anchor)),
+        '+'=>previous_siblings(node,ancestors).last().map_or(false,|s| std::ptr::eq(*s,anchor)),
+        '~'=>previous_siblings(node,ancestors).iter().any(|s| std::ptr::eq(*s,anchor)),
+        _=>ancestors.iter().any(|a| std::ptr::eq(*a,anchor)),
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    use crate::cssparser::cssparser;
+    use crate::htmlparser::HtmlParser;
+
+    fn style(html:&str,css:&str)->StyleNode{
+        let document=HtmlParser::new(html).parse_nodes().remove(0);
+        let stylesheet=cssparser::new(css).parse_stylesheet();
+        let mut viewport=Dimensions::default();
+        viewport.coordinates.width=800.0;
+        viewport.coordinates.height=600.0;
+        StyleNode::new(&document,&[&stylesheet],viewport)
+    }
+
+    //Width of every element in preorder,0 for those the stylesheet leaves auto
+    fn widths(html:&str,css:&str)->Vec<f32>{
+        fn walk(node:&StyleNode,result:&mut Vec<f32>){
+            result.push(node.num_or("width",0.0));
+            for child in &node.children{
+                walk(child,result);
+            }
+        }
+        let mut result=Vec::new();
+        walk(&style(html,css),&mut result);
+        result
+    }
+
+    fn specificity(selector:&str)->Specificity{
+        let stylesheet=cssparser::new(&format!("{}{{width:1px}}",selector)).parse_stylesheet();
+        let device=Device::new(800.0,600.0);
+        let rules=stylesheet.rules_for_device(&device);
+        rules[0].selectors[0].specificity()
+    }
+
+    #[test]
+    fn combinators_match_their_relation(){
+        let html="<html><div><p></p><span></span><em></em></div></html>";
+        assert_eq!(widths(html,"div p{width:1px}"),vec![0.0,0.0,1.0,0.0,0.0]);
+        assert_eq!(widths(html,"html>p{width:1px} div>span{width:2px}"),vec![0.0,0.0,0.0,2.0,0.0]);
+        assert_eq!(widths(html,"p+span{width:1px} p+em{width:2px}"),vec![0.0,0.0,0.0,1.0,0.0]);
+        assert_eq!(widths(html,"p~em{width:1px}"),vec![0.0,0.0,0.0,0.0,1.0]);
+    }
+
+    #[test]
+    fn compound_selectors_need_every_part(){
+        let html="<html><div id=\"a\" class=\"b c\"></div><div class=\"b\"></div></html>";
+        assert_eq!(widths(html,"div.b.c{width:1px}"),vec![0.0,1.0,0.0]);
+        assert_eq!(widths(html,"#a.b{width:1px} span.b{width:2px}"),vec![0.0,1.0,0.0]);
+        assert_eq!(widths(html,"*{width:1px}"),vec![1.0,1.0,1.0]);
+    }
+
+    #[test]
+    fn logical_pseudo_classes(){
+        let html="<html><div><p></p></div><div class=\"x\"><span></span></div></html>";
+        assert_eq!(widths(html,"div:has(> p){width:1px}"),vec![0.0,1.0,0.0,0.0,0.0]);
+        assert_eq!(widths(html,":is(p,span){width:1px}"),vec![0.0,0.0,1.0,0.0,1.0]);
+        assert_eq!(widths(html,"div:not(.x){width:1px}"),vec![0.0,1.0,0.0,0.0,0.0]);
+        assert_eq!(widths(html,":where(.x) span{width:1px}"),vec![0.0,0.0,0.0,0.0,1.0]);
+    }
+
+    #[test]
+    fn specificity_counts_ids_classes_and_types(){
+        assert_eq!(specificity("#a .b p"),(1,1,1));
+        assert_eq!(specificity("div.b.c:first-child"),(0,3,1));
+        assert_eq!(specificity(":where(#a) p"),(0,0,1));
+        assert_eq!(specificity(":is(#a,p).b"),(1,1,0));
+        assert_eq!(specificity(":not(.x,span)"),(0,1,0));
+        assert_eq!(specificity("div:has(> #a)"),(1,0,1));
+    }
+
+    #[test]
+    fn more_specific_rule_wins_over_later_rule(){
+        let html="<html><div id=\"a\" class=\"b c\"></div></html>";
+        assert_eq!(widths(html,"#a{width:1px} .b.c{width:2px} div{width:3px}")[1],1.0);
+        assert_eq!(widths(html,".b.c{width:1px} .c{width:2px}")[1],1.0);
+        assert_eq!(widths(html,".b{width:1px} .c{width:2px}")[1],2.0);
+    }
+
+    #[test]
+    fn invalid_selector_drops_the_whole_rule(){
+        let html="<html><div></div></html>";
+        assert_eq!(widths(html,"div,p:bogus{width:1px} html{width:2px}"),vec![2.0,0.0]);
+        assert_eq!(widths(html,"div,{width:1px}"),vec![0.0,0.0]);
+        assert_eq!(widths(html,"div,p{width:1px}"),vec![0.0,1.0]);
+    }
+
+    #[test]
+    fn important_declarations_win_over_specificity(){
+        let html="<html><div id=\"a\"></div></html>";
+        assert_eq!(widths(html,"#a{width:1px} div{width:2px !important}")[1],2.0);
+        assert_eq!(widths(html,"#a{width:1px !important} div{width:2px !important}")[1],1.0);
+        assert_eq!(widths(html,"div{width:1px !important} div{width:2px}")[1],1.0);
+    }
+
+    #[test]
+    fn later_declarations_win_ties(){
+        let html="<html><div class=\"b\"></div></html>";
+        assert_eq!(widths(html,"div{width:1px;width:2px}")[1],2.0);
+        assert_eq!(widths(html,"div{width:1px !important;width:2px}")[1],1.0);
+        assert_eq!(widths(html,".b{width:1px} div.b{width:2px} div.b{width:3px}")[1],3.0);
+        assert_eq!(widths(html,"div{width:1px} .b{width:2px} div{width:3px}")[1],2.0);
+    }
+
+    //Width of the div styled by one sheet of each origin,in cascade order
+    fn origin_width(user_agent:&str,user:&str,author:&str)->f32{
+        let document=HtmlParser::new("<html><div></div></html>").parse_nodes().remove(0);
+        let sheets=[(user_agent,Origin::UserAgent),(user,Origin::User),(author,Origin::Author)].iter()
+            .map(|&(css,origin)|{
+                let mut stylesheet=cssparser::new(css).parse_stylesheet();
+                stylesheet.origin=origin;
+                stylesheet
+            })
+            .collect::<Vec<Stylesheet>>();
+        let mut viewport=Dimensions::default();
+        viewport.coordinates.width=800.0;
+        let root=StyleNode::new(&document,&sheets.iter().collect::<Vec<_>>(),viewport);
+        root.children[0].num_or("width",0.0)
+    }
+
+    #[test]
+    fn origins_cascade_in_order(){
+        assert_eq!(origin_width("#x,div{width:1px}","div{width:2px}","div{width:3px}"),3.0);
+        assert_eq!(origin_width("div{width:1px}","div{width:2px}","*{width:3px}"),3.0);
+        assert_eq!(origin_width("div{width:1px}","div{width:2px}",""),2.0);
+        assert_eq!(origin_width("div{width:1px}","",""),1.0);
+    }
+
+    #[test]
+    fn important_declarations_reverse_the_origins(){
+        assert_eq!(origin_width("div{width:1px !important}","div{width:2px !important}","div{width:3px !important}"),1.0);
+        assert_eq!(origin_width("","*{width:2px !important}","#x,div{width:3px !important}"),2.0);
+        assert_eq!(origin_width("div{width:1px}","div{width:2px !important}","div{width:3px}"),2.0);
+    }
+
+    #[test]
+    fn user_agent_sheet_provides_defaults(){
+        let document=HtmlParser::new("<html><head></head><body><p></p><span></span></body></html>").parse_nodes().remove(0);
+        let user_agent=crate::cssuseragent::user_agent_stylesheet();
+        let root=StyleNode::new(&document,&[&user_agent],Dimensions::default());
+        let body=&root.children[1];
+        assert_eq!(root.get_display_value(),Display::Block);
+        assert_eq!(root.children[0].get_display_value(),Display::None);
+        assert_eq!(body.get_display_value(),Display::Block);
+        assert_eq!(body.children[0].get_display_value(),Display::Block);
+        assert_eq!(body.children[1].get_display_value(),Display::Inline);
+        assert_eq!(body.num_or("margin-top",0.0),8.0);
+    }
+
+    #[test]
+    fn siblings_with_the_same_key_share_their_style(){
+        let html="<html><p class=\"a b\"></p><p class=\"b a\"></p><p class=\"a\"></p><p id=\"x\" class=\"a b\"></p></html>";
+        let root=style(html,".a{width:1px} .b{width:2px} #x{width:3px}");
+        let p=&root.children;
+        assert!(Arc::ptr_eq(&p[0].style,&p[1].style));
+        assert!(!Arc::ptr_eq(&p[0].style,&p[2].style));
+        assert!(!Arc::ptr_eq(&p[0].style,&p[3].style));
+        assert_eq!(p.iter().map(|n| n.num_or("width",0.0)).collect::<Vec<f32>>(),vec![2.0,2.0,1.0,3.0]);
+    }
+
+    #[test]
+    fn structural_rules_are_matched_for_every_sibling(){
+        let html="<html><p></p><p></p><p></p></html>";
+        assert_eq!(widths(html,"p:first-child{width:1px}"),vec![0.0,1.0,0.0,0.0]);
+        assert_eq!(widths(html,"p+p{width:1px}"),vec![0.0,0.0,1.0,1.0]);
+        assert_eq!(widths(html,"p:nth-child(2n+1){width:1px}"),vec![0.0,1.0,0.0,1.0]);
+        let root=style(html,"p+p{width:1px}");
+        assert!(Arc::ptr_eq(&root.children[1].style,&root.children[2].style));
+    }
+
+    #[test]
+    fn has_rules_see_the_children_of_each_sibling(){
+        let html="<html><div><p></p></div><div></div><div><p></p></div></html>";
+        assert_eq!(widths(html,"div:has(p){width:1px}"),vec![0.0,1.0,0.0,0.0,1.0,0.0]);
+    }
+
+    #[test]
+    fn style_attribute_prevents_sharing(){
+        let html="<html><p></p><p style=\"width:2px\"></p><p></p></html>";
+        let root=style(html,"p{width:1px}");
+        let p=&root.children;
+        assert!(!Arc::ptr_eq(&p[0].style,&p[1].style));
+        assert!(Arc::ptr_eq(&p[0].style,&p[2].style));
+        assert_eq!(p.iter().map(|n| n.num_or("width",0.0)).collect::<Vec<f32>>(),vec![1.0,2.0,1.0]);
+    }
+
+    #[test]
+    fn setting_a_value_does_not_change_the_shared_style(){
+        let mut root=style("<html><p></p><p></p></html>","p{width:1px}");
+        root.children[0].set_value("width",Value::Length(5.0,Unit::Px));
+        assert_eq!(root.children[0].num_or("width",0.0),5.0);
+        assert_eq!(root.children[1].num_or("width",0.0),1.0);
+    }
+}